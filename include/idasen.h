@@ -0,0 +1,39 @@
+/* C interface of the idasen crate, built with the `cdylib` feature. */
+
+#ifndef IDASEN_H
+#define IDASEN_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct IdasenDesk IdasenDesk;
+
+/* Position in tenth of millimetres, speed and the registered user data. */
+typedef void (*IdasenPositionCallback)(uint16_t position, int16_t speed, void *user_data);
+
+/* Message of the last failure on the calling thread, or NULL. */
+const char *idasen_last_error(void);
+
+/* Connect to the desk with the given MAC address, or to any desk if `mac` is NULL. */
+IdasenDesk *idasen_connect(const char *mac);
+void idasen_free(IdasenDesk *desk);
+
+/* The functions below return 0 on success and -1 on failure. */
+int idasen_position(const IdasenDesk *desk, uint16_t *position);
+int idasen_move_to(const IdasenDesk *desk, uint16_t position);
+int idasen_up(const IdasenDesk *desk);
+int idasen_down(const IdasenDesk *desk);
+int idasen_stop(const IdasenDesk *desk);
+
+/* Called from a background thread on every position change, NULL unregisters it. */
+int idasen_set_position_callback(const IdasenDesk *desk, IdasenPositionCallback callback,
+                                 void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,312 @@
+use crate::{BDAddr, Config, DeskCommand, DeskTransport, Error, Idasen};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Who sent a command and through which interface, e.g. the user of a command line tool or
+/// the client of an IPC socket.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Origin {
+    pub client: String,
+    /// E.g. `cli`, `ipc`, `ffi` or `schedule`.
+    pub interface: String,
+}
+
+impl Origin {
+    pub fn new(client: impl Into<String>, interface: impl Into<String>) -> Self {
+        Self {
+            client: client.into(),
+            interface: interface.into(),
+        }
+    }
+}
+
+/// A command run on a desk, recorded in an `AuditLog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditEntry {
+    /// When the command was received, to the millisecond.
+    pub at: SystemTime,
+    #[cfg_attr(feature = "serde", serde(with = "crate::handle::address_string"))]
+    pub desk: BDAddr,
+    pub origin: Origin,
+    pub command: DeskCommand,
+    /// Position once the command was done, `None` if it failed.
+    pub position: Option<u16>,
+    pub error: Option<String>,
+}
+
+/// Append-only record of the commands run on desks, to answer "who moved my desk?" on shared
+/// desks.
+///
+/// Opened on a file, each entry is appended as a line as soon as it is recorded and queries
+/// read the file, so the log survives restarts and doesn't grow in memory. A line holds the
+/// tab separated time in milliseconds since the Unix epoch, desk address, interface, client,
+/// command and outcome, either `ok` and the position or `error` and the message.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, AuditLog, AuditQuery, Config, DeskCommand, Idasen, Origin};
+///
+/// let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+/// let log = AuditLog::new();
+/// let origin = Origin::new("alice", "cli");
+/// desk.execute_audited(DeskCommand::MoveTo(9000), &Config::default(), &log, &origin)
+///     .await
+///     .unwrap();
+///
+/// let entries = log.query(&AuditQuery::new().client("alice")).unwrap();
+/// assert_eq!(entries[0].command, DeskCommand::MoveTo(9000));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct AuditLog {
+    backend: Mutex<Backend>,
+}
+
+#[derive(Debug)]
+enum Backend {
+    Memory(Vec<AuditEntry>),
+    File { path: PathBuf, file: File },
+}
+
+impl AuditLog {
+    /// Log kept in memory only.
+    pub fn new() -> Self {
+        Self {
+            backend: Mutex::new(Backend::Memory(Vec::new())),
+        }
+    }
+
+    /// Log appending to the file at `path`, created if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            backend: Mutex::new(Backend::File { path, file }),
+        })
+    }
+
+    pub fn record(&self, entry: AuditEntry) -> io::Result<()> {
+        match &mut *self.backend.lock().unwrap() {
+            Backend::Memory(entries) => entries.push(entry),
+            Backend::File { file, .. } => writeln!(file, "{}", entry)?,
+        }
+        Ok(())
+    }
+
+    /// Entries matching `query`, oldest first.
+    pub fn query(&self, query: &AuditQuery) -> io::Result<Vec<AuditEntry>> {
+        let entries = match &*self.backend.lock().unwrap() {
+            Backend::Memory(entries) => entries.clone(),
+            Backend::File { path, .. } => fs::read_to_string(path)?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(entries
+            .into_iter()
+            .filter(|entry| query.matches(entry))
+            .collect())
+    }
+
+    /// Write the entries matching `query` as CSV with a header line, for spreadsheets.
+    pub fn export_csv(&self, query: &AuditQuery, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "time,desk,interface,client,command,position,error")?;
+        for entry in self.query(query)? {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                millis_since_epoch(entry.at),
+                entry.desk,
+                csv_field(&entry.origin.interface),
+                csv_field(&entry.origin.client),
+                csv_field(&entry.command.to_string()),
+                entry
+                    .position
+                    .map(|position| position.to_string())
+                    .unwrap_or_default(),
+                csv_field(entry.error.as_deref().unwrap_or_default()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which entries `AuditLog::query` returns; all of them unless narrowed down.
+///
+/// ```
+/// use idasen::AuditQuery;
+/// use std::time::{Duration, SystemTime};
+///
+/// let last_hour = AuditQuery::new()
+///     .since(SystemTime::now() - Duration::from_secs(3600))
+///     .moves_only();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+    desk: Option<BDAddr>,
+    client: Option<String>,
+    interface: Option<String>,
+    moves_only: bool,
+}
+
+impl AuditQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only entries recorded at or after `time`.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Only entries recorded before `time`.
+    pub fn until(mut self, time: SystemTime) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    pub fn desk(mut self, desk: BDAddr) -> Self {
+        self.desk = Some(desk);
+        self
+    }
+
+    pub fn client(mut self, client: impl Into<String>) -> Self {
+        self.client = Some(client.into());
+        self
+    }
+
+    pub fn interface(mut self, interface: impl Into<String>) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+
+    /// Leave out commands that can't move the desk, see `DeskCommand::moves`.
+    pub fn moves_only(mut self) -> Self {
+        self.moves_only = true;
+        self
+    }
+
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let mismatch = matches!(self.since, Some(since) if entry.at < since)
+            || matches!(self.until, Some(until) if entry.at >= until)
+            || matches!(self.desk, Some(desk) if entry.desk != desk)
+            || matches!(&self.client, Some(client) if entry.origin.client != *client)
+            || matches!(&self.interface, Some(interface) if entry.origin.interface != *interface)
+            || (self.moves_only && !entry.command.moves());
+        !mismatch
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Like `execute`, recording the command, who sent it and its outcome in `log`.
+    ///
+    /// Fails with `Error::CannotWriteAuditLog` if the entry can't be written, even though the
+    /// command was run.
+    pub async fn execute_audited(
+        &self,
+        command: DeskCommand,
+        config: &Config,
+        log: &AuditLog,
+        origin: &Origin,
+    ) -> Result<u16, Error> {
+        let at = SystemTime::now();
+        let result = self.execute(command.clone(), config).await;
+        log.record(AuditEntry {
+            at,
+            desk: self.mac_addr,
+            origin: origin.clone(),
+            command,
+            position: result.as_ref().ok().copied(),
+            error: result.as_ref().err().map(ToString::to_string),
+        })
+        .map_err(|_| Error::CannotWriteAuditLog)?;
+        result
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t",
+            millis_since_epoch(self.at),
+            self.desk,
+            one_field(&self.origin.interface),
+            one_field(&self.origin.client),
+            self.command,
+        )?;
+        match (self.position, &self.error) {
+            (Some(position), _) => write!(f, "ok {}", position),
+            (None, error) => write!(
+                f,
+                "error {}",
+                one_field(error.as_deref().unwrap_or_default())
+            ),
+        }
+    }
+}
+
+impl FromStr for AuditEntry {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid audit log entry");
+        let fields: Vec<_> = s.split('\t').collect();
+        let (at, desk, interface, client, command, outcome) = match fields[..] {
+            [at, desk, interface, client, command, outcome] => {
+                (at, desk, interface, client, command, outcome)
+            }
+            _ => return Err(invalid()),
+        };
+        let (position, error) = match outcome.split_once(' ').unwrap_or((outcome, "")) {
+            ("ok", position) => (Some(position.parse().map_err(|_| invalid())?), None),
+            ("error", message) => (None, Some(message.to_string())),
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            at: UNIX_EPOCH + Duration::from_millis(at.parse().map_err(|_| invalid())?),
+            desk: desk.parse().map_err(|_| invalid())?,
+            origin: Origin::new(client, interface),
+            command: command.parse().map_err(|_| invalid())?,
+            position,
+            error,
+        })
+    }
+}
+
+// Tabs and line breaks would end the field or the entry.
+fn one_field(value: &str) -> String {
+    value.replace(&['\t', '\n', '\r'][..], " ")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn millis_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,97 @@
+//! Command line tool controlling the desk, built with the `cli` feature.
+
+use idasen::blocking::{get_instance_from_config, Idasen};
+use idasen::{discover, BleDesk, Config, DeskCommand, Device, Height};
+use std::env;
+use std::error::Error;
+use std::process;
+
+const USAGE: &str = "\
+Usage: idasen [--mac <address>] <command>
+
+Commands:
+    scan                List desks nearby
+    status              Show the desk's height
+    up, down, stop      Nudge the desk up or down, or stop it
+    to <height>         Move to a height in the configured unit, e.g. `to 74.5`
+    preset list         List the saved presets
+    preset save <name>  Save the current height as a preset
+    preset load <name>  Move to a preset
+
+The desk address, height limits and presets are read from the config file, see
+`Config::default_path`.";
+
+fn main() {
+    if let Err(error) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut config = match Config::default_path() {
+        Some(path) => Config::load(path)?.unwrap_or_default(),
+        None => Config::default(),
+    };
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let ["--mac", mac, ..] = args[..] {
+        config.address = Some(mac.parse()?);
+        args.drain(..2);
+    }
+
+    match args[..] {
+        ["scan"] => scan()?,
+        ["status"] => {
+            let position = connect(&config)?.position()?;
+            println!("{}", Height(position).display(config.unit, 1));
+        }
+        ["up"] => connect(&config)?.up()?,
+        ["down"] => connect(&config)?.down()?,
+        ["stop"] => connect(&config)?.stop()?,
+        ["to", height] => {
+            let value = height
+                .parse()
+                .map_err(|_| format!("Invalid height '{}'.", height))?;
+            let target = Height::from_unit(value, config.unit);
+            connect(&config)?.execute(DeskCommand::MoveTo(target.into()), &config)?;
+        }
+        ["preset", "list"] => {
+            for (name, position) in &config.presets {
+                println!("{}: {}", name, Height(*position).display(config.unit, 1));
+            }
+        }
+        ["preset", "save", name] => {
+            let path = Config::default_path().ok_or("Cannot find the config directory.")?;
+            let position = connect(&config)?.position()?;
+            match config.presets.iter_mut().find(|(preset, _)| preset == name) {
+                Some(preset) => preset.1 = position,
+                None => config.presets.push((name.to_string(), position)),
+            }
+            config.save(path)?;
+        }
+        ["preset", "load", name] => connect(&config)?.move_to_preset(name, &config)?,
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+fn connect(config: &Config) -> Result<Idasen<BleDesk<impl Device>>, idasen::Error> {
+    get_instance_from_config(config)
+}
+
+fn scan() -> Result<(), Box<dyn Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    for desk in runtime.block_on(discover())? {
+        let rssi = desk
+            .rssi
+            .map(|rssi| format!("{} dBm", rssi))
+            .unwrap_or_default();
+        println!(
+            "{}\t{}\t{}",
+            desk.address,
+            desk.name.unwrap_or_default(),
+            rssi
+        );
+    }
+    Ok(())
+}
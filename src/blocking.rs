@@ -0,0 +1,330 @@
+//! Synchronous facade over the async API, enabled by the `blocking` feature.
+//!
+//! Each `Idasen` runs its own tokio runtime, so it can be used without an async context:
+//!
+//! ```no_run
+//! let desk = idasen::blocking::get_instance()?;
+//! desk.move_to(7200)?;
+//! println!("Position: {}", desk.position()?);
+//! # Ok::<(), idasen::Error>(())
+//! ```
+
+use crate::protocol::Command;
+use crate::{
+    AuditLog, BDAddr, BleDesk, Config, DeskCommand, DeskTransport, Device, Direction, Error,
+    IdasenBuilder, LastKnownPosition, Origin, PositionSpeed, ReconnectPolicy, Storage, Telemetry,
+    WriteType,
+};
+#[cfg(feature = "progress")]
+use crate::{LengthUnit, ProgressStyle};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio_stream::StreamExt;
+
+/// Blocking version of `idasen::get_instance`.
+pub fn get_instance() -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let runtime = runtime()?;
+    let inner = runtime.block_on(crate::get_instance())?;
+    Ok(Idasen { runtime, inner })
+}
+
+/// Blocking version of `idasen::get_instance_by_mac`.
+pub fn get_instance_by_mac(mac: &str) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let runtime = runtime()?;
+    let inner = runtime.block_on(crate::get_instance_by_mac(mac))?;
+    Ok(Idasen { runtime, inner })
+}
+
+/// Blocking version of `idasen::get_instance_by_name`.
+pub fn get_instance_by_name(name: &str) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let runtime = runtime()?;
+    let inner = runtime.block_on(crate::get_instance_by_name(name))?;
+    Ok(Idasen { runtime, inner })
+}
+
+/// Blocking version of `idasen::get_instance_from_config`.
+pub fn get_instance_from_config(config: &Config) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let runtime = runtime()?;
+    let inner = runtime.block_on(crate::get_instance_from_config(config))?;
+    Ok(Idasen { runtime, inner })
+}
+
+/// Blocking version of `idasen::IdasenBuilder::build`.
+pub fn build(builder: &IdasenBuilder) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let runtime = runtime()?;
+    let inner = runtime.block_on(builder.build())?;
+    Ok(Idasen { runtime, inner })
+}
+
+/// Blocking version of `idasen::wait_for_bluetooth`.
+pub fn wait_for_bluetooth(timeout: Duration) -> Result<(), Error> {
+    runtime()?.block_on(crate::wait_for_bluetooth(timeout))
+}
+
+fn runtime() -> Result<Runtime, Error> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|_| Error::CannotStartRuntime)
+}
+
+/// `idasen::Idasen` with blocking methods.
+pub struct Idasen<T: DeskTransport> {
+    runtime: Runtime,
+    inner: crate::Idasen<T>,
+}
+
+// Waits for the desk to stop and disconnect, the runtime would drop those tasks otherwise.
+impl<T: DeskTransport> Drop for Idasen<T> {
+    fn drop(&mut self) {
+        if let Some(release) = self.inner.release() {
+            self.runtime.block_on(release);
+        }
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Instantiate the struct, see `idasen::Idasen::new`.
+    pub fn new(desk: T) -> Result<Self, Error> {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(crate::Idasen::new(desk))?;
+        Ok(Self { runtime, inner })
+    }
+
+    /// Connect retrying as set in `policy`, see `idasen::Idasen::connect_with`.
+    pub fn connect_with(desk: T, policy: ReconnectPolicy) -> Result<Self, Error> {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(crate::Idasen::connect_with(desk, policy))?;
+        Ok(Self { runtime, inner })
+    }
+
+    /// The async desk, for the calls without a blocking version.
+    pub fn as_async(&self) -> &crate::Idasen<T> {
+        &self.inner
+    }
+
+    /// Run a future on the desk's runtime, e.g. one using `as_async`.
+    pub fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn mac_addr(&self) -> BDAddr {
+        self.inner.mac_addr
+    }
+
+    /// See `idasen::Idasen::persist_last_known_position`.
+    pub fn persist_last_known_position(&mut self, path: impl Into<PathBuf>) -> io::Result<()> {
+        self.inner.persist_last_known_position(path)
+    }
+
+    /// See `idasen::Idasen::persist_last_known_position_in`.
+    pub fn persist_last_known_position_in(
+        &mut self,
+        storage: impl Storage + 'static,
+    ) -> io::Result<()> {
+        self.inner.persist_last_known_position_in(storage)
+    }
+
+    /// See `idasen::Idasen::cache_position`.
+    pub fn cache_position(&mut self, max_age: Duration) {
+        self.inner.cache_position(max_age)
+    }
+
+    /// See `idasen::Idasen::reconnect_policy`.
+    pub fn reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.inner.reconnect_policy(policy)
+    }
+
+    /// See `idasen::Idasen::wake_up_after`.
+    pub fn wake_up_after(&mut self, idle: Option<Duration>) {
+        self.inner.wake_up_after(idle)
+    }
+
+    /// See `idasen::Idasen::telemetry`.
+    pub fn telemetry(&self) -> Telemetry {
+        self.inner.telemetry()
+    }
+
+    /// See `idasen::Idasen::read_latency`.
+    pub fn read_latency(&self) -> Option<Duration> {
+        self.inner.read_latency()
+    }
+
+    /// See `idasen::Idasen::write_type`.
+    pub fn write_type(&mut self, write_type: WriteType) {
+        self.inner.write_type(write_type)
+    }
+
+    /// See `idasen::Idasen::is_connected`.
+    pub fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    /// See `idasen::Idasen::disconnect`.
+    pub fn disconnect(&self) -> Result<(), Error> {
+        self.block_on(self.inner.disconnect())
+    }
+
+    /// See `idasen::Idasen::connect`.
+    pub fn connect(&self) -> Result<(), Error> {
+        self.block_on(self.inner.connect())
+    }
+
+    /// Connect again, see `idasen::Idasen::reconnect`.
+    pub fn reconnect(&self) -> Result<(), Error> {
+        self.block_on(self.inner.reconnect())
+    }
+
+    /// Return the most recent position read from the desk, without querying it.
+    pub fn last_known_position(&self) -> Option<LastKnownPosition> {
+        self.inner.last_known_position()
+    }
+
+    /// Move desk up.
+    pub fn up(&self) -> Result<(), Error> {
+        self.block_on(self.inner.up())
+    }
+
+    /// Lower the desk's position.
+    pub fn down(&self) -> Result<(), Error> {
+        self.block_on(self.inner.down())
+    }
+
+    /// See `idasen::Idasen::move`.
+    pub fn r#move(&self, direction: Direction) -> Result<(), Error> {
+        self.block_on(self.inner.r#move(direction))
+    }
+
+    /// See `idasen::Idasen::send`.
+    pub fn send(&self, command: Command) -> Result<(), Error> {
+        self.block_on(self.inner.send(command))
+    }
+
+    /// Stop desk from moving.
+    pub fn stop(&self) -> Result<(), Error> {
+        self.block_on(self.inner.stop())
+    }
+
+    /// Move desk to a desired position, returning once it got there.
+    pub fn move_to(&self, target_position: u16) -> Result<(), Error> {
+        self.block_on(self.inner.move_to(target_position))
+    }
+
+    /// See `idasen::Idasen::move_to_if_needed`.
+    pub fn move_to_if_needed(&self, target_position: u16, deadband: u16) -> Result<bool, Error> {
+        self.block_on(self.inner.move_to_if_needed(target_position, deadband))
+    }
+
+    /// Move desk to `percent` of its travel, see `idasen::Idasen::move_to_percent`.
+    pub fn move_to_percent(&self, percent: f32) -> Result<(), Error> {
+        self.block_on(self.inner.move_to_percent(percent))
+    }
+
+    /// Move desk up by `distance` tenth of millimetres, or as far up as it goes.
+    pub fn move_up_by(&self, distance: u16) -> Result<(), Error> {
+        self.block_on(self.inner.move_up_by(distance))
+    }
+
+    /// Move desk down by `distance` tenth of millimetres, or as far down as it goes.
+    pub fn move_down_by(&self, distance: u16) -> Result<(), Error> {
+        self.block_on(self.inner.move_down_by(distance))
+    }
+
+    /// Move desk to a desired position, letting the desk drive there on its own, see
+    /// `idasen::Idasen::move_to_native`.
+    pub fn move_to_native(&self, target_position: u16) -> Result<(), Error> {
+        self.block_on(self.inner.move_to_native(target_position))
+    }
+
+    /// Move desk to a desired position, showing a progress bar.
+    #[cfg(feature = "progress")]
+    pub fn move_to_with_progress(&self, target_position: u16) -> Result<(), Error> {
+        self.block_on(self.inner.move_to_with_progress(target_position))
+    }
+
+    /// Move desk to a desired position, showing a progress bar drawn in `style`.
+    #[cfg(feature = "progress")]
+    pub fn move_to_with_progress_style(
+        &self,
+        target_position: u16,
+        style: ProgressStyle,
+        unit: LengthUnit,
+    ) -> Result<(), Error> {
+        self.block_on(
+            self.inner
+                .move_to_with_progress_style(target_position, style, unit),
+        )
+    }
+
+    /// Run a command with the settings from `config`, see `idasen::Idasen::execute`.
+    pub fn execute(&self, command: DeskCommand, config: &Config) -> Result<u16, Error> {
+        self.block_on(self.inner.execute(command, config))
+    }
+
+    /// Move desk to a preset from `config`, see `idasen::Idasen::move_to_preset`.
+    pub fn move_to_preset(&self, name: &str, config: &Config) -> Result<(), Error> {
+        self.block_on(self.inner.move_to_preset(name, config))
+    }
+
+    /// Run a command and record it in `log`, see `idasen::Idasen::execute_audited`.
+    pub fn execute_audited(
+        &self,
+        command: DeskCommand,
+        config: &Config,
+        log: &AuditLog,
+        origin: &Origin,
+    ) -> Result<u16, Error> {
+        self.block_on(self.inner.execute_audited(command, config, log, origin))
+    }
+
+    /// Return the desk height in tenth millimeters (1m = 10000)
+    pub fn position(&self) -> Result<u16, Error> {
+        self.block_on(self.inner.position())
+    }
+
+    /// Return the desk height in tenth millimeters and speed.
+    pub fn position_and_speed(&self) -> Result<PositionSpeed, Error> {
+        self.block_on(self.inner.position_and_speed())
+    }
+
+    /// Where the desk is within its travel, in percent.
+    pub fn position_percent(&self) -> Result<f32, Error> {
+        self.block_on(self.inner.position_percent())
+    }
+
+    /// Like `position`, answering from a position at most `max_age` old.
+    pub fn position_cached(&self, max_age: Duration) -> Result<u16, Error> {
+        self.block_on(self.inner.position_cached(max_age))
+    }
+
+    /// Like `position`, always reading the desk.
+    pub fn read_position(&self) -> Result<u16, Error> {
+        self.block_on(self.inner.read_position())
+    }
+
+    /// Signal strength of the desk in dBm, `None` if the adapter doesn't report it.
+    pub fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.block_on(self.inner.rssi())
+    }
+
+    /// Name the desk advertises, e.g. `Desk 1234`.
+    pub fn name(&self) -> Result<Option<String>, Error> {
+        self.block_on(self.inner.name())
+    }
+
+    /// Iterate over position changes, each `next` blocks until the desk reports one.
+    pub fn position_iter(&self) -> Result<impl Iterator<Item = u16> + '_, Error> {
+        let mut stream = Box::pin(self.block_on(self.inner.position_stream())?);
+        Ok(std::iter::from_fn(move || self.block_on(stream.next())))
+    }
+
+    /// Iterate over position and speed changes, each `next` blocks until the desk reports one.
+    pub fn position_and_speed_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = PositionSpeed> + '_, Error> {
+        let mut stream = Box::pin(self.block_on(self.inner.position_and_speed_stream())?);
+        Ok(std::iter::from_fn(move || self.block_on(stream.next())))
+    }
+}
@@ -0,0 +1,139 @@
+use crate::movement::StopOnDrop;
+use crate::storage::SingleFile;
+use crate::{DeskTransport, Error, Idasen, Storage};
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const STORAGE_KEY: &str = "calibration";
+// How often the command is repeated while driving to an end of the travel.
+const END_REPEAT: Duration = Duration::from_millis(200);
+// How long the position has to stay the same for the desk to count as at the end.
+const END_SETTLE: Duration = Duration::from_secs(1);
+
+/// Offset between the height the desk reports and the height of its surface measured with a
+/// tape measure, see `Idasen::calibrate`.
+///
+/// ```
+/// use idasen::Calibration;
+///
+/// let path = std::env::temp_dir().join("idasen-calibration-doctest");
+/// let calibration = Calibration::from_measurement(6200, 6450);
+/// assert_eq!(calibration.apply(7000), 7250);
+/// calibration.save(&path).unwrap();
+/// assert_eq!(Calibration::load(&path).unwrap(), Some(calibration));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calibration {
+    // tenth mm added to the reported height
+    pub offset: i16,
+}
+
+impl Calibration {
+    /// Calibration making the `reported` height read `measured`, both in tenth millimetres.
+    pub fn from_measurement(reported: u16, measured: u16) -> Self {
+        Self {
+            offset: (measured as i32 - reported as i32)
+                .clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+        }
+    }
+
+    /// Measured height for a height reported by the desk.
+    pub fn apply(&self, reported: u16) -> u16 {
+        clamp(reported as i32 + self.offset as i32)
+    }
+
+    /// Height reported by the desk for a measured height.
+    pub fn remove(&self, measured: u16) -> u16 {
+        clamp(measured as i32 - self.offset as i32)
+    }
+
+    /// Read a calibration saved by `save`. Returns `None` if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        Self::load_from(SingleFile(path.as_ref().to_path_buf()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_to(SingleFile(path.as_ref().to_path_buf()))
+    }
+
+    /// Read a calibration saved by `save_to`, stored under the key `calibration`.
+    pub fn load_from(storage: impl Storage) -> io::Result<Option<Self>> {
+        let content = match storage.read(STORAGE_KEY)? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        let offset = content
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid calibration"))?;
+        Ok(Some(Self { offset }))
+    }
+
+    pub fn save_to(&self, storage: impl Storage) -> io::Result<()> {
+        storage.write(STORAGE_KEY, &format!("{}\n", self.offset))
+    }
+}
+
+fn clamp(height: i32) -> u16 {
+    height.clamp(0, u16::MAX as i32) as u16
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Calibrate positions against the surface height `measured` at the current position, in
+    /// tenth millimetres. From then on positions, including the targets of `move_to`, are
+    /// measured heights. Save the result to keep it for the next connection.
+    pub async fn calibrate(&self, measured: u16) -> Result<Calibration, Error> {
+        let reported = self.calibration().remove(self.read_position().await?);
+        let calibration = Calibration::from_measurement(reported, measured);
+        self.use_calibration(calibration);
+        Ok(calibration)
+    }
+
+    /// Positions the desk can move to with the calibration applied, `MIN_HEIGHT..=MAX_HEIGHT`
+    /// unless another range was measured with `measure_range` or set with `use_range`.
+    pub fn height_range(&self) -> RangeInclusive<u16> {
+        let calibration = self.calibration();
+        let range = self.reported_range();
+        calibration.apply(*range.start())..=calibration.apply(*range.end())
+    }
+
+    /// Drive the desk to the bottom and then to the top of its travel, and use the positions
+    /// it stopped at as the `height_range`, for desks whose travel differs from the factory
+    /// frame's, e.g. on casters or custom feet. Takes about half a minute and leaves the desk at
+    /// the top. Returns the range as reported by the desk, to be kept for `use_range`.
+    ///
+    /// Dropping the future stops the desk, like `move_to_with`.
+    pub async fn measure_range(&self) -> Result<RangeInclusive<u16>, Error> {
+        let mut moving = StopOnDrop(Some(self));
+        let bottom = self.drive_to_end(false).await?;
+        let top = self.drive_to_end(true).await?;
+        moving.0 = None;
+        self.move_ended();
+        let range = bottom..=top.max(bottom);
+        self.use_range(range.clone());
+        Ok(range)
+    }
+
+    // Keep moving until the position stops changing, returning the reported position there.
+    async fn drive_to_end(&self, going_up: bool) -> Result<u16, Error> {
+        let mut last = self.read_position().await?;
+        let mut still_since = Instant::now();
+        while still_since.elapsed() < END_SETTLE {
+            self.keep_moving(going_up).await?;
+            tokio::time::sleep(END_REPEAT).await;
+            let position = self.read_position().await?;
+            if position != last {
+                last = position;
+                still_since = Instant::now();
+            }
+        }
+        self.stop().await?;
+        Ok(self.calibration().remove(last))
+    }
+}
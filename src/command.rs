@@ -0,0 +1,163 @@
+use crate::schedule::ParseError;
+use crate::{Config, DeskTransport, Error, Idasen};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A single action on the desk, so IPC servers, FFI layers and command line tools can all go
+/// through `Idasen::execute` and validate commands the same way.
+///
+/// Positions and distances are in tenth of millimetres. As text a command is its name in
+/// kebab case followed by its argument, e.g. `move-to 7200` or `preset stand`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DeskCommand {
+    MoveTo(u16),
+    /// Move by a distance from the current position, downwards if negative.
+    MoveBy(i16),
+    /// Move to a preset from the config, by name.
+    Preset(String),
+    Up,
+    Down,
+    Stop,
+    /// Read the position without moving.
+    Position,
+    /// Refuse all moves until `Unlock`.
+    Lock,
+    Unlock,
+}
+
+impl DeskCommand {
+    /// Whether the command can move the desk or change which moves are allowed.
+    pub fn moves(&self) -> bool {
+        !matches!(self, DeskCommand::Position)
+    }
+}
+
+impl fmt::Display for DeskCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeskCommand::MoveTo(position) => write!(f, "move-to {}", position),
+            DeskCommand::MoveBy(distance) => write!(f, "move-by {}", distance),
+            DeskCommand::Preset(name) => write!(f, "preset {}", name),
+            DeskCommand::Up => f.write_str("up"),
+            DeskCommand::Down => f.write_str("down"),
+            DeskCommand::Stop => f.write_str("stop"),
+            DeskCommand::Position => f.write_str("position"),
+            DeskCommand::Lock => f.write_str("lock"),
+            DeskCommand::Unlock => f.write_str("unlock"),
+        }
+    }
+}
+
+impl FromStr for DeskCommand {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, argument) = match s.find(char::is_whitespace) {
+            Some(index) => (&s[..index], s[index..].trim()),
+            None => (s, ""),
+        };
+        let invalid = || ParseError("command");
+        let command = match (name, argument) {
+            ("move-to", position) => DeskCommand::MoveTo(position.parse().map_err(|_| invalid())?),
+            ("move-by", distance) => DeskCommand::MoveBy(distance.parse().map_err(|_| invalid())?),
+            ("preset", name) if !name.is_empty() => DeskCommand::Preset(name.to_string()),
+            ("up", "") => DeskCommand::Up,
+            ("down", "") => DeskCommand::Down,
+            ("stop", "") => DeskCommand::Stop,
+            ("position", "") => DeskCommand::Position,
+            ("lock", "") => DeskCommand::Lock,
+            ("unlock", "") => DeskCommand::Unlock,
+            _ => return Err(invalid()),
+        };
+        Ok(command)
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Run `command` with the presets, limits and tuning from `config`. Returns the position
+    /// once the command is done, for `Up` and `Down` once the desk started moving.
+    ///
+    /// Targets outside the config's limits fail with `Error::PositionNotInRange`, unknown
+    /// presets with `Error::UnknownPreset`.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+    /// use idasen::{mock::MockDesk, Config, DeskCommand, Error, Idasen};
+    ///
+    /// let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+    /// let mut config = Config::default();
+    /// config.presets.push(("stand".to_string(), 11000));
+    ///
+    /// let position = desk.execute(DeskCommand::Preset("stand".to_string()), &config).await.unwrap();
+    /// assert!((position as i32 - 11000).abs() <= 10);
+    ///
+    /// desk.execute(DeskCommand::Lock, &config).await.unwrap();
+    /// assert!(matches!(
+    ///     desk.execute(DeskCommand::MoveBy(-500), &config).await,
+    ///     Err(Error::PositionNotInRange { .. })
+    /// ));
+    /// # });
+    /// ```
+    pub async fn execute(&self, command: DeskCommand, config: &Config) -> Result<u16, Error> {
+        let target = match command {
+            DeskCommand::MoveTo(position) => position,
+            DeskCommand::MoveBy(distance) => {
+                let position = self.read_position().await? as i32 + distance as i32;
+                if position < 0 || position > u16::MAX as i32 {
+                    let requested = position.clamp(0, u16::MAX as i32) as u16;
+                    return Err(Error::out_of_range(requested, &config.limits()));
+                }
+                position as u16
+            }
+            DeskCommand::Preset(name) => config.preset(&name).ok_or(Error::UnknownPreset(name))?,
+            DeskCommand::Up => {
+                let position = self.read_position().await?;
+                if position >= config.max_height {
+                    return Err(Error::out_of_range(position, &config.limits()));
+                }
+                self.up().await?;
+                return self.read_position().await;
+            }
+            DeskCommand::Down => {
+                let position = self.read_position().await?;
+                if position <= config.min_height {
+                    return Err(Error::out_of_range(position, &config.limits()));
+                }
+                self.down().await?;
+                return self.read_position().await;
+            }
+            DeskCommand::Stop => {
+                self.stop().await?;
+                return self.read_position().await;
+            }
+            DeskCommand::Position => return self.read_position().await,
+            DeskCommand::Lock => {
+                let restriction = self.restrict_around(0, Duration::from_secs(0)).await?;
+                return Ok(*restriction.range.start());
+            }
+            DeskCommand::Unlock => {
+                self.lift_restriction();
+                return self.read_position().await;
+            }
+        };
+
+        if !config.allows(target) {
+            return Err(Error::out_of_range(target, &config.limits()));
+        }
+        self.move_to_with(target, config.move_options()).await?;
+        self.read_position().await
+    }
+
+    /// Move desk to the preset called `name` in `config`, e.g. one loaded with `Config::load`.
+    /// Same as executing `DeskCommand::Preset`.
+    pub async fn move_to_preset(&self, name: &str, config: &Config) -> Result<(), Error> {
+        self.execute(DeskCommand::Preset(name.to_string()), config)
+            .await
+            .map(|_| ())
+    }
+}
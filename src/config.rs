@@ -0,0 +1,384 @@
+use crate::storage::read_file;
+use crate::{BDAddr, LengthUnit, MoveOptions, Storage, Tuning, MAX_HEIGHT, MIN_HEIGHT};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+const STORAGE_KEY: &str = "config.toml";
+
+/// Settings shared by all tools controlling the desk, so a CLI, a daemon and third-party
+/// frontends read and write the same file.
+///
+/// Stored as TOML, by default under `default_path`. Only the keys written by `save` are
+/// understood, unknown keys are rejected so typos don't go unnoticed.
+///
+/// ```
+/// use idasen::{Config, LengthUnit};
+///
+/// let mut config = Config::default();
+/// config.presets.push(("sit".to_string(), 7200));
+/// config.unit = LengthUnit::Inches;
+/// let text = config.to_string();
+/// assert!(text.contains("sit = 7200"));
+/// assert_eq!(text.parse::<Config>().unwrap(), config);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Config {
+    /// Desk to connect to, any desk if not set.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::handle::optional_address_string")
+    )]
+    pub address: Option<BDAddr>,
+    /// Name the desk advertises, to find it again when its address changed, see
+    /// `IdasenBuilder::name`.
+    pub name: Option<String>,
+    /// File remembering the address of the desk last connected to, see
+    /// `IdasenBuilder::address_cache`.
+    pub address_cache: Option<PathBuf>,
+    /// Positions the desk should stay within, in tenth of millimetres.
+    pub min_height: u16,
+    pub max_height: u16,
+    /// Named positions in tenth of millimetres, in the order they are shown.
+    pub presets: Vec<(String, u16)>,
+    pub tuning: Tuning,
+    pub unit: LengthUnit,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            address: None,
+            name: None,
+            address_cache: None,
+            min_height: MIN_HEIGHT,
+            max_height: MAX_HEIGHT,
+            presets: Vec::new(),
+            tuning: Tuning::default(),
+            unit: LengthUnit::Centimetres,
+        }
+    }
+}
+
+impl Config {
+    /// `idasen/config.toml` in the platform's configuration directory: `$XDG_CONFIG_HOME` or
+    /// `~/.config` on Linux, `~/Library/Application Support` on macOS and `%APPDATA%` on
+    /// Windows. `None` if the directory can't be determined.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = if cfg!(target_os = "windows") {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        Some(dir?.join("idasen").join("config.toml"))
+    }
+
+    /// Read a config saved by `save`. Returns `None` if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        read_file(path)?.map(|content| content.parse()).transpose()
+    }
+
+    /// Write the config, creating the directory if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+
+    /// Read a config saved by `save_to`, stored under the key `config.toml` so a
+    /// `FileStorage::in_config_dir` finds the file at `default_path`.
+    pub fn load_from(storage: impl Storage) -> io::Result<Option<Self>> {
+        storage.read(STORAGE_KEY)?.map(|content| content.parse()).transpose()
+    }
+
+    pub fn save_to(&self, storage: impl Storage) -> io::Result<()> {
+        storage.write(STORAGE_KEY, &self.to_string())
+    }
+
+    pub fn preset(&self, name: &str) -> Option<u16> {
+        self.presets
+            .iter()
+            .find(|(preset, _)| preset == name)
+            .map(|(_, position)| *position)
+    }
+
+    /// Whether `position` is within the configured limits.
+    pub fn allows(&self, position: u16) -> bool {
+        self.limits().contains(&position)
+    }
+
+    pub fn limits(&self) -> RangeInclusive<u16> {
+        self.min_height..=self.max_height
+    }
+
+    /// Options for `Idasen::move_to_with` with the configured tuning.
+    pub fn move_options(&self) -> MoveOptions<'static> {
+        MoveOptions::new().tuning(self.tuning)
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "unit = \"{}\"", self.unit.symbol())?;
+
+        writeln!(f, "\n[desk]")?;
+        if let Some(address) = self.address {
+            writeln!(f, "address = \"{}\"", address)?;
+        }
+        if let Some(name) = &self.name {
+            writeln!(f, "name = {}", quoted(name))?;
+        }
+        if let Some(path) = &self.address_cache {
+            writeln!(f, "address_cache = {}", quoted(&path.to_string_lossy()))?;
+        }
+
+        writeln!(f, "\n[limits]")?;
+        writeln!(f, "min_height = {}", self.min_height)?;
+        writeln!(f, "max_height = {}", self.max_height)?;
+
+        writeln!(f, "\n[presets]")?;
+        for (name, position) in &self.presets {
+            writeln!(f, "{} = {}", key(name), position)?;
+        }
+
+        let tuning = &self.tuning;
+        writeln!(f, "\n[tuning]")?;
+        writeln!(f, "tolerance = {}", tuning.tolerance)?;
+        writeln!(f, "tick_ms = {}", tuning.tick.as_millis())?;
+        writeln!(f, "braking_distance = {}", tuning.braking_distance)?;
+        writeln!(f, "braking_time_ms = {}", tuning.braking_time.as_millis())?;
+        if let Some(watchdog) = tuning.watchdog {
+            writeln!(f, "watchdog_ms = {}", watchdog.as_millis())?;
+        }
+        if let Some(slow_approach) = tuning.slow_approach {
+            writeln!(f, "slow_approach = {}", slow_approach)?;
+        }
+        // Zero for no timeout, as leaving it out means the default.
+        let timeout = tuning.timeout.unwrap_or_default();
+        writeln!(f, "timeout_ms = {}", timeout.as_millis())?;
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        // Where the limits were last set, for the error if they contradict each other.
+        let mut limits_line = 0;
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let invalid = |message: &str| invalid(line_number, message);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let (name, value) = split_key(line).ok_or_else(|| invalid("expected key = value"))?;
+            let value = Value::parse(value).ok_or_else(|| invalid("invalid value"))?;
+            let number = || value.number().ok_or_else(|| invalid("expected a number"));
+            let height = || {
+                let height = number()?;
+                if height > u16::MAX as u64 {
+                    return Err(invalid("height out of range"));
+                }
+                Ok(height as u16)
+            };
+            let millis = || number().map(Duration::from_millis);
+            match (section.as_str(), name.as_str()) {
+                ("", "unit") => {
+                    config.unit = match value.string() {
+                        Some("cm") => LengthUnit::Centimetres,
+                        Some("in") => LengthUnit::Inches,
+                        _ => return Err(invalid("unit must be \"cm\" or \"in\"")),
+                    }
+                }
+                ("desk", "address") => {
+                    let address = value.string().ok_or_else(|| invalid("expected a string"))?;
+                    config.address = Some(address.parse().map_err(|_| invalid("invalid address"))?);
+                }
+                ("desk", "name") => {
+                    let name = value.string().ok_or_else(|| invalid("expected a string"))?;
+                    config.name = Some(name.to_string());
+                }
+                ("desk", "address_cache") => {
+                    let path = value.string().ok_or_else(|| invalid("expected a string"))?;
+                    config.address_cache = Some(PathBuf::from(path));
+                }
+                ("limits", "min_height") => {
+                    config.min_height = height()?;
+                    limits_line = line_number;
+                }
+                ("limits", "max_height") => {
+                    config.max_height = height()?;
+                    limits_line = line_number;
+                }
+                ("presets", _) => config.presets.push((name.clone(), height()?)),
+                ("tuning", "tolerance") => config.tuning.tolerance = height()?,
+                ("tuning", "tick_ms") => config.tuning.tick = millis()?,
+                ("tuning", "braking_distance") => config.tuning.braking_distance = height()?,
+                ("tuning", "braking_time_ms") => config.tuning.braking_time = millis()?,
+                ("tuning", "watchdog_ms") => config.tuning.watchdog = Some(millis()?),
+                ("tuning", "slow_approach") => config.tuning.slow_approach = Some(height()?),
+                ("tuning", "timeout_ms") => {
+                    config.tuning.timeout = Some(millis()?).filter(|timeout| !timeout.is_zero())
+                }
+                _ => return Err(invalid("unknown key")),
+            }
+        }
+        if config.min_height > config.max_height {
+            return Err(invalid(limits_line, "min_height is above max_height"));
+        }
+        Ok(config)
+    }
+}
+
+fn invalid(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid config, line {}: {}", line, message),
+    )
+}
+
+// The subset of TOML values the config uses.
+enum Value {
+    String(String),
+    Number(u64),
+}
+
+impl Value {
+    fn parse(value: &str) -> Option<Self> {
+        let value = strip_comment(value);
+        if let Some(quoted) = value.strip_prefix('"') {
+            let (string, rest) = unquote(quoted)?;
+            let rest = rest.trim();
+            return if rest.is_empty() || rest.starts_with('#') {
+                Some(Value::String(string))
+            } else {
+                None
+            };
+        }
+        value.replace('_', "").parse().ok().map(Value::Number)
+    }
+
+    fn string(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            Value::Number(_) => None,
+        }
+    }
+
+    fn number(&self) -> Option<u64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::String(_) => None,
+        }
+    }
+}
+
+// A key written bare if TOML allows it, quoted otherwise.
+fn key(name: &str) -> String {
+    let bare = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        name.to_string()
+    } else {
+        quoted(name)
+    }
+}
+
+// A basic string, TOML doesn't allow control characters in them unescaped.
+fn quoted(string: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn split_key(line: &str) -> Option<(String, &str)> {
+    if let Some(quoted) = line.strip_prefix('"') {
+        let (name, rest) = unquote(quoted)?;
+        let value = rest.trim_start().strip_prefix('=')?;
+        return Some((name, value));
+    }
+    let (name, value) = line.split_once('=')?;
+    Some((name.trim().to_string(), value))
+}
+
+// Read a basic string up to its closing quote, returning it and the rest of the line.
+fn unquote(quoted: &str) -> Option<(String, &str)> {
+    let mut string = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &quoted[index + 1..])),
+            '\\' => match chars.next()?.1 {
+                '"' => string.push('"'),
+                '\\' => string.push('\\'),
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                'b' => string.push('\u{8}'),
+                'f' => string.push('\u{c}'),
+                'u' => string.push(unicode(&mut chars, 4)?),
+                'U' => string.push(unicode(&mut chars, 8)?),
+                _ => return None,
+            },
+            _ => string.push(c),
+        }
+    }
+    None
+}
+
+// The character of a `\\u` or `\\U` escape, written with exactly `digits` hex digits.
+fn unicode(chars: &mut impl Iterator<Item = (usize, char)>, digits: usize) -> Option<char> {
+    let hex: String = chars.take(digits).map(|(_, c)| c).collect();
+    if hex.len() != digits {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+}
+
+fn strip_comment(value: &str) -> &str {
+    let value = value.trim();
+    if value.starts_with('"') {
+        value
+    } else {
+        value.split('#').next().unwrap_or_default().trim()
+    }
+}
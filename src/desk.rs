@@ -0,0 +1,891 @@
+use crate::{
+    try_bytes_to_position_speed, Calibration, BDAddr, DeskEvent, DeskTransport, Direction,
+    DiscoveredServices, Error, LastKnownPosition, PositionSpeed, Profile, RateLimit, Restriction,
+    Storage, WriteType, CONTROL_UUID, DOWN, FIRMWARE_REVISION_UUID, MAX_HEIGHT, MIN_HEIGHT,
+    POSITION_UUID, REFERENCE_INPUT_UUID, STOP, UP, WAKE_UP,
+};
+use crate::firmware::info_string;
+use crate::protocol::Command;
+use crate::storage::SingleFile;
+use crate::telemetry::Odometer;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_stream::{Stream, StreamExt};
+use uuid::Uuid;
+
+// Desks idle for longer than this may ignore the first movement command.
+const WAKE_UP_AFTER: Duration = Duration::from_secs(60);
+// The desk keeps moving this long after the last movement command, unless told to stop.
+pub(crate) const COMMAND_RUNS: Duration = Duration::from_secs(1);
+// Window `RateLimit::max_per_minute` counts the moves in.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+// How far a receiver of `events` may fall behind before it misses events.
+const EVENT_CAPACITY: usize = 64;
+
+pub struct Idasen<T>
+where
+    T: DeskTransport,
+{
+    pub mac_addr: BDAddr,
+    // Shared with the tasks cleaning up after dropped futures.
+    desk: Arc<T>,
+    // Shared with the position streams, see `events`.
+    events: broadcast::Sender<DeskEvent>,
+    last_known: Mutex<Option<LastKnownPosition>>,
+    last_known_storage: Option<Box<dyn Storage>>,
+    // Position last written to `last_known_storage`.
+    persisted_position: Mutex<Option<u16>>,
+    // Characteristics to subscribe to again after reconnecting.
+    subscriptions: Mutex<Vec<Uuid>>,
+    // Whether the desk notifies position changes, see `has_position_notifications`.
+    notifying: bool,
+    counters: Mutex<ErrorCounters>,
+    discovered: DiscoveredServices,
+    // Measured by `profile` or set with `use_profile`.
+    profile: Mutex<Option<Profile>>,
+    max_position_age: Option<Duration>,
+    calibration: Mutex<Calibration>,
+    // Travel measured by `measure_range` or set with `use_range`, as reported by the desk.
+    range: Mutex<Option<RangeInclusive<u16>>>,
+    restriction: Mutex<Option<Restriction>>,
+    last_move_ended: Mutex<Option<Instant>>,
+    rate_limit: Mutex<Option<RateLimit>>,
+    // When the moves within the last `RATE_WINDOW` started, oldest first.
+    move_starts: Mutex<VecDeque<Instant>>,
+    // Read at connect and kept, the firmware doesn't change while connected. `None` until a
+    // read succeeded.
+    firmware_revision: Mutex<Option<Option<String>>>,
+    // Direction and top speed of the last long move, for `load_factor`.
+    top_speed: Mutex<Option<(bool, u16)>>,
+    // Last position read or notified and when, shared with the position streams.
+    cached_position: Arc<Mutex<Option<(PositionSpeed, Instant)>>>,
+    // Fed with the same positions, for `telemetry`.
+    odometer: Arc<Mutex<Odometer>>,
+    // Smoothed duration of the position reads, see `read_latency`.
+    read_latency: Mutex<Option<Duration>>,
+    // When the desk was last read or written, and whether it was disconnected since, for
+    // `spawn_idle_disconnect`.
+    last_activity: Mutex<(Instant, bool)>,
+    // When a movement command was last written, shared with `Monitor` to tell the desk's own
+    // buttons from commands sent through here.
+    last_command: Arc<Mutex<Option<Instant>>>,
+    reconnect_policy: ReconnectPolicy,
+    wake_up_after: Option<Duration>,
+    // How commands are written, see `write_type`.
+    write_type: WriteType,
+    // Whether the last read or write went through, or failed for another reason than a lost
+    // connection.
+    connected: Mutex<bool>,
+}
+
+/// How reads and writes reconnect after the connection was lost, e.g. because the desk was
+/// out of range for a while, and how `Idasen::connect_with` connects. Each attempt waits twice
+/// as long as the previous one. Running out of several attempts fails with
+/// `Error::RetriesExhausted`, holding the last error.
+///
+/// The default is a single attempt right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Attempts to reconnect before the operation fails, at least one.
+    pub attempts: u32,
+    /// Wait after the first failed attempt.
+    pub backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Failures since the desk was connected, e.g. for diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorCounters {
+    /// Reads and writes that failed, also after reconnecting.
+    pub failed_operations: u32,
+    /// Reconnects after the connection was lost.
+    pub reconnects: u32,
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Instantiate the struct. Requires a `DeskTransport`, wrap a btleplug `Device` in `BleDesk`.
+    ///
+    /// The desk is disconnected again if connecting fails, also when the future is dropped
+    /// before it is done.
+    pub async fn new(desk: T) -> Result<Self, Error> {
+        Self::connect_with(desk, ReconnectPolicy::default()).await
+    }
+
+    /// Like `new`, retrying to connect and subscribe as set in `policy`, which is then used for
+    /// reconnects too. Some platforms often fail the first connect after a scan. A desk without
+    /// the expected characteristics fails right away.
+    pub async fn connect_with(desk: T, policy: ReconnectPolicy) -> Result<Self, Error> {
+        let desk = Arc::new(desk);
+        let mac_addr = desk.address();
+        let mut connecting = DisconnectOnDrop(Some(desk.clone()));
+        let notifying = retrying(policy, || set_up(&*desk)).await?;
+
+        let discovered = DiscoveredServices::new(desk.characteristics());
+        // Without retrying, `firmware_revision` reads it again if this fails.
+        let firmware_revision = if desk.has_characteristic(FIRMWARE_REVISION_UUID) {
+            let value = desk.read(FIRMWARE_REVISION_UUID).await.ok();
+            value.map(|value| Some(info_string(&value)))
+        } else {
+            Some(None)
+        };
+        connecting.0 = None;
+        Ok(Self {
+            desk,
+            mac_addr,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            last_known: Mutex::new(None),
+            last_known_storage: None,
+            persisted_position: Mutex::new(None),
+            subscriptions: Mutex::new(if notifying {
+                vec![POSITION_UUID]
+            } else {
+                Vec::new()
+            }),
+            notifying,
+            counters: Mutex::new(ErrorCounters::default()),
+            discovered,
+            profile: Mutex::new(None),
+            max_position_age: None,
+            calibration: Mutex::new(Calibration::default()),
+            range: Mutex::new(None),
+            restriction: Mutex::new(None),
+            last_move_ended: Mutex::new(None),
+            rate_limit: Mutex::new(None),
+            move_starts: Mutex::new(VecDeque::new()),
+            firmware_revision: Mutex::new(firmware_revision),
+            top_speed: Mutex::new(None),
+            cached_position: Arc::new(Mutex::new(None)),
+            odometer: Arc::new(Mutex::new(Odometer::default())),
+            read_latency: Mutex::new(None),
+            last_activity: Mutex::new((Instant::now(), false)),
+            last_command: Arc::new(Mutex::new(None)),
+            reconnect_policy: policy,
+            wake_up_after: Some(WAKE_UP_AFTER),
+            write_type: WriteType::WithoutResponse,
+            connected: Mutex::new(true),
+        })
+    }
+
+    /// Retry reconnecting as set in `policy` before a read or write fails, e.g. in a daemon
+    /// that has to get through the desk being out of range for a while.
+    pub fn reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy;
+    }
+
+    /// Write `WAKE_UP` before `up` and `down` once the desk has been idle for `idle`, see
+    /// `idle_for`, so the first command after a break isn't dropped. A minute by default,
+    /// `None` never wakes the desk up.
+    pub fn wake_up_after(&mut self, idle: Option<Duration>) {
+        self.wake_up_after = idle;
+    }
+
+    /// Write the movement commands with `write_type`, `WriteType::WithoutResponse` by default
+    /// like the app does. With `WriteType::WithResponse` the desk acknowledges every command,
+    /// so on a congested connection they aren't silently dropped and one the desk rejects
+    /// fails with `Error::BtlePlugError`, at the cost of a round trip per command. `write_raw`
+    /// takes the write type with every call.
+    pub fn write_type(&mut self, write_type: WriteType) {
+        self.write_type = write_type;
+    }
+
+    pub(crate) fn command_write_type(&self) -> WriteType {
+        self.write_type
+    }
+
+    /// Whether the desk notifies position changes. Some platforms fail subscribing to them
+    /// even though reads work; moves then read the position every tick instead, while
+    /// position streams, `Monitor` and `Recording` fail with `Error::CannotSubscribePosition`.
+    pub fn has_position_notifications(&self) -> bool {
+        self.notifying
+    }
+
+    /// Whether the desk could be reached the last time it was read or written.
+    pub fn is_connected(&self) -> bool {
+        *self.connected.lock().unwrap()
+    }
+
+    // Tells `events` when the connection comes or goes.
+    fn set_connected(&self, connected: bool) {
+        let was_connected = std::mem::replace(&mut *self.connected.lock().unwrap(), connected);
+        if was_connected != connected {
+            self.emit(if connected {
+                DeskEvent::Connected
+            } else {
+                DeskEvent::Disconnected
+            });
+        }
+    }
+
+    /// Receive what happens to the desk from now on, e.g. to drive an app's state from one
+    /// stream: `Connected` and `Disconnected` as the connection comes and goes,
+    /// `PositionChanged` whenever a read or notification brings a new position or speed, and
+    /// `MoveStarted` followed by `MoveCompleted` or `MoveAborted` for each move to a target.
+    ///
+    /// A receiver more than 64 events behind misses the oldest ones. The channel closes once
+    /// the desk is dropped.
+    pub fn events(&self) -> broadcast::Receiver<DeskEvent> {
+        self.events.subscribe()
+    }
+
+    pub(crate) fn emit(&self, event: DeskEvent) {
+        // Nobody listening is not an error.
+        let _ = self.events.send(event);
+    }
+
+    /// Save the position to `path` whenever the desk is read at rest, so it can be loaded
+    /// with `LastKnownPosition::load` while the desk is out of range. Failing to write the
+    /// file doesn't affect reading the position.
+    ///
+    /// A position already saved in the file is loaded, so `last_known_position` has a value
+    /// before the desk is read for the first time.
+    pub fn persist_last_known_position(&mut self, path: impl Into<PathBuf>) -> io::Result<()> {
+        self.persist_last_known_position_in(SingleFile(path.into()))
+    }
+
+    /// Like `persist_last_known_position`, saving the position to `storage`.
+    pub fn persist_last_known_position_in(
+        &mut self,
+        storage: impl Storage + 'static,
+    ) -> io::Result<()> {
+        let saved = LastKnownPosition::load_from(&storage)?;
+        self.last_known_storage = Some(Box::new(storage));
+
+        if let Some(saved) = saved {
+            *self.persisted_position.lock().unwrap() = Some(saved.position);
+            self.last_known.lock().unwrap().get_or_insert(saved);
+        }
+        Ok(())
+    }
+
+    /// Let `position` and `position_and_speed` answer like `position_cached`, from a position
+    /// at most `max_age` old, so frequent polling, e.g. by a status bar, doesn't cost a
+    /// Bluetooth round trip each time. Moves always read the desk, as do `read_position` and
+    /// `read_position_and_speed`.
+    pub fn cache_position(&mut self, max_age: Duration) {
+        self.max_position_age = Some(max_age);
+    }
+
+    /// Use a calibration from `calibrate`, e.g. loaded with `Calibration::load`. Position
+    /// streams opened before keep the previous calibration.
+    pub fn use_calibration(&self, calibration: Calibration) {
+        *self.calibration.lock().unwrap() = calibration;
+    }
+
+    /// Use a travel measured earlier with `measure_range`, as reported by the desk.
+    pub fn use_range(&self, range: RangeInclusive<u16>) {
+        *self.range.lock().unwrap() = Some(range);
+    }
+
+    // Travel as reported by the desk, before the calibration is applied.
+    pub(crate) fn reported_range(&self) -> RangeInclusive<u16> {
+        let range = self.range.lock().unwrap().clone();
+        range.unwrap_or(MIN_HEIGHT..=MAX_HEIGHT)
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        *self.calibration.lock().unwrap()
+    }
+
+    /// Use a profile measured earlier, e.g. loaded with `Profile::load`, for estimates and to
+    /// predict where moves come to a halt, see `MoveOptions::profile`.
+    pub fn use_profile(&self, profile: Profile) {
+        *self.profile.lock().unwrap() = Some(profile);
+    }
+
+    /// Profile set with `use_profile` or last measured with `profile`.
+    pub fn known_profile(&self) -> Option<Profile> {
+        *self.profile.lock().unwrap()
+    }
+
+    /// How much slower the desk moved in its last long move than the `known_profile` says, e.g.
+    /// 1.2 when it was 20 % slower. A sudden lasting change suggests something heavy was put on
+    /// the desk or is caught. `None` without a profile or a move of at least 2 cm.
+    pub fn load_factor(&self) -> Option<f64> {
+        let profile = self.known_profile()?;
+        let (going_up, top_speed) = (*self.top_speed.lock().unwrap())?;
+        let expected = if going_up { profile.up } else { profile.down }.speed;
+        if top_speed == 0 {
+            return None;
+        }
+        Some(expected as f64 / top_speed as f64)
+    }
+
+    pub(crate) fn record_top_speed(&self, going_up: bool, speed: u16) {
+        *self.top_speed.lock().unwrap() = Some((going_up, speed));
+    }
+
+    /// Limit movement as set in `restriction`, replacing any earlier one.
+    pub fn restrict(&self, restriction: Restriction) {
+        *self.restriction.lock().unwrap() = Some(restriction);
+    }
+
+    pub fn lift_restriction(&self) {
+        *self.restriction.lock().unwrap() = None;
+    }
+
+    pub fn restriction(&self) -> Option<Restriction> {
+        self.restriction.lock().unwrap().clone()
+    }
+
+    /// Limit how often moves may start as set in `limit`, replacing any earlier limit. Moves
+    /// started before count towards it.
+    pub fn limit_rate(&self, limit: RateLimit) {
+        *self.rate_limit.lock().unwrap() = Some(limit);
+    }
+
+    pub fn lift_rate_limit(&self) {
+        *self.rate_limit.lock().unwrap() = None;
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    // Whether a move to `target_position` may start now.
+    pub(crate) fn check_move(&self, target_position: u16) -> Result<(), Error> {
+        if let Some(ref restriction) = *self.restriction.lock().unwrap() {
+            if !restriction.range.contains(&target_position) {
+                return Err(Error::out_of_range(target_position, &restriction.range));
+            }
+            if let Some(ended) = *self.last_move_ended.lock().unwrap() {
+                if ended.elapsed() < restriction.cooldown {
+                    return Err(Error::CoolingDown);
+                }
+            }
+        }
+        self.count_move_start()
+    }
+
+    fn count_move_start(&self) -> Result<(), Error> {
+        let mut starts = self.move_starts.lock().unwrap();
+        while matches!(starts.front(), Some(start) if start.elapsed() >= RATE_WINDOW) {
+            starts.pop_front();
+        }
+        if let Some(limit) = *self.rate_limit.lock().unwrap() {
+            let too_soon =
+                matches!(starts.back(), Some(start) if start.elapsed() < limit.min_interval);
+            if too_soon || starts.len() >= limit.max_per_minute as usize {
+                return Err(Error::CoolingDown);
+            }
+        }
+        starts.push_back(Instant::now());
+        Ok(())
+    }
+
+    // Repeating `up` or `down` while the desk still runs from the last command, as holding a
+    // button does, continues the move started by the first one.
+    fn count_start_from_rest(&self) -> Result<(), Error> {
+        let last_command = *self.last_command.lock().unwrap();
+        match last_command {
+            Some(written) if written.elapsed() < COMMAND_RUNS => Ok(()),
+            _ => self.count_move_start(),
+        }
+    }
+
+    pub(crate) fn move_ended(&self) {
+        *self.last_move_ended.lock().unwrap() = Some(Instant::now());
+    }
+
+    // Whether the restriction allows moving further from the last position read.
+    fn check_direction(&self, going_up: bool) -> Result<(), Error> {
+        if let Some(ref restriction) = *self.restriction.lock().unwrap() {
+            let edge = if going_up {
+                *restriction.range.end()
+            } else {
+                *restriction.range.start()
+            };
+            // Not knowing where the desk is counts as being at the edge.
+            let position = self.last_known_position().map_or(edge, |last| last.position);
+            let at_edge = if going_up {
+                position >= edge
+            } else {
+                position <= edge
+            };
+            if at_edge {
+                return Err(Error::out_of_range(position, &restriction.range));
+            }
+        }
+        Ok(())
+    }
+
+    /// Services and characteristics found while connecting, including the ones `Idasen`
+    /// doesn't use.
+    pub fn discovered_services(&self) -> &DiscoveredServices {
+        &self.discovered
+    }
+
+    pub(crate) fn known_firmware_revision(&self) -> &Mutex<Option<Option<String>>> {
+        &self.firmware_revision
+    }
+
+    /// Whether the desk offers the characteristic.
+    pub fn has_characteristic(&self, uuid: Uuid) -> bool {
+        self.desk.has_characteristic(uuid)
+    }
+
+    /// Signal strength of the desk in dBm, to tell a weak connection from a faulty desk.
+    /// `None` if the adapter doesn't report it.
+    pub async fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.desk.rssi().await
+    }
+
+    /// Name the desk advertises, e.g. `Desk 1234`, which the app lets the user change.
+    pub async fn name(&self) -> Result<Option<String>, Error> {
+        self.desk.name().await
+    }
+
+    /// Manufacturer specific data the desk advertises, by company identifier.
+    pub async fn manufacturer_data(&self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        self.desk.manufacturer_data().await
+    }
+
+    pub fn error_counters(&self) -> ErrorCounters {
+        *self.counters.lock().unwrap()
+    }
+
+    /// Return the most recent position read from the desk, without querying it.
+    pub fn last_known_position(&self) -> Option<LastKnownPosition> {
+        *self.last_known.lock().unwrap()
+    }
+
+    /// Move desk up. Counts as a move towards a `RateLimit` if the desk was at rest.
+    pub async fn up(&self) -> Result<(), Error> {
+        self.check_direction(true)?;
+        self.count_start_from_rest()?;
+        self.write_direction(true).await
+    }
+
+    /// Lower the desk's position. Counts as a move towards a `RateLimit` if the desk was at
+    /// rest.
+    pub async fn down(&self) -> Result<(), Error> {
+        self.check_direction(false)?;
+        self.count_start_from_rest()?;
+        self.write_direction(false).await
+    }
+
+    // Like `up` and `down`, for moves already counted by `check_move` or not started by the
+    // user.
+    pub(crate) async fn keep_moving(&self, going_up: bool) -> Result<(), Error> {
+        self.check_direction(going_up)?;
+        self.write_direction(going_up).await
+    }
+
+    async fn write_direction(&self, going_up: bool) -> Result<(), Error> {
+        self.wake_up_if_idle().await?;
+        let command = if going_up { &UP } else { &DOWN };
+        self.write_raw(CONTROL_UUID, command, self.write_type).await
+    }
+
+    /// Move the desk in `direction`, like `up` and `down`.
+    pub async fn r#move(&self, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Up => self.up().await,
+            Direction::Down => self.down().await,
+        }
+    }
+
+    /// Write `command` to the desk, going through the same checks as `up`, `down` and `stop`.
+    /// `Command::WakeUp` is written as is, regardless of how long the desk has been idle.
+    pub async fn send(&self, command: Command) -> Result<(), Error> {
+        match command {
+            Command::Up => self.up().await,
+            Command::Down => self.down().await,
+            Command::Stop => self.stop().await,
+            Command::WakeUp => self.write_raw(CONTROL_UUID, &WAKE_UP, self.write_type).await,
+        }
+    }
+
+    async fn wake_up_if_idle(&self) -> Result<(), Error> {
+        match self.wake_up_after {
+            Some(after) if self.idle_for() >= after => {
+                self.write_raw(CONTROL_UUID, &WAKE_UP, self.write_type).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Stop desk from moving.
+    pub async fn stop(&self) -> Result<(), Error> {
+        self.write_raw(CONTROL_UUID, &STOP, self.write_type).await
+    }
+
+    /// Return the desk height in tenth millimeters (1m = 10000)
+    pub async fn position(&self) -> Result<u16, Error> {
+        Ok(self.position_and_speed().await?.position)
+    }
+
+    /// Return the denk height in tenth millimeters and speed in unknown dimension
+    pub async fn position_and_speed(&self) -> Result<PositionSpeed, Error> {
+        match self.max_position_age {
+            Some(max_age) => self.position_and_speed_cached(max_age).await,
+            None => self.read_position_and_speed().await,
+        }
+    }
+
+    /// Like `position`, answering from the last position read or received by a position
+    /// stream if it is at most `max_age` old, e.g. for a display redrawn many times a second.
+    pub async fn position_cached(&self, max_age: Duration) -> Result<u16, Error> {
+        Ok(self.position_and_speed_cached(max_age).await?.position)
+    }
+
+    /// Like `position_and_speed`, answering from a position at most `max_age` old.
+    pub async fn position_and_speed_cached(&self, max_age: Duration) -> Result<PositionSpeed, Error> {
+        if let Some((position_speed, read_at)) = *self.cached_position.lock().unwrap() {
+            if read_at.elapsed() <= max_age {
+                return Ok(position_speed);
+            }
+        }
+        self.read_position_and_speed().await
+    }
+
+    /// Like `position`, always reading the desk.
+    pub async fn read_position(&self) -> Result<u16, Error> {
+        Ok(self.read_position_and_speed().await?.position)
+    }
+
+    /// Like `position_and_speed`, always reading the desk.
+    pub async fn read_position_and_speed(&self) -> Result<PositionSpeed, Error> {
+        let started = Instant::now();
+        let value = self.read_raw(POSITION_UUID).await?;
+        self.record_read_latency(started.elapsed());
+        let mut position_speed =
+            try_bytes_to_position_speed(&value).ok_or(Error::CannotReadPosition)?;
+        position_speed.position = self.calibration().apply(position_speed.position);
+        self.remember_position(&position_speed);
+        remember_latest(&self.cached_position, &self.events, position_speed);
+        self.odometer.lock().unwrap().record(position_speed.position);
+        Ok(position_speed)
+    }
+
+    pub(crate) fn odometer(&self) -> &Mutex<Odometer> {
+        &self.odometer
+    }
+
+    /// How long reading the position takes, averaged over the recent reads, `None` before
+    /// the first one. Moves follow the desk through notifications and only read it once it
+    /// has stopped, so a `MoveOptions::tick` shorter than this doesn't slow them down; other
+    /// loops polling `position` can't go faster than this, see `cache_position`.
+    pub fn read_latency(&self) -> Option<Duration> {
+        *self.read_latency.lock().unwrap()
+    }
+
+    // Each read counts for an eighth, so a single slow one doesn't swing the average.
+    fn record_read_latency(&self, latency: Duration) {
+        let mut average = self.read_latency.lock().unwrap();
+        *average = Some(match *average {
+            Some(average) => (average * 7 + latency) / 8,
+            None => latency,
+        });
+    }
+
+    fn remember_position(&self, position_speed: &PositionSpeed) {
+        let last = LastKnownPosition::now(position_speed.position);
+        *self.last_known.lock().unwrap() = Some(last);
+
+        if let Some(ref storage) = self.last_known_storage {
+            let mut persisted = self.persisted_position.lock().unwrap();
+            if position_speed.speed == 0
+                && *persisted != Some(last.position)
+                && last.save_to(storage).is_ok()
+            {
+                *persisted = Some(last.position);
+            }
+        }
+    }
+
+    /// Listen to position and speed changes
+    pub async fn position_and_speed_stream(&self) -> Result<impl Stream<Item = PositionSpeed>, Error> {
+        if !self.notifying {
+            return Err(Error::CannotSubscribePosition);
+        }
+        let calibration = self.calibration();
+        let cached_position = self.cached_position.clone();
+        let events = self.events.clone();
+        let odometer = self.odometer.clone();
+        Ok(self.desk.notifications().await?.filter_map(move |notification| {
+            // Malformed notifications are skipped, the next one comes within a moment.
+            if notification.uuid == POSITION_UUID {
+                let mut position_speed = try_bytes_to_position_speed(&notification.value)?;
+                position_speed.position = calibration.apply(position_speed.position);
+                remember_latest(&cached_position, &events, position_speed);
+                odometer.lock().unwrap().record(position_speed.position);
+                Some(position_speed)
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Listen to position changes, also those made with the desk's own buttons. Positions
+    /// are calibrated like `position`.
+    pub async fn position_stream(&self) -> Result<impl Stream<Item = u16>, Error> {
+        Ok(self.position_and_speed_stream().await?.map(|position_speed| position_speed.position))
+    }
+
+    /// Read any characteristic. Together with `write_raw` and `subscribe_raw` this allows
+    /// experimenting with undocumented commands over the same connection.
+    pub async fn read_raw(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        self.reconnecting(|| self.desk.read(uuid)).await
+    }
+
+    /// Write any characteristic. Writing to `CONTROL_UUID` moves the desk.
+    pub async fn write_raw(
+        &self,
+        uuid: Uuid,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<(), Error> {
+        if uuid == CONTROL_UUID || uuid == REFERENCE_INPUT_UUID {
+            *self.last_command.lock().unwrap() = Some(Instant::now());
+        }
+        self.reconnecting(|| self.desk.write(uuid, data, write_type)).await
+    }
+
+    pub(crate) fn last_command(&self) -> Arc<Mutex<Option<Instant>>> {
+        self.last_command.clone()
+    }
+
+    /// Enable notifications for any characteristic and listen to its values.
+    pub async fn subscribe_raw(&self, uuid: Uuid) -> Result<impl Stream<Item = Vec<u8>>, Error> {
+        self.reconnecting(|| self.desk.subscribe(uuid)).await?;
+        self.subscriptions.lock().unwrap().push(uuid);
+        Ok(self.desk.notifications().await?.filter_map(move |notification| {
+            if notification.uuid == uuid {
+                Some(notification.value)
+            } else {
+                None
+            }
+        }))
+    }
+
+    // Run `operation`, and once more after reconnecting if the connection was lost, e.g. while
+    // the computer was asleep.
+    async fn reconnecting<'a, R, F, O>(&'a self, operation: F) -> Result<R, Error>
+    where
+        F: Fn() -> O,
+        O: Future<Output = Result<R, Error>> + 'a,
+    {
+        *self.last_activity.lock().unwrap() = (Instant::now(), false);
+        let result = match operation().await {
+            Err(Error::BtlePlugError(btleplug::Error::NotConnected))
+            | Err(Error::BtlePlugError(btleplug::Error::TimedOut(_))) => {
+                self.set_connected(false);
+                self.counters.lock().unwrap().reconnects += 1;
+                match self.reconnect_retrying().await {
+                    Ok(()) => operation().await,
+                    Err(error) => Err(error),
+                }
+            }
+            result => {
+                self.set_connected(true);
+                result
+            }
+        };
+        if result.is_err() {
+            self.counters.lock().unwrap().failed_operations += 1;
+        }
+        self.last_activity.lock().unwrap().0 = Instant::now();
+        result
+    }
+
+    /// Time since the desk was last read or written.
+    pub fn idle_for(&self) -> Duration {
+        self.last_activity.lock().unwrap().0.elapsed()
+    }
+
+    // Disconnect unless already done since the last activity. The next read or write
+    // reconnects.
+    pub(crate) async fn disconnect_idle(&self) -> Result<(), Error> {
+        {
+            let mut last_activity = self.last_activity.lock().unwrap();
+            if last_activity.1 {
+                return Ok(());
+            }
+            last_activity.1 = true;
+        }
+        self.set_connected(false);
+        self.desk.disconnect().await
+    }
+
+    /// Disconnect from the desk, e.g. before the computer goes to sleep, keeping everything
+    /// set up on the instance. The next read or write connects again, or `connect`.
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        self.last_activity.lock().unwrap().1 = true;
+        self.set_connected(false);
+        self.desk.disconnect().await
+    }
+
+    /// Connect after `disconnect`, like `reconnect`.
+    pub async fn connect(&self) -> Result<(), Error> {
+        self.reconnect().await
+    }
+
+    /// Connect again and subscribe to the notifications again. Reads and writes do this on
+    /// their own after the connection was lost, see `reconnect_policy`.
+    pub async fn reconnect(&self) -> Result<(), Error> {
+        self.desk.connect().await?;
+        let subscriptions = self.subscriptions.lock().unwrap().clone();
+        for uuid in subscriptions {
+            self.desk.subscribe(uuid).await?;
+        }
+        self.set_connected(true);
+        Ok(())
+    }
+
+    async fn reconnect_retrying(&self) -> Result<(), Error> {
+        retrying(self.reconnect_policy, || self.reconnect()).await
+    }
+
+    // Stop the desk from a task of its own, for a move whose future was dropped. Left alone,
+    // the desk keeps going until the last command times out.
+    pub(crate) fn stop_in_background(&self) {
+        let desk = self.desk.clone();
+        let write_type = self.write_type;
+        spawn_if_in_runtime(async move {
+            let _ = desk.write(CONTROL_UUID, &STOP, write_type).await;
+        });
+    }
+
+    // Stop the desk if it may still be moving on a command from here, then disconnect, unless
+    // already disconnected. Returned as a future owning what it needs, to run after `self` is
+    // gone.
+    pub(crate) fn release(&self) -> Option<impl Future<Output = ()> + Send + 'static> {
+        {
+            let mut last_activity = self.last_activity.lock().unwrap();
+            if last_activity.1 {
+                return None;
+            }
+            last_activity.1 = true;
+        }
+        let moving = matches!(
+            *self.last_command.lock().unwrap(),
+            Some(at) if at.elapsed() < COMMAND_RUNS
+        );
+        let desk = self.desk.clone();
+        let write_type = self.write_type;
+        Some(async move {
+            if moving {
+                let _ = desk.write(CONTROL_UUID, &STOP, write_type).await;
+            }
+            let _ = desk.disconnect().await;
+        })
+    }
+}
+
+// Dropping the desk, e.g. when a panic unwinds or a tool returns early, stops a move started
+// from here and disconnects, so the desk isn't left running or held by a stale connection.
+impl<T: DeskTransport> Drop for Idasen<T> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release() {
+            spawn_if_in_runtime(release);
+        }
+    }
+}
+
+// Remember `position_speed` as the latest, telling `events` if it differs from the one before.
+fn remember_latest(
+    cached_position: &Mutex<Option<(PositionSpeed, Instant)>>,
+    events: &broadcast::Sender<DeskEvent>,
+    position_speed: PositionSpeed,
+) {
+    let previous = cached_position
+        .lock()
+        .unwrap()
+        .replace((position_speed, Instant::now()));
+    if previous.map(|(previous, _)| previous) != Some(position_speed) {
+        // Nobody listening is not an error.
+        let _ = events.send(DeskEvent::PositionChanged {
+            position: position_speed.position,
+            speed: position_speed.speed,
+        });
+    }
+}
+
+// Connect to a new desk, check it is one and subscribe to its position. Returns whether
+// subscribing worked.
+async fn set_up<T: DeskTransport>(desk: &T) -> Result<bool, Error> {
+    desk.connect().await?;
+
+    if !desk.has_characteristic(CONTROL_UUID) {
+        return Err(Error::CharacteristicsNotFound(format!("Control {}", CONTROL_UUID)));
+    }
+    if !desk.has_characteristic(POSITION_UUID) {
+        return Err(Error::CharacteristicsNotFound(format!("Position {}", POSITION_UUID)));
+    }
+
+    match desk.subscribe(POSITION_UUID).await {
+        Ok(()) => Ok(true),
+        Err(Error::PairingRequired) => Err(Error::PairingRequired),
+        Err(_) => Ok(false),
+    }
+}
+
+// Run `operation` until it succeeds or `policy` runs out of attempts, waiting twice as long
+// after each failure. Missing characteristics and pairing won't be fixed by another attempt.
+async fn retrying<R, F, O>(policy: ReconnectPolicy, mut operation: F) -> Result<R, Error>
+where
+    F: FnMut() -> O,
+    O: Future<Output = Result<R, Error>>,
+{
+    let mut backoff = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(error @ Error::CharacteristicsNotFound(_)) | Err(error @ Error::PairingRequired) => {
+                return Err(error)
+            }
+            Err(error) if attempt >= policy.attempts => {
+                return Err(if attempt > 1 {
+                    Error::RetriesExhausted {
+                        attempts: attempt,
+                        source: Box::new(error),
+                    }
+                } else {
+                    error
+                });
+            }
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.checked_mul(2).unwrap_or(backoff);
+                attempt += 1;
+            }
+            Ok(result) => return Ok(result),
+        }
+    }
+}
+
+// Disconnects the transport it holds when dropped, see `Idasen::new`.
+struct DisconnectOnDrop<T: DeskTransport>(Option<Arc<T>>);
+
+impl<T: DeskTransport> Drop for DisconnectOnDrop<T> {
+    fn drop(&mut self) {
+        if let Some(desk) = self.0.take() {
+            spawn_if_in_runtime(async move {
+                let _ = desk.disconnect().await;
+            });
+        }
+    }
+}
+
+// Cleaning up is skipped when the runtime itself is shutting down.
+fn spawn_if_in_runtime(future: impl Future<Output = ()> + Send + 'static) {
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        runtime.spawn(future);
+    }
+}
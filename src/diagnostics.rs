@@ -0,0 +1,240 @@
+use crate::{
+    adapter_info, DeskTransport, DeviceInfo, Direction, Error, ErrorCounters, FirmwareIssue,
+    Idasen, LastKnownPosition, PositionSpeed, MAX_HEIGHT, MIN_HEIGHT,
+};
+use std::fmt;
+use std::time::Duration;
+
+// A single command runs the motor for about half a second, then the desk slows down.
+const JOG_SETTLE: Duration = Duration::from_millis(1500);
+
+// Jogs moving the desk less than this, in tenth of millimetres, count as stalled.
+const STALL_DISTANCE: u16 = 20;
+
+/// State of the Bluetooth adapters and the desk, to attach to bug reports.
+///
+/// Collecting never fails, failures are recorded in `errors` instead. `Display` gives a plain
+/// text version, the `serde` feature a serializable one. For the characteristic traffic,
+/// record a trace with `trace::Recorder`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostics {
+    pub crate_version: String,
+    pub adapters: Vec<String>,
+    pub address: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub firmware_issues: Vec<FirmwareIssue>,
+    pub position: Option<PositionSpeed>,
+    pub last_known_position: Option<LastKnownPosition>,
+    pub counters: Option<ErrorCounters>,
+    /// See `Idasen::read_latency`.
+    pub read_latency: Option<Duration>,
+    /// What went wrong while collecting.
+    pub errors: Vec<String>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Add the Bluetooth adapters, works also when no desk can be found.
+    pub async fn with_adapters(mut self) -> Self {
+        match adapter_info().await {
+            Ok(adapters) => self.adapters = adapters,
+            Err(error) => self.errors.push(format!("adapters: {}", error)),
+        }
+        self
+    }
+
+    /// Add what the connected desk reports.
+    pub async fn with_desk<T: DeskTransport>(mut self, desk: &Idasen<T>) -> Self {
+        self.address = Some(desk.mac_addr.to_string());
+        match desk.firmware_revision().await {
+            Ok(revision) => self.firmware_revision = revision,
+            Err(error) => self.errors.push(format!("firmware revision: {}", error)),
+        }
+        match desk.firmware_advisories().await {
+            Ok(advisories) => {
+                self.firmware_issues = advisories.iter().map(|advisory| advisory.issue).collect()
+            }
+            Err(error) => self.errors.push(format!("firmware advisories: {}", error)),
+        }
+        match desk.position_and_speed().await {
+            Ok(position) => self.position = Some(position),
+            Err(error) => self.errors.push(format!("position: {}", error)),
+        }
+        self.last_known_position = desk.last_known_position();
+        self.counters = Some(desk.error_counters());
+        self.read_latency = desk.read_latency();
+        self
+    }
+}
+
+/// Result of the health check run by `Idasen::diagnose`, e.g. to go through the desks of a
+/// room and flag those with a weak signal or a motor that doesn't move.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiagnosticsReport {
+    pub address: String,
+    pub device_info: Option<DeviceInfo>,
+    pub rssi: Option<i16>,
+    pub position: Option<u16>,
+    /// How far the desk moved on a single up command, `None` if it couldn't be sent.
+    pub jog_up: Option<u16>,
+    /// How far the desk moved on a single down command, `None` if it couldn't be sent.
+    pub jog_down: Option<u16>,
+    /// What went wrong during the check.
+    pub errors: Vec<String>,
+}
+
+impl DiagnosticsReport {
+    /// Whether the desk hardly moved on a command it accepted.
+    pub fn stalled(&self) -> bool {
+        [self.jog_up, self.jog_down]
+            .iter()
+            .any(|jog| matches!(jog, Some(distance) if *distance < STALL_DISTANCE))
+    }
+
+    /// Whether the signal is weaker than `rssi` dBm, `false` if the adapter doesn't report it.
+    pub fn weak_signal(&self, rssi: i16) -> bool {
+        matches!(self.rssi, Some(signal) if signal < rssi)
+    }
+
+    /// Whether every step of the check passed with a signal of at least `rssi` dBm.
+    pub fn is_healthy(&self, rssi: i16) -> bool {
+        self.errors.is_empty()
+            && self.jog_up.is_some()
+            && self.jog_down.is_some()
+            && !self.stalled()
+            && !self.weak_signal(rssi)
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Diagnostics for the adapters and this desk.
+    pub async fn collect_diagnostics(&self) -> Diagnostics {
+        Diagnostics::new()
+            .with_adapters()
+            .await
+            .with_desk(self)
+            .await
+    }
+
+    /// Check the desk: read its device information, signal strength and position, then jog it
+    /// up and down with a single command each. The desk moves by a few centimetres and ends
+    /// up about where it was, away from the closer end of its travel first.
+    ///
+    /// Never fails, failures are recorded in the report's `errors` instead.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+    /// use idasen::{mock::MockDesk, Idasen};
+    ///
+    /// let desk = Idasen::new(MockDesk::new(7000).with_rssi(-85)).await.unwrap();
+    /// let report = desk.diagnose().await;
+    /// assert!(!report.stalled());
+    /// assert!(report.weak_signal(-80));
+    /// # });
+    /// ```
+    pub async fn diagnose(&self) -> DiagnosticsReport {
+        let mut report = DiagnosticsReport {
+            address: self.mac_addr.to_string(),
+            ..DiagnosticsReport::default()
+        };
+        match self.device_info().await {
+            Ok(info) => report.device_info = Some(info),
+            Err(error) => report.errors.push(format!("device info: {}", error)),
+        }
+        match self.rssi().await {
+            Ok(rssi) => report.rssi = rssi,
+            Err(error) => report.errors.push(format!("rssi: {}", error)),
+        }
+        let position = match self.read_position().await {
+            Ok(position) => position,
+            Err(error) => {
+                report.errors.push(format!("position: {}", error));
+                return report;
+            }
+        };
+        report.position = Some(position);
+
+        let directions = if position < (MIN_HEIGHT + MAX_HEIGHT) / 2 {
+            [Direction::Up, Direction::Down]
+        } else {
+            [Direction::Down, Direction::Up]
+        };
+        for &direction in &directions {
+            match self.jog(direction).await {
+                Ok(distance) => match direction {
+                    Direction::Up => report.jog_up = Some(distance),
+                    Direction::Down => report.jog_down = Some(distance),
+                },
+                Err(error) => report
+                    .errors
+                    .push(format!("jog {:?}: {}", direction, error)),
+            }
+        }
+        report
+    }
+
+    // Distance the desk travelled on a single movement command.
+    async fn jog(&self, direction: Direction) -> Result<u16, Error> {
+        let before = self.read_position().await?;
+        self.r#move(direction).await?;
+        tokio::time::sleep(JOG_SETTLE).await;
+        let after = self.read_position().await?;
+        Ok((after as i32 - before as i32).unsigned_abs() as u16)
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "idasen {}", self.crate_version)?;
+        for adapter in &self.adapters {
+            writeln!(f, "adapter: {}", adapter)?;
+        }
+        if let Some(ref address) = self.address {
+            writeln!(f, "desk: {}", address)?;
+        }
+        if let Some(ref revision) = self.firmware_revision {
+            writeln!(f, "firmware revision: {}", revision)?;
+        }
+        for issue in &self.firmware_issues {
+            writeln!(f, "firmware issue: {:?}", issue)?;
+        }
+        if let Some(ref position) = self.position {
+            writeln!(
+                f,
+                "position: {} speed: {}",
+                position.position, position.speed
+            )?;
+        }
+        if let Some(ref last) = self.last_known_position {
+            writeln!(
+                f,
+                "last known position: {} ({}s ago)",
+                last.position,
+                last.age().as_secs()
+            )?;
+        }
+        if let Some(ref counters) = self.counters {
+            writeln!(
+                f,
+                "failed operations: {} reconnects: {}",
+                counters.failed_operations, counters.reconnects
+            )?;
+        }
+        if let Some(latency) = self.read_latency {
+            writeln!(f, "read latency: {}ms", latency.as_millis())?;
+        }
+        for error in &self.errors {
+            writeln!(f, "error: {}", error)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,707 @@
+use crate::{
+    AdapterSwitch, BDAddr, BleDesk, Config, DeskTransport, Device, Error, Failover, Idasen,
+    Restriction, CONTROL_SERVICE_UUID, MAX_HEIGHT, MIN_HEIGHT,
+};
+use btleplug::api::{Central, Manager as _, ScanFilter};
+use btleplug::platform::{Manager, Peripheral};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+// How long `get_desks` scans for.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(2);
+// How often the peripherals are checked during a scan.
+const SCAN_POLL: Duration = Duration::from_millis(100);
+// How often `wait_for_bluetooth` tries to scan again.
+const RADIO_POLL: Duration = Duration::from_millis(500);
+
+/// A desk found by `discover` or `scan`, e.g. to let the user pick one before connecting with
+/// `Idasen::new(BleDesk::new(desk.device))`.
+///
+/// With the `serde` feature it serializes without the device and the advertised services.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiscoveredDesk<P> {
+    /// Name the desk advertises, e.g. `Desk 1234`.
+    pub name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::handle::address_string"))]
+    pub address: BDAddr,
+    /// Signal strength in dBm, the closest desk has the highest.
+    pub rssi: Option<i16>,
+    /// Transmission power in dBm, if advertised.
+    pub tx_power_level: Option<i16>,
+    /// Services the desk advertises, e.g. `CONTROL_SERVICE_UUID`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub services: Vec<Uuid>,
+    /// Manufacturer specific data, by company identifier.
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub device: P,
+}
+
+/// What a search that found no desk covered, carried by `Error::CannotFindDevice`, e.g. to
+/// tell a desk out of range from a search for the wrong address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanSummary {
+    /// Adapters searched.
+    pub adapters: usize,
+    /// Address searched for, `None` for any desk.
+    pub mac: Option<BDAddr>,
+    /// Name searched for, `None` for any desk.
+    pub name: Option<String>,
+    /// How long the search took.
+    pub duration: Duration,
+    /// Peripherals the adapters saw, desks or not.
+    pub peripherals_seen: usize,
+}
+
+impl fmt::Display for ScanSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "searched {} adapter(s) for {:.1} s and saw {} peripheral(s)",
+            self.adapters,
+            self.duration.as_secs_f32(),
+            self.peripherals_seen
+        )?;
+        if let Some(mac) = self.mac {
+            write!(f, ", looking for {}", mac)?;
+        }
+        if let Some(ref name) = self.name {
+            write!(f, ", looking for '{}'", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find desks on all adapters, the one with address `mac` if given. Fails with
+/// `Error::NoAdapter` on a machine without Bluetooth and `Error::CannotFindDevice` if no
+/// adapter sees a desk.
+///
+/// A desk is recognized by the Linak control service it advertises, so renamed desks are
+/// found too, or else by a name containing `Desk`.
+///
+/// Each adapter is searched on a task of its own, which ends the scan it started also when the
+/// returned future is dropped.
+pub async fn get_desks(mac: Option<BDAddr>) -> Result<Vec<impl Device>, Error> {
+    Ok(find_desks(mac, None, SCAN_TIMEOUT).await?.0)
+}
+
+/// Finds a desk and connects to it like `get_instance`, with the search tuned to the setup,
+/// e.g. a slow adapter or a second Bluetooth dongle.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), idasen::Error> {
+/// use idasen::IdasenBuilder;
+/// use std::time::Duration;
+///
+/// let desk = IdasenBuilder::new()
+///     .adapter_index(1)
+///     .scan_timeout(Duration::from_secs(30))
+///     .mac("EC:86:F6:00:00:01".parse()?)
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdasenBuilder {
+    mac: Option<BDAddr>,
+    name: Option<String>,
+    adapter_index: Option<usize>,
+    scan_timeout: Duration,
+    address_cache: Option<PathBuf>,
+}
+
+impl IdasenBuilder {
+    /// Search all adapters for any desk, scanning for 2 seconds.
+    pub fn new() -> Self {
+        Self {
+            mac: None,
+            name: None,
+            adapter_index: None,
+            scan_timeout: SCAN_TIMEOUT,
+            address_cache: None,
+        }
+    }
+
+    /// Search for the desk set in `config`, by address and then by name, or any desk if it
+    /// has neither.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            mac: config.address,
+            name: config.name.clone(),
+            address_cache: config.address_cache.clone(),
+            ..Self::new()
+        }
+    }
+
+    /// Only accept the desk with this address. The scan ends as soon as it is seen.
+    pub fn mac(mut self, mac: BDAddr) -> Self {
+        self.mac = Some(mac);
+        self
+    }
+
+    /// Only accept a desk advertising exactly `name`, e.g. `Desk 7412`. Together with `mac`,
+    /// the desk is searched by name if no desk has the address, as the desk's LE privacy can
+    /// change the address between sessions. That takes a second scan.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Only search the adapter at `index`, in the order listed by `adapter_info`. Finding
+    /// fails with `Error::NoAdapter` if there is none.
+    pub fn adapter_index(mut self, index: usize) -> Self {
+        self.adapter_index = Some(index);
+        self
+    }
+
+    /// Scan for at most `timeout`.
+    pub fn scan_timeout(mut self, timeout: Duration) -> Self {
+        self.scan_timeout = timeout;
+        self
+    }
+
+    /// Remember the address of the desk `build` connects to in the file at `path`, and look
+    /// for that desk first the next time, which ends the scan as soon as it is seen. The usual
+    /// search only runs if it can't be reached. Ignored when `mac` is set; a file that can't be
+    /// read or written is treated like no file, as it only saves time.
+    pub fn address_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.address_cache = Some(path.into());
+        self
+    }
+
+    /// Find the desks like `get_desks`, without connecting.
+    pub async fn find(&self) -> Result<Vec<impl Device>, Error> {
+        let name = match &self.name {
+            Some(name) => name,
+            None => {
+                return find_desks(self.mac, self.adapter_index, self.scan_timeout)
+                    .await
+                    .map(|(desks, _)| desks)
+            }
+        };
+        let mut by_address = None;
+        if self.mac.is_some() {
+            match find_desks(self.mac, self.adapter_index, self.scan_timeout).await {
+                Err(Error::CannotFindDevice { scan }) => by_address = scan,
+                desks => return desks.map(|(desks, _)| desks),
+            }
+        }
+        let (desks, mut summary) = find_desks(None, self.adapter_index, self.scan_timeout).await?;
+        if let Some(by_address) = by_address {
+            summary.mac = by_address.mac;
+            summary.duration += by_address.duration;
+        }
+        named(desks, name, summary).await
+    }
+
+    /// Connect to the first desk found that answers.
+    pub async fn build(&self) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+        let path = match &self.address_cache {
+            Some(path) if self.mac.is_none() => path,
+            _ => return self.connect().await,
+        };
+        let cached = fs::read_to_string(path)
+            .ok()
+            .and_then(|address| address.trim().parse::<BDAddr>().ok());
+        let desk = match cached {
+            Some(mac) => {
+                let remembered = Self {
+                    mac: Some(mac),
+                    name: None,
+                    ..self.clone()
+                };
+                match remembered.connect().await {
+                    Ok(desk) => desk,
+                    Err(_) => self.connect().await?,
+                }
+            }
+            None => self.connect().await?,
+        };
+        if cached != Some(desk.mac_addr) {
+            let _ = fs::write(path, desk.mac_addr.to_string());
+        }
+        Ok(desk)
+    }
+
+    async fn connect(&self) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+        let desks = self.find().await?;
+        connect_first(desks.into_iter().map(BleDesk::new)).await
+    }
+
+    /// Like `find`, searching `central` instead of the platform's adapters, e.g. a simulator
+    /// or another btleplug backend. The adapter index is ignored.
+    pub async fn find_on<C: Central>(&self, central: &C) -> Result<Vec<C::Peripheral>, Error> {
+        let started = Instant::now();
+        let mut desks = search_adapter_for_desks(central, self.mac, self.scan_timeout).await?;
+        if self.name.is_some() && desks.is_empty() && self.mac.is_some() {
+            desks = search_adapter_for_desks(central, None, self.scan_timeout).await?;
+        }
+        let summary = ScanSummary {
+            adapters: 1,
+            mac: self.mac,
+            name: None,
+            duration: started.elapsed(),
+            peripherals_seen: peripherals_seen(central).await,
+        };
+        if let Some(name) = &self.name {
+            return named(desks, name, summary).await;
+        }
+        if desks.is_empty() {
+            Err(Error::not_found(summary))
+        } else {
+            Ok(desks)
+        }
+    }
+
+    /// Like `build`, searching `central` instead of the platform's adapters.
+    pub async fn build_on<C: Central>(
+        &self,
+        central: &C,
+    ) -> Result<Idasen<BleDesk<C::Peripheral>>, Error>
+    where
+        C::Peripheral: 'static,
+    {
+        let desks = self.find_on(central).await?;
+        connect_first(desks.into_iter().map(BleDesk::new)).await
+    }
+}
+
+impl Default for IdasenBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn find_desks(
+    mac: Option<BDAddr>,
+    adapter_index: Option<usize>,
+    scan_timeout: Duration,
+) -> Result<(Vec<impl Device>, ScanSummary), Error> {
+    let manager = Manager::new().await?;
+    let mut adapters = manager.adapters().await?;
+    if let Some(index) = adapter_index {
+        if index >= adapters.len() {
+            return Err(Error::NoAdapter);
+        }
+        adapters = vec![adapters.swap_remove(index)];
+    }
+    if adapters.is_empty() {
+        return Err(Error::NoAdapter);
+    }
+    let mut summary = ScanSummary {
+        adapters: adapters.len(),
+        mac,
+        ..ScanSummary::default()
+    };
+    let started = Instant::now();
+    let mut jobs = Vec::new();
+
+    for adapter in adapters {
+        jobs.push(tokio::spawn(async move {
+            let desks = search_adapter_for_desks(&adapter, mac, scan_timeout).await;
+            (desks, peripherals_seen(&adapter).await)
+        }));
+    }
+
+    let mut desks = Vec::new();
+    let mut disabled = None;
+    for job in jobs {
+        if let Ok((job_desks, seen)) = job.await {
+            summary.peripherals_seen += seen;
+            match job_desks {
+                Ok(mut job_desks) => desks.append(&mut job_desks),
+                Err(error @ Error::BluetoothDisabled { .. }) => disabled = Some(error),
+                Err(_) => {}
+            }
+        }
+    }
+    summary.duration = started.elapsed();
+
+    if desks.is_empty() {
+        Err(disabled.unwrap_or_else(|| Error::not_found(summary)))
+    } else {
+        Ok((desks, summary))
+    }
+}
+
+// Peripherals the adapter has seen, for `ScanSummary`. BlueZ includes the ones it knew before.
+async fn peripherals_seen<C: Central>(adapter: &C) -> usize {
+    adapter
+        .peripherals()
+        .await
+        .map_or(0, |peripherals| peripherals.len())
+}
+
+// The desks advertising exactly `name`, failing with `Error::CannotFindDevice` if there are
+// none.
+async fn named<P: Device>(
+    desks: Vec<P>,
+    name: &str,
+    mut summary: ScanSummary,
+) -> Result<Vec<P>, Error> {
+    let mut named = Vec::new();
+    for device in desks {
+        let properties = device.properties().await?.unwrap_or_default();
+        if properties.local_name.as_deref() == Some(name) {
+            named.push(device);
+        }
+    }
+    if named.is_empty() {
+        summary.name = Some(name.to_string());
+        Err(Error::not_found(summary))
+    } else {
+        Ok(named)
+    }
+}
+
+async fn search_adapter_for_desks<C: Central>(
+    adapter: &C,
+    mac: Option<BDAddr>,
+    scan_timeout: Duration,
+) -> Result<Vec<C::Peripheral>, Error> {
+    // BlueZ keeps devices it has seen before, including paired ones. Connecting to a known
+    // address directly skips the discovery session and its permission requirements.
+    if let Some(mac) = mac {
+        let known: Vec<_> = adapter
+            .peripherals()
+            .await?
+            .into_iter()
+            .filter(|peripheral| peripheral.address() == mac)
+            .collect();
+        if !known.is_empty() {
+            return Ok(known);
+        }
+    }
+
+    start_scan(adapter).await?;
+    let deadline = Instant::now() + scan_timeout;
+    let desks = loop {
+        tokio::time::sleep(SCAN_POLL.min(deadline.saturating_duration_since(Instant::now()))).await;
+        let desks = matching_desks(adapter, mac).await;
+        // Any number of desks may answer to a name, so those scans run to the end.
+        let found = mac.is_some() && matches!(&desks, Ok(desks) if !desks.is_empty());
+        if found || desks.is_err() || Instant::now() >= deadline {
+            break desks;
+        }
+    };
+    adapter.stop_scan().await?;
+    desks
+}
+
+async fn matching_desks<C: Central>(
+    adapter: &C,
+    mac: Option<BDAddr>,
+) -> Result<Vec<C::Peripheral>, Error> {
+    let mut desks = Vec::new();
+    for peripheral in adapter.peripherals().await? {
+        if let Some(props) = peripheral.properties().await? {
+            if match mac {
+                Some(mac) => props.address == mac,
+                None => {
+                    props.services.contains(&CONTROL_SERVICE_UUID)
+                        || props.local_name.iter().any(|name| name.contains("Desk"))
+                }
+            } {
+                desks.push(peripheral);
+            }
+        }
+    }
+    Ok(desks)
+}
+
+/// Find all desks nearby without connecting to them, the strongest signal first.
+pub async fn discover() -> Result<Vec<DiscoveredDesk<impl Device>>, Error> {
+    let mut desks = Vec::new();
+    for device in get_desks(None).await? {
+        desks.push(discovered(device).await?);
+    }
+    desks.sort_by_key(|desk| Reverse(desk.rssi));
+    Ok(desks)
+}
+
+/// Search all adapters for desks like `discover`, calling `on_desk` with each desk as soon as
+/// it is seen and how long the scan has run so far, e.g. for a "searching…" display.
+///
+/// The scan ends after `timeout`, or as soon as `on_desk` returns `ControlFlow::Break`, e.g.
+/// once the desk wanted showed up. Returns the desks seen in the order they were found, and
+/// fails with `Error::CannotFindDevice` if there were none.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), idasen::Error> {
+/// use std::ops::ControlFlow;
+/// use std::time::Duration;
+///
+/// let desks = idasen::scan(Duration::from_secs(10), |desk, elapsed| {
+///     println!("{:.1}s: found {}", elapsed.as_secs_f32(), desk.address);
+///     if desk.name.as_deref() == Some("Desk 7412") {
+///         ControlFlow::Break(())
+///     } else {
+///         ControlFlow::Continue(())
+///     }
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn scan(
+    timeout: Duration,
+    on_desk: impl FnMut(&DiscoveredDesk<Peripheral>, Duration) -> ControlFlow<()>,
+) -> Result<Vec<DiscoveredDesk<Peripheral>>, Error> {
+    let manager = Manager::new().await?;
+    let adapters = manager.adapters().await?;
+    if adapters.is_empty() {
+        return Err(Error::NoAdapter);
+    }
+    scan_adapters(&adapters, timeout, on_desk).await
+}
+
+/// Like `scan`, searching `central` instead of the platform's adapters.
+pub async fn scan_on<C: Central>(
+    central: &C,
+    timeout: Duration,
+    on_desk: impl FnMut(&DiscoveredDesk<C::Peripheral>, Duration) -> ControlFlow<()>,
+) -> Result<Vec<DiscoveredDesk<C::Peripheral>>, Error> {
+    scan_adapters(std::slice::from_ref(central), timeout, on_desk).await
+}
+
+/// Wait up to `timeout` for Bluetooth to be turned on and ready, e.g. while asking the user
+/// to turn it on once `get_instance` failed with `Error::BluetoothDisabled`. Also covers the
+/// radio still starting up after boot or waking from sleep.
+///
+/// Fails with `Error::NoAdapter` on a machine without Bluetooth and with
+/// `Error::BluetoothDisabled` if scanning still fails once `timeout` is up.
+pub async fn wait_for_bluetooth(timeout: Duration) -> Result<(), Error> {
+    let manager = Manager::new().await?;
+    let adapters = manager.adapters().await?;
+    let adapter = adapters.first().ok_or(Error::NoAdapter)?;
+    wait_for_bluetooth_on(adapter, timeout).await
+}
+
+/// Like `wait_for_bluetooth`, waiting for `central` instead of the platform's first adapter.
+pub async fn wait_for_bluetooth_on<C: Central>(
+    central: &C,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match start_scan(central).await {
+            Ok(()) => return Ok(central.stop_scan().await?),
+            Err(Error::BluetoothDisabled { .. }) if Instant::now() < deadline => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                tokio::time::sleep(RADIO_POLL.min(remaining)).await
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+// Starting a scan is the first thing to fail while Bluetooth is turned off or its radio is
+// still starting up. btleplug has no state for it, Windows reports the device as not ready
+// or not connected, BlueZ the adapter as not ready or not powered.
+async fn start_scan<C: Central>(adapter: &C) -> Result<(), Error> {
+    match adapter.start_scan(ScanFilter::default()).await {
+        Err(btleplug::Error::Other(error)) => {
+            let message = error.to_string();
+            let disabled = ["NotReady", "not ready", "Not Powered", "0x8007048F"]
+                .iter()
+                .any(|reason| message.contains(reason));
+            Err(if disabled {
+                Error::BluetoothDisabled {
+                    source: btleplug::Error::Other(error),
+                }
+            } else {
+                Error::BtlePlugError(btleplug::Error::Other(error))
+            })
+        }
+        result => Ok(result?),
+    }
+}
+
+// All adapters are polled in one loop, so `on_desk` is never called concurrently.
+async fn scan_adapters<C: Central>(
+    adapters: &[C],
+    timeout: Duration,
+    mut on_desk: impl FnMut(&DiscoveredDesk<C::Peripheral>, Duration) -> ControlFlow<()>,
+) -> Result<Vec<DiscoveredDesk<C::Peripheral>>, Error> {
+    for adapter in adapters {
+        start_scan(adapter).await?;
+    }
+    let started = Instant::now();
+    let mut desks: Vec<DiscoveredDesk<C::Peripheral>> = Vec::new();
+    let scanned = async {
+        loop {
+            let remaining = timeout.saturating_sub(started.elapsed());
+            tokio::time::sleep(SCAN_POLL.min(remaining)).await;
+            for adapter in adapters {
+                for device in matching_desks(adapter, None).await? {
+                    if desks.iter().any(|desk| desk.address == device.address()) {
+                        continue;
+                    }
+                    let desk = discovered(device).await?;
+                    let flow = on_desk(&desk, started.elapsed());
+                    desks.push(desk);
+                    if let ControlFlow::Break(()) = flow {
+                        return Ok(());
+                    }
+                }
+            }
+            if started.elapsed() >= timeout {
+                return Ok::<_, Error>(());
+            }
+        }
+    }
+    .await;
+    for adapter in adapters {
+        adapter.stop_scan().await?;
+    }
+    scanned?;
+    if desks.is_empty() {
+        let mut summary = ScanSummary {
+            adapters: adapters.len(),
+            duration: started.elapsed(),
+            ..ScanSummary::default()
+        };
+        for adapter in adapters {
+            summary.peripherals_seen += peripherals_seen(adapter).await;
+        }
+        Err(Error::not_found(summary))
+    } else {
+        Ok(desks)
+    }
+}
+
+async fn discovered<P: Device>(device: P) -> Result<DiscoveredDesk<P>, Error> {
+    let properties = device.properties().await?.unwrap_or_default();
+    Ok(DiscoveredDesk {
+        name: properties.local_name,
+        address: device.address(),
+        rssi: properties.rssi,
+        tx_power_level: properties.tx_power_level,
+        services: properties.services,
+        manufacturer_data: properties.manufacturer_data,
+        device,
+    })
+}
+
+/// Describe the Bluetooth adapters, as reported by the platform.
+pub async fn adapter_info() -> Result<Vec<String>, Error> {
+    let manager = Manager::new().await?;
+    let mut info = Vec::new();
+    for adapter in manager.adapters().await? {
+        info.push(adapter.adapter_info().await?);
+    }
+    Ok(info)
+}
+
+/// Connect to all `candidates` at once and keep the first one that turns out to be a desk,
+/// e.g. when several peripherals matched or a desk advertises on more than one address. The
+/// others are disconnected again. Fails with the last error if none of them connects.
+pub async fn connect_first<T: DeskTransport + 'static>(
+    candidates: impl IntoIterator<Item = T>,
+) -> Result<Idasen<T>, Error> {
+    let (sender, mut results) = mpsc::unbounded_channel();
+    let attempts: Vec<_> = candidates
+        .into_iter()
+        .map(|candidate| {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let _ = sender.send(Idasen::new(candidate).await);
+            })
+        })
+        .collect();
+    drop(sender);
+
+    let mut error = Error::CannotFindDevice { scan: None };
+    while let Some(result) = results.recv().await {
+        match result {
+            Ok(desk) => {
+                for attempt in &attempts {
+                    attempt.abort();
+                }
+                // Attempts that connected meanwhile would hold on to their desks otherwise.
+                for attempt in attempts {
+                    let _ = attempt.await;
+                }
+                while let Some(result) = results.recv().await {
+                    if let Ok(other) = result {
+                        let _ = other.disconnect().await;
+                    }
+                }
+                return Ok(desk);
+            }
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+/// Get instance of `Idasen` struct. The desk will be discovered by the name. If multiple are
+/// applicable, the first one to connect is used.
+pub async fn get_instance() -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let desks = get_desks(None).await?;
+    connect_first(desks.into_iter().map(BleDesk::new)).await
+}
+
+/// Get the desk set in `config`, e.g. one loaded from `Config::default_path`, or any desk if it
+/// has no address. Limits narrower than the desk's range are applied with `Idasen::restrict`,
+/// so every move stays within them, not just those run with `execute`.
+pub async fn get_instance_from_config(
+    config: &Config,
+) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let desk = IdasenBuilder::from_config(config).build().await?;
+    if config.min_height > MIN_HEIGHT || config.max_height < MAX_HEIGHT {
+        desk.restrict(Restriction {
+            range: config.min_height..=config.max_height,
+            cooldown: Duration::from_secs(0),
+        });
+    }
+    Ok(desk)
+}
+
+/// Get the desk instance by it's Bluetooth MAC address (BD_ADDR).
+/// The address can be obtained also by accessing `mac_addr` property
+/// on instantiated `Idasen` instance.
+pub async fn get_instance_by_mac(mac: &str) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    let addr = mac.parse::<BDAddr>();
+    match addr {
+        Ok(addr) => {
+            let desks = get_desks(Some(addr)).await?;
+            connect_first(desks.into_iter().map(BleDesk::new)).await
+        }
+        Err(err) => Err(Error::MacAddrParseFailed(err)),
+    }
+}
+
+/// Get the desk advertising exactly `name`, e.g. `Desk 7412`, for desks whose address changes
+/// between sessions. If several desks share the name, the first one to connect is used.
+pub async fn get_instance_by_name(name: &str) -> Result<Idasen<BleDesk<impl Device>>, Error> {
+    IdasenBuilder::new().name(name).build().await
+}
+
+/// Get the desk by its MAC address like `get_instance_by_mac`, reaching it through every
+/// adapter that can see it and switching between them when one fails. The receiver gets an
+/// event for each switch.
+pub async fn get_instance_with_failover(
+    mac: &str,
+) -> Result<
+    (
+        Idasen<Failover<BleDesk<impl Device>>>,
+        mpsc::UnboundedReceiver<AdapterSwitch>,
+    ),
+    Error,
+> {
+    let addr = mac.parse::<BDAddr>().map_err(Error::MacAddrParseFailed)?;
+    let desks = get_desks(Some(addr)).await?;
+    let (failover, switches) = Failover::new(desks.into_iter().map(BleDesk::new));
+    Ok((Idasen::new(failover).await?, switches))
+}
@@ -0,0 +1,114 @@
+use crate::ScanSummary;
+use btleplug::api::ParseBDAddrError;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// `scan` tells what the search covered, `None` if no scan took place, e.g. when
+    /// `connect_first` got no candidates.
+    #[error("Cannot find the device{}.", describe_scan(.scan))]
+    CannotFindDevice { scan: Option<ScanSummary> },
+
+    #[error("Cannot connect to the device.")]
+    Connectionerrored,
+
+    #[error("Cannot scan for devices.")]
+    Scanerrored,
+
+    #[error("No Bluetooth adapter found.")]
+    NoAdapter,
+
+    /// The adapter is there but turned off, or its radio is still starting up, see
+    /// `wait_for_bluetooth`.
+    #[error("Bluetooth is turned off or not ready yet: {source}")]
+    BluetoothDisabled { source: btleplug::Error },
+
+    #[error("Permission denied.")]
+    PermissionDenied,
+
+    #[error("Cannot discover Bluetooth characteristics.")]
+    CharacteristicsDiscoveryFailed,
+
+    #[error("Bluetooth characteristics not found: '{}'.", _0)]
+    CharacteristicsNotFound(String),
+
+    /// All positions in tenth of millimetres.
+    #[error("Desired position {requested} has to be between {min} and {max}.")]
+    PositionNotInRange { requested: u16, min: u16, max: u16 },
+
+    #[error("The desk must be paired first, pair it in the system's Bluetooth settings.")]
+    PairingRequired,
+
+    #[error("Cannot subscribe to read position.")]
+    CannotSubscribePosition,
+
+    #[error("Cannot read position.")]
+    CannotReadPosition,
+
+    #[error("Skip limit for the day has been reached.")]
+    SkipLimitReached,
+
+    #[error("Replayed trace has no more values to read.")]
+    TraceExhausted,
+
+    #[error("Cannot start the async runtime.")]
+    CannotStartRuntime,
+
+    #[error("The desk did not move as commanded.")]
+    DeskNotMoving,
+
+    #[error("The desk did not reach its target in time, it stopped at {last_position}.")]
+    MoveTimeout { last_position: u16 },
+
+    #[error("The move was interrupted by another command.")]
+    MoveInterrupted,
+
+    #[error("Another move is in progress.")]
+    DeskBusy,
+
+    #[error("The desk was moved by hand during the move.")]
+    ManualOverride,
+
+    #[error("The desk's control task has stopped.")]
+    DeskTaskStopped,
+
+    #[error("Moves are restricted, wait for the cooldown to end.")]
+    CoolingDown,
+
+    #[error("No preset named '{}'.", _0)]
+    UnknownPreset(String),
+
+    #[error("Cannot write the audit log.")]
+    CannotWriteAuditLog,
+
+    #[error("Still failing after {attempts} attempts: {source}")]
+    RetriesExhausted { attempts: u32, source: Box<Error> },
+
+    #[error("errored to parse mac address.")]
+    MacAddrParseFailed(#[from] ParseBDAddrError),
+
+    #[error("bluetooth error {0}")]
+    BtlePlugError(#[from] btleplug::Error),
+}
+
+impl Error {
+    pub(crate) fn not_found(scan: ScanSummary) -> Self {
+        Error::CannotFindDevice { scan: Some(scan) }
+    }
+
+    pub(crate) fn out_of_range(requested: u16, range: &RangeInclusive<u16>) -> Self {
+        Error::PositionNotInRange {
+            requested,
+            min: *range.start(),
+            max: *range.end(),
+        }
+    }
+}
+
+fn describe_scan(scan: &Option<ScanSummary>) -> String {
+    match scan {
+        Some(scan) => format!(", {}", scan),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,190 @@
+use crate::schedule::{Rule, ScheduleEvent, TimeOfDay};
+use crate::{AdapterSwitch, BDAddr, Direction, Error, Posture};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of the `EventRecord` wire format, raised whenever a change could break a reader.
+/// New event types and new fields are added without raising it, so readers should skip what
+/// they don't know.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Something that happened to a desk, in one format for every delivery channel, e.g. an IPC
+/// socket, a log file or a webhook.
+///
+/// With the `serde` feature an event serializes to an object whose `type` field names the
+/// event in snake case, next to the event's own fields:
+///
+/// ```text
+/// {"type": "position_changed", "position": 7200, "speed": 0}
+/// {"type": "reminder_fired", "reminder": {"kind": "schedule", "at": "09:00", "position": 11000}}
+/// ```
+///
+/// Positions are in tenth of millimetres, speeds in hundredths of millimetres per second and
+/// times in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum DeskEvent {
+    /// The desk can be reached, also again after `Disconnected`.
+    Connected,
+    /// The connection to the desk was lost or closed.
+    Disconnected,
+    PositionChanged {
+        position: u16,
+        speed: i16,
+    },
+    PostureChanged {
+        posture: Posture,
+    },
+    /// The desk started moving without a command from this library, because its paddle or
+    /// memory buttons were pressed or another app moved it. The desk doesn't tell these apart.
+    MovedByHand {
+        direction: Direction,
+    },
+    /// The desk is now reached through another adapter, see `Failover`.
+    AdapterSwitched {
+        from: usize,
+        to: usize,
+    },
+    /// A schedule started moving the desk.
+    ReminderFired {
+        reminder: Reminder,
+    },
+    /// A move to `target` started, see `Idasen::events`.
+    MoveStarted {
+        target: u16,
+    },
+    /// A move reached its target, one started by a reminder or, from `Idasen::events`, any
+    /// move to a target.
+    MoveCompleted,
+    /// A move to a target ended before reaching it, because it failed or was dropped.
+    MoveAborted,
+    /// A scheduled move was postponed.
+    Snoozed {
+        position: u16,
+        until: u64,
+    },
+    /// A scheduled move was due but had been skipped.
+    Skipped {
+        position: u16,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// What made the desk move in a `DeskEvent::ReminderFired`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Reminder {
+    /// A schedule rule, see `schedule::Rule`.
+    Schedule { at: TimeOfDay, position: u16 },
+    /// The user had been sitting for `sitting_for` milliseconds, see `schedule::AutoStand`.
+    AutoStand { sitting_for: u64 },
+    /// The user arrived, see `schedule::Arrival`.
+    Arrival,
+}
+
+/// A `DeskEvent` with the envelope every channel sends it in: the schema version, when it
+/// happened and the address of the desk, if known.
+///
+/// With the `serde` feature the event's fields are inlined next to the envelope's:
+///
+/// ```text
+/// {"version": 1, "timestamp": 1700000000000, "desk": "EC:86:F6:00:00:01", "type": "connected"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventRecord {
+    pub version: u32,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub desk: Option<String>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub event: DeskEvent,
+}
+
+impl EventRecord {
+    /// Record `event` as happening now.
+    ///
+    /// ```
+    /// use idasen::{BDAddr, DeskEvent, EventRecord, EVENT_SCHEMA_VERSION};
+    ///
+    /// let desk = BDAddr::from([0xEC, 0x86, 0xF6, 0x00, 0x00, 0x01]);
+    /// let record = EventRecord::new(Some(desk), DeskEvent::Connected);
+    /// assert_eq!(record.version, EVENT_SCHEMA_VERSION);
+    /// assert_eq!(record.desk.as_deref(), Some("EC:86:F6:00:00:01"));
+    /// ```
+    pub fn new(desk: Option<BDAddr>, event: DeskEvent) -> Self {
+        Self {
+            version: EVENT_SCHEMA_VERSION,
+            timestamp: millis_since_epoch(SystemTime::now()),
+            desk: desk.map(|desk| desk.to_string()),
+            event,
+        }
+    }
+}
+
+impl From<&Error> for DeskEvent {
+    fn from(error: &Error) -> Self {
+        DeskEvent::Error {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<AdapterSwitch> for DeskEvent {
+    fn from(switch: AdapterSwitch) -> Self {
+        DeskEvent::AdapterSwitched {
+            from: switch.from,
+            to: switch.to,
+        }
+    }
+}
+
+impl From<ScheduleEvent> for DeskEvent {
+    fn from(event: ScheduleEvent) -> Self {
+        match event {
+            ScheduleEvent::MoveStarted {
+                rule: Rule { at, position },
+            } => DeskEvent::ReminderFired {
+                reminder: Reminder::Schedule { at, position },
+            },
+            ScheduleEvent::AutoStandStarted { sitting_for } => DeskEvent::ReminderFired {
+                reminder: Reminder::AutoStand {
+                    sitting_for: sitting_for.as_millis() as u64,
+                },
+            },
+            ScheduleEvent::ArrivalStarted => DeskEvent::ReminderFired {
+                reminder: Reminder::Arrival,
+            },
+            ScheduleEvent::MoveCompleted { .. }
+            | ScheduleEvent::AutoStandCompleted
+            | ScheduleEvent::ArrivalCompleted => DeskEvent::MoveCompleted,
+            ScheduleEvent::MoveFailed { error, .. }
+            | ScheduleEvent::AutoStandFailed { error }
+            | ScheduleEvent::ArrivalFailed { error } => DeskEvent::from(&error),
+            ScheduleEvent::Snoozed { rule, until } => DeskEvent::Snoozed {
+                position: rule.position,
+                until: millis_since_epoch(until),
+            },
+            ScheduleEvent::Skipped { rule } => DeskEvent::Skipped {
+                position: rule.position,
+            },
+        }
+    }
+}
+
+fn millis_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,201 @@
+use crate::{DeskTransport, Error, NotificationStream};
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, Characteristic, WriteType};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Emitted by `Failover` when it moved over to another transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdapterSwitch {
+    /// Index of the transport that failed, in the order passed to `Failover::new`.
+    pub from: usize,
+    /// Index of the transport used from now on.
+    pub to: usize,
+}
+
+/// `DeskTransport` over the same desk seen through several adapters, switching to another one
+/// when the adapter in use fails, e.g. because the dongle was unplugged or its driver crashed.
+///
+/// A lost connection is retried on the current adapter first, as that is usually the desk
+/// moving out of range or the computer waking up. Other Bluetooth errors, and reconnects that
+/// fail, move on to the next adapter that can connect to the desk. See
+/// `get_instance_with_failover`.
+#[derive(Debug)]
+pub struct Failover<T: DeskTransport> {
+    transports: Vec<T>,
+    current: Mutex<usize>,
+    // Characteristics to subscribe to again after switching.
+    subscriptions: Mutex<Vec<Uuid>>,
+    events: mpsc::UnboundedSender<AdapterSwitch>,
+}
+
+impl<T: DeskTransport> Failover<T> {
+    /// Create the transport together with the receiving end of its switch events. The first
+    /// transport is used until it fails.
+    pub fn new(
+        transports: impl IntoIterator<Item = T>,
+    ) -> (Self, mpsc::UnboundedReceiver<AdapterSwitch>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        let failover = Self {
+            transports: transports.into_iter().collect(),
+            current: Mutex::new(0),
+            subscriptions: Mutex::new(Vec::new()),
+            events,
+        };
+        (failover, receiver)
+    }
+
+    /// Index of the transport in use.
+    pub fn current(&self) -> usize {
+        *self.current.lock().unwrap()
+    }
+
+    pub fn transports(&self) -> &[T] {
+        &self.transports
+    }
+
+    fn transport(&self) -> Result<(usize, &T), Error> {
+        let index = self.current();
+        let transport = self
+            .transports
+            .get(index)
+            .ok_or(Error::CannotFindDevice { scan: None })?;
+        Ok((index, transport))
+    }
+
+    // Connect through the transports after `failed` in turn, keeping the first that works.
+    async fn switch_from(&self, failed: usize, resubscribe: bool) -> Result<(), Error> {
+        let mut error = Error::CannotFindDevice { scan: None };
+        for offset in 1..self.transports.len() {
+            let index = (failed + offset) % self.transports.len();
+            match self.connect_through(index, resubscribe).await {
+                Ok(()) => {
+                    *self.current.lock().unwrap() = index;
+                    // Nobody listening is not an error.
+                    let _ = self.events.send(AdapterSwitch {
+                        from: failed,
+                        to: index,
+                    });
+                    return Ok(());
+                }
+                Err(err) => error = err,
+            }
+        }
+        Err(error)
+    }
+
+    async fn connect_through(&self, index: usize, resubscribe: bool) -> Result<(), Error> {
+        let transport = &self.transports[index];
+        transport.connect().await?;
+        if resubscribe {
+            let subscriptions = self.subscriptions.lock().unwrap().clone();
+            for uuid in subscriptions {
+                transport.subscribe(uuid).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Run `operation` on the current transport, and once more on another one if it failed
+    // with an error other than a lost connection, which `Idasen` reconnects itself.
+    async fn failing_over<'a, R, F, O>(&'a self, operation: F) -> Result<R, Error>
+    where
+        F: Fn(&'a T) -> O,
+        O: Future<Output = Result<R, Error>> + 'a,
+    {
+        let (index, transport) = self.transport()?;
+        match operation(transport).await {
+            Err(Error::BtlePlugError(error)) if !lost_connection(&error) => {
+                if self.switch_from(index, true).await.is_err() {
+                    return Err(Error::BtlePlugError(error));
+                }
+                operation(self.transport()?.1).await
+            }
+            result => result,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: DeskTransport> DeskTransport for Failover<T> {
+    fn address(&self) -> BDAddr {
+        self.transports
+            .first()
+            .map(|transport| transport.address())
+            .unwrap_or_default()
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        let (index, transport) = self.transport()?;
+        match transport.connect().await {
+            Ok(()) => Ok(()),
+            // `Idasen` subscribes again after connecting.
+            Err(error) => self.switch_from(index, false).await.map_err(|_| error),
+        }
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.transport()?.1.disconnect().await
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        match self.transport() {
+            Ok((_, transport)) => transport.has_characteristic(uuid),
+            Err(_) => false,
+        }
+    }
+
+    fn characteristics(&self) -> Vec<Characteristic> {
+        match self.transport() {
+            Ok((_, transport)) => transport.characteristics(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        self.failing_over(|transport| transport.read(uuid)).await
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8], write_type: WriteType) -> Result<(), Error> {
+        self.failing_over(|transport| transport.write(uuid, data, write_type))
+            .await
+    }
+
+    async fn subscribe(&self, uuid: Uuid) -> Result<(), Error> {
+        {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            if !subscriptions.contains(&uuid) {
+                subscriptions.push(uuid);
+            }
+        }
+        self.failing_over(|transport| transport.subscribe(uuid))
+            .await
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        self.failing_over(|transport| transport.notifications())
+            .await
+    }
+
+    async fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.transport()?.1.rssi().await
+    }
+
+    async fn name(&self) -> Result<Option<String>, Error> {
+        self.transport()?.1.name().await
+    }
+
+    async fn manufacturer_data(&self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        self.transport()?.1.manufacturer_data().await
+    }
+}
+
+fn lost_connection(error: &btleplug::Error) -> bool {
+    matches!(
+        error,
+        btleplug::Error::NotConnected | btleplug::Error::TimedOut(_)
+    )
+}
@@ -0,0 +1,238 @@
+//! C ABI, enabled by the `cdylib` feature. The declarations are in `include/idasen.h`.
+//!
+//! Functions returning `c_int` return 0 on success and -1 on failure, `idasen_last_error`
+//! then describes the failure. All calls block until the desk has answered.
+
+use crate::{get_desks, BDAddr, BleDesk, DeskTransport, Idasen};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fmt::Display;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+/// Connected desk, created by `idasen_connect` and released by `idasen_free`.
+pub struct IdasenDesk {
+    runtime: Runtime,
+    desk: Arc<Idasen<Box<dyn DeskTransport>>>,
+    callback: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Called with the position in tenth of millimetres, the speed and the registered user data.
+pub type IdasenPositionCallback = extern "C" fn(position: u16, speed: i16, user_data: *mut c_void);
+
+// The user data is only handed back to the callback, thread safety is up to the caller.
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(error: impl Display) {
+    let message = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn status<E: Display>(result: Result<(), E>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            set_error(error);
+            -1
+        }
+    }
+}
+
+/// Message of the last failure on the calling thread, or null. Valid until the next call on
+/// the same thread.
+#[no_mangle]
+pub extern "C" fn idasen_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| match *last.borrow() {
+        Some(ref message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Connect to the desk with the given MAC address, or to any desk if `mac` is null. Returns
+/// null on failure.
+///
+/// # Safety
+///
+/// `mac` has to be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_connect(mac: *const c_char) -> *mut IdasenDesk {
+    let mac = if mac.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(mac).to_str().map(str::parse::<BDAddr>) {
+            Ok(Ok(mac)) => Some(mac),
+            _ => {
+                set_error("invalid MAC address");
+                return ptr::null_mut();
+            }
+        }
+    };
+
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            set_error(error);
+            return ptr::null_mut();
+        }
+    };
+    let desk = runtime.block_on(async {
+        let device = get_desks(mac)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(crate::Error::CannotFindDevice { scan: None })?;
+        let transport: Box<dyn DeskTransport> = Box::new(BleDesk::new(device));
+        Idasen::new(transport).await
+    });
+    match desk {
+        Ok(desk) => Box::into_raw(Box::new(IdasenDesk {
+            runtime,
+            desk: Arc::new(desk),
+            callback: Mutex::new(None),
+        })),
+        Err(error) => {
+            set_error(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Disconnect and release the desk.
+///
+/// # Safety
+///
+/// `desk` has to be null or returned by `idasen_connect`, and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_free(desk: *mut IdasenDesk) {
+    if desk.is_null() {
+        return;
+    }
+    let desk = Box::from_raw(desk);
+    if let Some(release) = desk.desk.release() {
+        desk.runtime.block_on(release);
+    }
+    // Shutting down the runtime also stops the position callback.
+    drop(desk);
+}
+
+/// Store the current position in tenth of millimetres in `position`.
+///
+/// # Safety
+///
+/// `desk` has to be returned by `idasen_connect` and `position` has to be writable.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_position(desk: *const IdasenDesk, position: *mut u16) -> c_int {
+    let desk = match desk.as_ref() {
+        Some(desk) => desk,
+        None => return status(Err("desk is null")),
+    };
+    match desk.runtime.block_on(desk.desk.position()) {
+        Ok(current) => {
+            *position = current;
+            0
+        }
+        Err(error) => status(Err(error)),
+    }
+}
+
+/// Move the desk to `position` in tenth of millimetres, returning once it got there.
+///
+/// # Safety
+///
+/// `desk` has to be returned by `idasen_connect`.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_move_to(desk: *const IdasenDesk, position: u16) -> c_int {
+    match desk.as_ref() {
+        Some(desk) => status(desk.runtime.block_on(desk.desk.move_to(position))),
+        None => status(Err("desk is null")),
+    }
+}
+
+/// Start moving the desk up.
+///
+/// # Safety
+///
+/// `desk` has to be returned by `idasen_connect`.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_up(desk: *const IdasenDesk) -> c_int {
+    match desk.as_ref() {
+        Some(desk) => status(desk.runtime.block_on(desk.desk.up())),
+        None => status(Err("desk is null")),
+    }
+}
+
+/// Start moving the desk down.
+///
+/// # Safety
+///
+/// `desk` has to be returned by `idasen_connect`.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_down(desk: *const IdasenDesk) -> c_int {
+    match desk.as_ref() {
+        Some(desk) => status(desk.runtime.block_on(desk.desk.down())),
+        None => status(Err("desk is null")),
+    }
+}
+
+/// Stop the desk.
+///
+/// # Safety
+///
+/// `desk` has to be returned by `idasen_connect`.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_stop(desk: *const IdasenDesk) -> c_int {
+    match desk.as_ref() {
+        Some(desk) => status(desk.runtime.block_on(desk.desk.stop())),
+        None => status(Err("desk is null")),
+    }
+}
+
+/// Call `callback` from a background thread on every position change, replacing the
+/// previous callback. A null `callback` unregisters it.
+///
+/// # Safety
+///
+/// `desk` has to be returned by `idasen_connect`. `user_data` is passed to `callback` as is
+/// and has to stay valid while the callback is registered.
+#[no_mangle]
+pub unsafe extern "C" fn idasen_set_position_callback(
+    desk: *const IdasenDesk,
+    callback: Option<IdasenPositionCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    let desk = match desk.as_ref() {
+        Some(desk) => desk,
+        None => return status(Err("desk is null")),
+    };
+    let mut registered = desk.callback.lock().unwrap();
+    if let Some(previous) = registered.take() {
+        previous.abort();
+    }
+
+    if let Some(callback) = callback {
+        let idasen = desk.desk.clone();
+        let user_data = UserData(user_data);
+        *registered = Some(desk.runtime.spawn(async move {
+            if let Ok(stream) = idasen.position_and_speed_stream().await {
+                tokio::pin!(stream);
+                while let Some(position_speed) = stream.next().await {
+                    callback(position_speed.position, position_speed.speed, user_data.0);
+                }
+            }
+        }));
+    }
+    0
+}
@@ -0,0 +1,115 @@
+use crate::{
+    DeskTransport, Error, Idasen, FIRMWARE_REVISION_UUID, MANUFACTURER_NAME_UUID,
+    MODEL_NUMBER_UUID, SERIAL_NUMBER_UUID,
+};
+use uuid::Uuid;
+
+/// Misbehaviour of some firmware revisions that apps may need to work around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FirmwareIssue {
+    /// The desk keeps moving after a stop command.
+    IgnoresStop,
+    /// The controller needs a command before it reacts to movement commands.
+    NeedsWakeUp,
+}
+
+/// What the desk reports in its standard Device Information service, e.g. for an inventory of
+/// desks. Each field is `None` if the desk doesn't offer it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub firmware_revision: Option<String>,
+}
+
+/// Known problem of the listed firmware revisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareAdvisory {
+    pub revisions: &'static [&'static str],
+    pub issue: FirmwareIssue,
+    pub description: &'static str,
+}
+
+impl FirmwareAdvisory {
+    pub fn applies_to(&self, revision: &str) -> bool {
+        self.revisions.contains(&revision.trim())
+    }
+}
+
+/// Advisories checked by `Idasen::firmware_advisories`. Only revisions with confirmed reports
+/// are listed; there are none yet.
+pub const FIRMWARE_ADVISORIES: &[FirmwareAdvisory] = &[];
+
+/// Advisories from `table` applying to `revision`.
+///
+/// ```
+/// use idasen::{advisories_for, FirmwareAdvisory, FirmwareIssue};
+///
+/// let table = [FirmwareAdvisory {
+///     revisions: &["1.2.3"],
+///     issue: FirmwareIssue::IgnoresStop,
+///     description: "Overshoots, send stop twice.",
+/// }];
+/// assert_eq!(advisories_for("1.2.3", &table), vec![&table[0]]);
+/// assert!(advisories_for("1.2.4", &table).is_empty());
+/// ```
+pub fn advisories_for<'a>(
+    revision: &str,
+    table: &'a [FirmwareAdvisory],
+) -> Vec<&'a FirmwareAdvisory> {
+    table
+        .iter()
+        .filter(|advisory| advisory.applies_to(revision))
+        .collect()
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Firmware revision reported by the desk, `None` if it doesn't offer one. Read when
+    /// connecting and kept, so calling this again doesn't cost a Bluetooth round trip.
+    pub async fn firmware_revision(&self) -> Result<Option<String>, Error> {
+        if let Some(revision) = self.known_firmware_revision().lock().unwrap().clone() {
+            return Ok(revision);
+        }
+        let revision = self.read_info(FIRMWARE_REVISION_UUID).await?;
+        *self.known_firmware_revision().lock().unwrap() = Some(revision.clone());
+        Ok(revision)
+    }
+
+    /// Manufacturer, model, serial number and firmware revision reported by the desk.
+    pub async fn device_info(&self) -> Result<DeviceInfo, Error> {
+        Ok(DeviceInfo {
+            manufacturer: self.read_info(MANUFACTURER_NAME_UUID).await?,
+            model: self.read_info(MODEL_NUMBER_UUID).await?,
+            serial_number: self.read_info(SERIAL_NUMBER_UUID).await?,
+            firmware_revision: self.firmware_revision().await?,
+        })
+    }
+
+    /// Known problems of the desk's firmware, from `FIRMWARE_ADVISORIES`.
+    pub async fn firmware_advisories(&self) -> Result<Vec<&'static FirmwareAdvisory>, Error> {
+        Ok(match self.firmware_revision().await? {
+            Some(revision) => advisories_for(&revision, FIRMWARE_ADVISORIES),
+            None => Vec::new(),
+        })
+    }
+
+    async fn read_info(&self, uuid: Uuid) -> Result<Option<String>, Error> {
+        if !self.has_characteristic(uuid) {
+            return Ok(None);
+        }
+        let value = self.read_raw(uuid).await?;
+        Ok(Some(info_string(&value)))
+    }
+}
+
+// Device Information strings are sometimes padded with NUL bytes.
+pub(crate) fn info_string(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,167 @@
+use crate::{BDAddr, Config, DeskHandle, DeskStatus, Error};
+
+/// Named set of desks sharing presets and height limits, e.g. all desks in a training room,
+/// managed as one unit.
+///
+/// Commands go to all desks at once, each desk's outcome is reported separately so one
+/// unreachable desk doesn't hide that the others moved.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Config, DeskGroup, Idasen};
+///
+/// let mut config = Config::default();
+/// config.presets.push(("stand".to_string(), 11000));
+/// let mut room = DeskGroup::new("training-room", config);
+/// for position in [7000, 9000] {
+///     room.add(Idasen::new(MockDesk::new(position)).await.unwrap().spawn());
+/// }
+///
+/// for (_, result) in room.preset("stand").await.unwrap() {
+///     result.unwrap();
+/// }
+/// let status = room.status().await;
+/// assert!(status.is_level(20));
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeskGroup {
+    pub name: String,
+    /// Presets and limits applying to every desk in the group.
+    pub config: Config,
+    desks: Vec<DeskHandle>,
+}
+
+/// Snapshot of all desks in a group, see `DeskGroup::status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupStatus {
+    pub desks: Vec<DeskStatus>,
+}
+
+impl DeskGroup {
+    pub fn new(name: impl Into<String>, config: Config) -> Self {
+        Self {
+            name: name.into(),
+            config,
+            desks: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, desk: DeskHandle) {
+        self.desks.push(desk);
+    }
+
+    /// Remove the desk with the given address, returning its handle.
+    pub fn remove(&mut self, mac_addr: BDAddr) -> Option<DeskHandle> {
+        let index = self
+            .desks
+            .iter()
+            .position(|desk| desk.mac_addr == mac_addr)?;
+        Some(self.desks.remove(index))
+    }
+
+    pub fn desks(&self) -> &[DeskHandle] {
+        &self.desks
+    }
+
+    /// The desk with the given address, e.g. to query its status alone.
+    pub fn desk(&self, mac_addr: BDAddr) -> Option<&DeskHandle> {
+        self.desks.iter().find(|desk| desk.mac_addr == mac_addr)
+    }
+
+    /// Move all desks to `position`. Fails without moving any desk if the position is outside
+    /// the group's limits.
+    pub async fn move_to(&self, position: u16) -> Result<Vec<(BDAddr, Result<(), Error>)>, Error> {
+        if !self.config.allows(position) {
+            return Err(Error::out_of_range(position, &self.config.limits()));
+        }
+        let moves: Vec<_> = self
+            .desks
+            .iter()
+            .map(|desk| (desk.mac_addr, desk.move_to(position)))
+            .collect();
+        let mut results = Vec::new();
+        for (mac_addr, result) in moves {
+            results.push((mac_addr, result.await));
+        }
+        Ok(results)
+    }
+
+    /// Move all desks to the group's preset called `name`.
+    pub async fn preset(&self, name: &str) -> Result<Vec<(BDAddr, Result<(), Error>)>, Error> {
+        let position = self
+            .config
+            .preset(name)
+            .ok_or_else(|| Error::UnknownPreset(name.to_string()))?;
+        self.move_to(position).await
+    }
+
+    pub async fn stop(&self) -> Vec<(BDAddr, Result<(), Error>)> {
+        let stops: Vec<_> = self
+            .desks
+            .iter()
+            .map(|desk| (desk.mac_addr, desk.stop()))
+            .collect();
+        let mut results = Vec::new();
+        for (mac_addr, result) in stops {
+            results.push((mac_addr, result.await));
+        }
+        results
+    }
+
+    /// Query all desks at once over their open connections, see `DeskHandle::status`. A desk
+    /// whose task has stopped is reported as not connected.
+    pub async fn status(&self) -> GroupStatus {
+        let queries: Vec<_> = self
+            .desks
+            .iter()
+            .map(|desk| (desk.mac_addr, desk.status()))
+            .collect();
+        let mut desks = Vec::new();
+        for (mac_addr, status) in queries {
+            desks.push(status.await.unwrap_or_else(|error| DeskStatus {
+                mac_addr,
+                connected: false,
+                position: None,
+                speed: None,
+                moving: None,
+                posture: None,
+                rssi: None,
+                last_error: Some(error.to_string()),
+                firmware_revision: None,
+            }));
+        }
+        GroupStatus { desks }
+    }
+}
+
+impl GroupStatus {
+    /// Number of desks that could be read.
+    pub fn reachable(&self) -> usize {
+        self.known().count()
+    }
+
+    pub fn lowest(&self) -> Option<u16> {
+        self.known().min()
+    }
+
+    pub fn highest(&self) -> Option<u16> {
+        self.known().max()
+    }
+
+    /// Whether all desks could be read and are within `tolerance` of each other.
+    pub fn is_level(&self, tolerance: u16) -> bool {
+        match (self.lowest(), self.highest()) {
+            (Some(lowest), Some(highest)) => {
+                self.reachable() == self.desks.len() && highest - lowest <= tolerance
+            }
+            _ => self.desks.is_empty(),
+        }
+    }
+
+    fn known(&self) -> impl Iterator<Item = u16> + '_ {
+        self.desks.iter().filter_map(|desk| desk.position)
+    }
+}
@@ -0,0 +1,365 @@
+use crate::monitor::STANDING_ABOVE;
+use crate::{BDAddr, DeskTransport, Error, Idasen, PositionSpeed, Posture};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Cloneable handle to a desk controlled from its own task, see `Idasen::spawn`.
+///
+/// Each desk's moves and reads run on separate tasks, so with several desks a slow or
+/// disconnected one doesn't hold up the others. A new move replaces the one in progress, or
+/// waits or is rejected as set with `Idasen::spawn_with`, and `stop` interrupts all of them;
+/// an interrupted `move_to` fails with `Error::MoveInterrupted`.
+/// Dropping a `move_to` future stops the desk, like dropping `Idasen::move_to`.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Idasen};
+///
+/// let desks = vec![MockDesk::new(7000), MockDesk::new(9000)];
+/// let mut handles = Vec::new();
+/// for desk in desks {
+///     handles.push(Idasen::new(desk).await.unwrap().spawn());
+/// }
+/// let moves: Vec<_> = handles.iter().map(|desk| desk.move_to(8000)).collect();
+/// for result in moves {
+///     result.await.unwrap();
+/// }
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeskHandle {
+    pub mac_addr: BDAddr,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+/// Snapshot of a desk for dashboards, see `Idasen::status` and `DeskHandle::status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeskStatus {
+    /// Written as `EC:86:F6:00:00:01` when serialized.
+    #[cfg_attr(feature = "serde", serde(with = "address_string"))]
+    pub mac_addr: BDAddr,
+    /// Whether the position could be read just now.
+    pub connected: bool,
+    /// Desk height in tenth millimeters, `None` if it couldn't be read.
+    pub position: Option<u16>,
+    /// Speed as reported by the desk, read together with the position.
+    pub speed: Option<i16>,
+    /// Which way the desk is moving, `None` while resting or if it couldn't be read.
+    pub moving: Option<Direction>,
+    /// Heights above the middle of the range count as standing.
+    pub posture: Option<Posture>,
+    /// Signal strength in dBm, `None` if the adapter doesn't report it.
+    pub rssi: Option<i16>,
+    /// The most recent failure of a command sent through a handle, or of reading the position
+    /// for `Idasen::status`.
+    pub last_error: Option<String>,
+    pub firmware_revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// Direction of a speed reported by the desk, `None` if it is resting.
+    pub fn of(speed: i16) -> Option<Self> {
+        match speed {
+            0 => None,
+            speed if speed > 0 => Some(Direction::Up),
+            _ => Some(Direction::Down),
+        }
+    }
+}
+
+/// What a `DeskHandle::move_to` does while another move is in progress, see
+/// `Idasen::spawn_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MovePolicy {
+    /// Interrupt the move in progress, which fails with `Error::MoveInterrupted`.
+    Supersede,
+    /// Start once the moves sent before are done.
+    Queue,
+    /// Fail with `Error::DeskBusy`, leaving the move in progress alone.
+    Reject,
+}
+
+#[derive(Debug)]
+enum Command {
+    MoveTo(u16, oneshot::Sender<Result<(), Error>>),
+    Stop(oneshot::Sender<Result<(), Error>>),
+    Position(oneshot::Sender<Result<u16, Error>>),
+    Status(oneshot::Sender<DeskStatus>),
+}
+
+// Kept by the control task for `DeskHandle::status`.
+#[derive(Debug, Default)]
+struct Known {
+    last_error: Option<String>,
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Read the height, speed and signal strength of the desk in one call, e.g. to render a
+    /// dashboard. A failed read is reported as not connected, with the error in `last_error`.
+    pub async fn status(&self) -> DeskStatus {
+        let firmware_revision = self.firmware_revision().await.ok().flatten();
+        let (mut status, read) = read_status(self, firmware_revision).await;
+        status.last_error = read.err().map(|error| error.to_string());
+        status
+    }
+}
+
+impl<T: DeskTransport + 'static> Idasen<T> {
+    /// Move the desk onto its own task and control it through the returned handle. The task
+    /// ends once all handles are dropped. A new move supersedes the one in progress.
+    pub fn spawn(self) -> DeskHandle {
+        self.spawn_with(MovePolicy::Supersede)
+    }
+
+    /// Like `spawn`, handling moves sent while another one is in progress as set in `policy`,
+    /// e.g. to queue the moves of several parts of an application instead of letting them
+    /// interrupt each other.
+    pub fn spawn_with(self, policy: MovePolicy) -> DeskHandle {
+        let mac_addr = self.mac_addr;
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(control(Arc::new(self), receiver, policy));
+        DeskHandle { mac_addr, commands }
+    }
+}
+
+// The commands are sent right away, not when the returned futures are first polled, so moves
+// of several desks started one after another run at the same time.
+impl DeskHandle {
+    /// Move desk to a desired position, see `Idasen::move_to`.
+    pub fn move_to(&self, target_position: u16) -> impl Future<Output = Result<(), Error>> {
+        let result = self.request(|reply| Command::MoveTo(target_position, reply));
+        async move { result?.await.unwrap_or(Err(Error::MoveInterrupted)) }
+    }
+
+    /// Interrupt a move in progress and stop the desk.
+    pub fn stop(&self) -> impl Future<Output = Result<(), Error>> {
+        let result = self.request(Command::Stop);
+        async move { result?.await.unwrap_or(Err(Error::DeskTaskStopped)) }
+    }
+
+    /// Return the desk height in tenth millimeters, also while moving.
+    pub fn position(&self) -> impl Future<Output = Result<u16, Error>> {
+        let result = self.request(Command::Position);
+        async move { result?.await.unwrap_or(Err(Error::DeskTaskStopped)) }
+    }
+
+    /// Read the desk's position and report it together with the last error and the firmware
+    /// revision. Runs alongside a move in progress, like `position`.
+    pub fn status(&self) -> impl Future<Output = Result<DeskStatus, Error>> {
+        let result = self.request(Command::Status);
+        async move { result?.await.map_err(|_| Error::DeskTaskStopped) }
+    }
+
+    fn request<R>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<R>) -> Command,
+    ) -> Result<oneshot::Receiver<R>, Error> {
+        let (reply, result) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| Error::DeskTaskStopped)?;
+        Ok(result)
+    }
+}
+
+async fn control<T: DeskTransport + 'static>(
+    desk: Arc<Idasen<T>>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    policy: MovePolicy,
+) {
+    let mut moves: Vec<JoinHandle<()>> = Vec::new();
+    // Held by every move task until it ends, so more than one reference means busy.
+    let running = Arc::new(());
+    // Resolves once the last move sent has ended, when the next queued one starts.
+    let mut last_done: Option<oneshot::Receiver<()>> = None;
+    let known = Arc::new(Mutex::new(Known::default()));
+    while let Some(command) = commands.recv().await {
+        match command {
+            Command::MoveTo(target_position, mut reply) => {
+                let busy = Arc::strong_count(&running) > 1;
+                if !busy {
+                    moves.clear();
+                }
+                match policy {
+                    MovePolicy::Supersede => abort_all(&mut moves),
+                    MovePolicy::Reject if busy => {
+                        let _ = reply.send(Err(Error::DeskBusy));
+                        continue;
+                    }
+                    _ => {}
+                }
+                let previous = last_done.take().filter(|_| policy == MovePolicy::Queue);
+                let (done, next) = oneshot::channel::<()>();
+                last_done = Some(next);
+                let running = running.clone();
+                let desk = desk.clone();
+                let known = known.clone();
+                moves.push(tokio::spawn(async move {
+                    let _running = running;
+                    let _done = done;
+                    let moving = UnlessAbandoned {
+                        reply: &mut reply,
+                        future: Box::pin(async move {
+                            if let Some(previous) = previous {
+                                let _ = previous.await;
+                            }
+                            desk.move_to(target_position).await
+                        }),
+                    };
+                    // Otherwise the move was dropped, which stops the desk.
+                    if let Some(result) = moving.await {
+                        remember_error(&known, &result);
+                        let _ = reply.send(result);
+                    }
+                }));
+            }
+            Command::Stop(reply) => {
+                abort_all(&mut moves);
+                let result = desk.stop().await;
+                remember_error(&known, &result);
+                let _ = reply.send(result);
+            }
+            Command::Position(reply) => {
+                // Reads don't wait for the command queue, a move may be polling too.
+                let desk = desk.clone();
+                let known = known.clone();
+                tokio::spawn(async move {
+                    let result = desk.position().await;
+                    remember_error(&known, &result);
+                    let _ = reply.send(result);
+                });
+            }
+            Command::Status(reply) => {
+                let desk = desk.clone();
+                let known = known.clone();
+                tokio::spawn(async move {
+                    let _ = reply.send(status(&desk, &known).await);
+                });
+            }
+        }
+    }
+    abort_all(&mut moves);
+}
+
+fn abort_all(moves: &mut Vec<JoinHandle<()>>) {
+    for previous in moves.drain(..) {
+        previous.abort();
+    }
+}
+
+// Runs `future` until it is done or the receiver of `reply` is dropped, whichever comes first.
+struct UnlessAbandoned<'a, R, F> {
+    reply: &'a mut oneshot::Sender<R>,
+    future: Pin<Box<F>>,
+}
+
+impl<R, F: Future> Future for UnlessAbandoned<'_, R, F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        self.reply.poll_closed(cx).map(|()| None)
+    }
+}
+
+async fn status<T: DeskTransport>(desk: &Idasen<T>, known: &Mutex<Known>) -> DeskStatus {
+    let firmware_revision = desk.firmware_revision().await.ok().flatten();
+    let (mut status, read) = read_status(desk, firmware_revision).await;
+    remember_error(known, &read);
+    status.last_error = known.lock().unwrap().last_error.clone();
+    status
+}
+
+// Everything but `last_error`, together with the outcome of reading the position.
+async fn read_status<T: DeskTransport>(
+    desk: &Idasen<T>,
+    firmware_revision: Option<String>,
+) -> (DeskStatus, Result<PositionSpeed, Error>) {
+    let read = desk.position_and_speed().await;
+    let current = read.as_ref().ok().copied();
+    let rssi = match current {
+        Some(_) => desk.rssi().await.ok().flatten(),
+        None => None,
+    };
+    let position = current.map(|current| current.position);
+    let status = DeskStatus {
+        mac_addr: desk.mac_addr,
+        connected: current.is_some(),
+        position,
+        speed: current.map(|current| current.speed),
+        moving: current.and_then(|current| Direction::of(current.speed)),
+        posture: position.map(|position| {
+            if position > STANDING_ABOVE {
+                Posture::Standing
+            } else {
+                Posture::Sitting
+            }
+        }),
+        rssi,
+        last_error: None,
+        firmware_revision,
+    };
+    (status, read)
+}
+
+fn remember_error<R>(known: &Mutex<Known>, result: &Result<R, Error>) {
+    if let Err(error) = result {
+        known.lock().unwrap().last_error = Some(error.to_string());
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) mod address_string {
+    use crate::BDAddr;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(address: &BDAddr, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(address)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BDAddr, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) mod optional_address_string {
+    use crate::BDAddr;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        address: &Option<BDAddr>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match address {
+            Some(address) => serializer.collect_str(address),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<BDAddr>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|address| address.parse().map_err(de::Error::custom))
+            .transpose()
+    }
+}
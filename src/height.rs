@@ -0,0 +1,159 @@
+use crate::{MAX_HEIGHT, MIN_HEIGHT};
+use std::fmt;
+
+/// Unit to show desk heights in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LengthUnit {
+    Centimetres,
+    Inches,
+}
+
+impl LengthUnit {
+    /// Format a height in tenth of millimetres, with the unit appended.
+    ///
+    /// ```
+    /// use idasen::LengthUnit;
+    ///
+    /// assert_eq!(LengthUnit::Centimetres.format(7250), "72.5cm");
+    /// assert_eq!(LengthUnit::Inches.format(7250), "28.5in");
+    /// ```
+    pub fn format(&self, position: u16) -> String {
+        match self {
+            LengthUnit::Centimetres => format!("{}cm", position as f32 / 100.0),
+            LengthUnit::Inches => format!("{:.1}in", position as f32 / 254.0),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LengthUnit::Centimetres => "cm",
+            LengthUnit::Inches => "in",
+        }
+    }
+
+    // Tenth of millimetres in one unit.
+    fn tenth_millimetres(&self) -> f64 {
+        match self {
+            LengthUnit::Centimetres => 100.0,
+            LengthUnit::Inches => 254.0,
+        }
+    }
+}
+
+/// Desk height in tenth of millimetres, as returned by `Idasen::position`, for showing it to
+/// users.
+///
+/// Displays in centimetres with one decimal unless a precision is given; `display` picks the
+/// unit.
+///
+/// ```
+/// use idasen::{Height, LengthUnit};
+///
+/// let height = Height(7320);
+/// assert_eq!(height.to_string(), "73.2 cm");
+/// assert_eq!(format!("{:.2}", height), "73.20 cm");
+/// assert_eq!(height.display(LengthUnit::Inches, 1).to_string(), "28.8 in");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Height(pub u16);
+
+impl Height {
+    /// The height of `value` in `unit`, rounded to tenth of millimetres.
+    ///
+    /// ```
+    /// use idasen::{Height, LengthUnit};
+    ///
+    /// assert_eq!(Height::from_unit(72.5, LengthUnit::Centimetres), Height(7250));
+    /// assert_eq!(Height::from_in(30.0), Height(7620));
+    /// assert_eq!(Height::from_mm(620.0).to_cm(), 62.0);
+    /// assert!(!Height::from_cm(50.0).is_in_range());
+    /// ```
+    pub fn from_unit(value: f64, unit: LengthUnit) -> Self {
+        Self((value * unit.tenth_millimetres()).round() as u16)
+    }
+
+    pub fn from_cm(centimetres: f64) -> Self {
+        Self::from_unit(centimetres, LengthUnit::Centimetres)
+    }
+
+    pub fn from_mm(millimetres: f64) -> Self {
+        Self((millimetres * 10.0).round() as u16)
+    }
+
+    pub fn from_in(inches: f64) -> Self {
+        Self::from_unit(inches, LengthUnit::Inches)
+    }
+
+    /// The height in `unit`.
+    pub fn to_unit(&self, unit: LengthUnit) -> f64 {
+        self.0 as f64 / unit.tenth_millimetres()
+    }
+
+    pub fn to_cm(&self) -> f64 {
+        self.to_unit(LengthUnit::Centimetres)
+    }
+
+    pub fn to_mm(&self) -> f64 {
+        self.0 as f64 / 10.0
+    }
+
+    pub fn to_in(&self) -> f64 {
+        self.to_unit(LengthUnit::Inches)
+    }
+
+    /// Whether the desk can reach the height, between `MIN_HEIGHT` and `MAX_HEIGHT`. A
+    /// calibrated desk has its own range, see `Idasen::height_range`.
+    pub fn is_in_range(&self) -> bool {
+        (MIN_HEIGHT..=MAX_HEIGHT).contains(&self.0)
+    }
+
+    /// Display in `unit` with `precision` decimals.
+    pub fn display(&self, unit: LengthUnit, precision: usize) -> HeightDisplay {
+        HeightDisplay {
+            height: *self,
+            unit,
+            precision,
+        }
+    }
+}
+
+impl From<u16> for Height {
+    fn from(position: u16) -> Self {
+        Self(position)
+    }
+}
+
+impl From<Height> for u16 {
+    fn from(height: Height) -> Self {
+        height.0
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(LengthUnit::Centimetres, f.precision().unwrap_or(1))
+            .fmt(f)
+    }
+}
+
+/// `Height` formatted by `Height::display`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeightDisplay {
+    height: Height,
+    unit: LengthUnit,
+    precision: usize,
+}
+
+impl fmt::Display for HeightDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.*} {}",
+            self.precision,
+            self.height.to_unit(self.unit),
+            self.unit.symbol()
+        )
+    }
+}
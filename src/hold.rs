@@ -0,0 +1,91 @@
+use crate::{DeskTransport, Error, Idasen};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// How often the command is repeated while held, the desk stops soon after the last one.
+const HOLD_REPEAT: Duration = Duration::from_millis(200);
+
+/// The desk moving for as long as a button is held, see `Idasen::hold_up`.
+///
+/// The command is repeated on a task of its own like the paddle does. `release` or dropping
+/// the guard stops the desk.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Idasen};
+/// use std::sync::Arc;
+///
+/// let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+/// let hold = desk.hold_up();
+/// tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+/// hold.release().await.unwrap();
+/// assert!(desk.read_position().await.unwrap() > 7000);
+/// # });
+/// ```
+pub struct Hold<T: DeskTransport> {
+    desk: Arc<Idasen<T>>,
+    // Only ends if a command failed.
+    task: Option<JoinHandle<Error>>,
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Raise the desk until the returned guard is released or dropped, e.g. for a "press and
+    /// hold" button.
+    pub fn hold_up(self: &Arc<Self>) -> Hold<T> {
+        self.hold(true)
+    }
+
+    /// Lower the desk until the returned guard is released or dropped.
+    pub fn hold_down(self: &Arc<Self>) -> Hold<T> {
+        self.hold(false)
+    }
+
+    fn hold(self: &Arc<Self>, going_up: bool) -> Hold<T> {
+        let desk = self.clone();
+        let task = tokio::spawn(async move {
+            let mut repeat = tokio::time::interval(HOLD_REPEAT);
+            loop {
+                repeat.tick().await;
+                let result = if going_up {
+                    desk.up().await
+                } else {
+                    desk.down().await
+                };
+                if let Err(error) = result {
+                    let _ = desk.stop().await;
+                    return error;
+                }
+            }
+        });
+        Hold {
+            desk: self.clone(),
+            task: Some(task),
+        }
+    }
+}
+
+impl<T: DeskTransport> Hold<T> {
+    /// Stop the desk. Fails with the error that ended the hold early, if a command failed.
+    pub async fn release(mut self) -> Result<(), Error> {
+        let task = self.task.take().expect("the hold runs until released");
+        task.abort();
+        let result = match task.await {
+            Ok(error) => Err(error),
+            Err(_) => self.desk.stop().await,
+        };
+        self.desk.move_ended();
+        result
+    }
+}
+
+impl<T: DeskTransport> Drop for Hold<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            self.desk.stop_in_background();
+            self.desk.move_ended();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{DeskTransport, Idasen};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+impl<T: DeskTransport + 'static> Idasen<T> {
+    /// Disconnect from the desk once it hasn't been read or written for `after`, to save the
+    /// radio on laptops where the connection would otherwise stay open all day. The next read
+    /// or move reconnects, reusing the characteristics found before.
+    ///
+    /// Notification streams end with the connection, so a `Monitor` following the desk
+    /// reconnects right away; don't combine the two. The task ends once the desk is dropped.
+    ///
+    /// ```
+    /// # #[cfg(feature = "mock")]
+    /// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+    /// use idasen::{mock::MockDesk, Idasen};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let mock = MockDesk::new(7000);
+    /// let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+    /// desk.spawn_idle_disconnect(Duration::from_secs(300));
+    ///
+    /// tokio::time::sleep(Duration::from_secs(301)).await;
+    /// assert!(!mock.is_connected());
+    /// assert_eq!(desk.position().await.unwrap(), 7000);
+    /// assert!(mock.is_connected());
+    /// # });
+    /// ```
+    pub fn spawn_idle_disconnect(self: &Arc<Self>, after: Duration) -> JoinHandle<()> {
+        tokio::spawn(disconnect_when_idle(Arc::downgrade(self), after))
+    }
+}
+
+async fn disconnect_when_idle<T: DeskTransport>(desk: Weak<Idasen<T>>, after: Duration) {
+    loop {
+        let wait = match desk.upgrade() {
+            Some(desk) => {
+                let idle_for = desk.idle_for();
+                if idle_for >= after {
+                    // A failed disconnect is retried after another idle period.
+                    let _ = desk.disconnect_idle().await;
+                    after
+                } else {
+                    after - idle_for
+                }
+            }
+            None => return,
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
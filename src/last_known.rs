@@ -0,0 +1,85 @@
+use crate::storage::SingleFile;
+use crate::Storage;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const STORAGE_KEY: &str = "last-known-position";
+
+/// Most recent position read from the desk and when it was read.
+///
+/// ```
+/// use idasen::LastKnownPosition;
+///
+/// let path = std::env::temp_dir().join("idasen-last-known-doctest");
+/// let last = LastKnownPosition::now(7200);
+/// last.save(&path).unwrap();
+///
+/// let loaded = LastKnownPosition::load(&path).unwrap().unwrap();
+/// assert_eq!(loaded.position, 7200);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LastKnownPosition {
+    // tenth mm
+    pub position: u16,
+    pub at: SystemTime,
+}
+
+impl LastKnownPosition {
+    pub fn now(position: u16) -> Self {
+        Self {
+            position,
+            at: SystemTime::now(),
+        }
+    }
+
+    /// Time elapsed since the position was read.
+    pub fn age(&self) -> Duration {
+        self.at.elapsed().unwrap_or_default()
+    }
+
+    /// Read the position saved by `save`. Returns `None` if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        Self::load_from(SingleFile(path.as_ref().to_path_buf()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_to(SingleFile(path.as_ref().to_path_buf()))
+    }
+
+    /// Read the position saved by `save_to`, stored under the key `last-known-position`.
+    pub fn load_from(storage: impl Storage) -> io::Result<Option<Self>> {
+        let content = match storage.read(STORAGE_KEY)? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid last known position");
+        let mut fields = content.split_whitespace();
+        let position = fields
+            .next()
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        let seconds = fields
+            .next()
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        Ok(Some(Self {
+            position,
+            at: UNIX_EPOCH + Duration::from_secs(seconds),
+        }))
+    }
+
+    pub fn save_to(&self, storage: impl Storage) -> io::Result<()> {
+        let seconds = self
+            .at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        storage.write(STORAGE_KEY, &format!("{} {}\n", self.position, seconds))
+    }
+}
@@ -0,0 +1,543 @@
+use crate::transport::{DeskTransport, NotificationStream};
+use crate::{
+    position_speed_to_bytes, BDAddr, CharPropFlags, Characteristic, Error, PositionSpeed,
+    ValueNotification, WriteType, CONTROL_SERVICE_UUID, CONTROL_UUID, DOWN, FIRMWARE_REVISION_UUID,
+    MANUFACTURER_NAME_UUID, MAX_HEIGHT, MIN_HEIGHT, MODEL_NUMBER_UUID, POSITION_UUID,
+    REFERENCE_INPUT_UUID, SERIAL_NUMBER_UUID, STOP, UP,
+};
+use async_trait::async_trait;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior, Sleep};
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+// Tenth of millimetres per second, the desk travels at roughly 38 mm/s.
+const TRAVEL_SPEED: f64 = 380.0;
+// Tenth of millimetres per second squared.
+const ACCELERATION: f64 = 1900.0;
+const DECELERATION: f64 = 3800.0;
+// The motor keeps running for this long after each up/down command or target.
+const COMMAND_HOLD: Duration = Duration::from_millis(500);
+const SIMULATION_STEP: Duration = Duration::from_millis(5);
+const NOTIFICATION_INTERVAL: Duration = Duration::from_millis(100);
+// Round trip of a single read or write, the move loop relies on commands taking real time.
+const LATENCY: Duration = Duration::from_millis(30);
+// Seed of the generator deciding jitter and lost notifications, fixed so runs repeat.
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Simulated desk implementing `DeskTransport`, for testing without hardware.
+///
+/// It reacts to the up, down and stop commands like the real controller: each command keeps
+/// the motor running for a short while, the speed ramps up and down, the travel is limited to
+/// `MIN_HEIGHT..=MAX_HEIGHT` and position notifications are sent while the desk moves. Reads
+/// and writes take a Bluetooth-like round trip of a few tens of milliseconds. Targets written
+/// to `REFERENCE_INPUT_UUID` are approached at full speed, slowing down to stop right on them.
+///
+/// The simulation follows tokio's clock. With time paused (`start_paused` or
+/// `tokio::time::pause`, from tokio's `test-util` feature) moves finish instantly and take the
+/// same simulated time on every run.
+///
+/// A poor adapter can be simulated with `with_latency`, `with_notification_latency`,
+/// `with_packet_loss` and `with_write_loss`. The jitter and the lost notifications and writes
+/// are drawn from a generator with a fixed seed, so they repeat between runs too.
+///
+/// ```
+/// use idasen::{mock::MockDesk, Idasen};
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread()
+///     .enable_time()
+///     .start_paused(true)
+///     .build()
+///     .unwrap();
+/// runtime.block_on(async {
+///     let mock = MockDesk::new(7000);
+///     let desk = Idasen::new(mock.clone()).await.unwrap();
+///     desk.move_to(7300).await.unwrap();
+///     assert!((mock.position() as i32 - 7300).abs() <= 10);
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockDesk {
+    address: BDAddr,
+    // Device Information characteristics offered and their values.
+    device_info: Vec<(Uuid, String)>,
+    rssi: Option<i16>,
+    name: Option<String>,
+    latency: Duration,
+    notification_latency: Duration,
+    notification_jitter: Duration,
+    // fraction of notifications dropped
+    packet_loss: f64,
+    // fraction of writes without response dropped
+    write_loss: f64,
+    // whether subscribing to the position fails
+    no_notifications: bool,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    // tenth mm
+    position: f64,
+    // tenth mm per second, positive when going up
+    speed: f64,
+    // direction of the last move command and until when the motor runs
+    command: Option<(f64, Instant)>,
+    // last target written to the reference input and until when the motor runs
+    reference: Option<(f64, Instant)>,
+    // direction the paddle is pressed in and until when, overriding commands
+    paddle: Option<(f64, Instant)>,
+    updated: Instant,
+    connected: bool,
+    subscribed: bool,
+    random: u64,
+}
+
+impl MockDesk {
+    /// Create a desk resting at `position`.
+    pub fn new(position: u16) -> Self {
+        Self {
+            address: BDAddr::from([0xEC, 0x86, 0xF6, 0x00, 0x00, 0x01]),
+            device_info: Vec::new(),
+            rssi: None,
+            name: None,
+            latency: LATENCY,
+            notification_latency: Duration::from_secs(0),
+            notification_jitter: Duration::from_secs(0),
+            packet_loss: 0.0,
+            write_loss: 0.0,
+            no_notifications: false,
+            state: Arc::new(Mutex::new(State {
+                position: position.clamp(MIN_HEIGHT, MAX_HEIGHT) as f64,
+                speed: 0.0,
+                command: None,
+                reference: None,
+                paddle: None,
+                updated: Instant::now(),
+                connected: false,
+                subscribed: false,
+                random: SEED,
+            })),
+        }
+    }
+
+    /// Use a different Bluetooth address, e.g. to simulate several desks.
+    pub fn with_address(mut self, address: BDAddr) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Offer the Device Information firmware revision.
+    pub fn with_firmware_revision(mut self, revision: impl Into<String>) -> Self {
+        self.offer_device_info(FIRMWARE_REVISION_UUID, revision.into());
+        self
+    }
+
+    /// Offer the Device Information manufacturer name, model number and serial number.
+    pub fn with_device_info(
+        mut self,
+        manufacturer: impl Into<String>,
+        model: impl Into<String>,
+        serial_number: impl Into<String>,
+    ) -> Self {
+        self.offer_device_info(MANUFACTURER_NAME_UUID, manufacturer.into());
+        self.offer_device_info(MODEL_NUMBER_UUID, model.into());
+        self.offer_device_info(SERIAL_NUMBER_UUID, serial_number.into());
+        self
+    }
+
+    /// Report `rssi` dBm as the signal strength.
+    pub fn with_rssi(mut self, rssi: i16) -> Self {
+        self.rssi = Some(rssi);
+        self
+    }
+
+    /// Advertise `name`, e.g. `Desk 1234`.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Take `latency` for the round trip of each read and write, instead of 30 ms.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Deliver each notification `latency` plus up to `jitter` after the position it carries
+    /// was sampled.
+    pub fn with_notification_latency(mut self, latency: Duration, jitter: Duration) -> Self {
+        self.notification_latency = latency;
+        self.notification_jitter = jitter;
+        self
+    }
+
+    /// Drop the given fraction of notifications, between 0 and 1. A dropped notification is
+    /// not sent again, the next one comes with the next change of position.
+    pub fn with_packet_loss(mut self, loss: f64) -> Self {
+        self.packet_loss = loss;
+        self
+    }
+
+    /// Drop the given fraction of writes without response, between 0 and 1. A dropped write
+    /// succeeds but the desk never sees it, as happens on a congested link.
+    pub fn with_write_loss(mut self, loss: f64) -> Self {
+        self.write_loss = loss;
+        self
+    }
+
+    /// Fail subscribing to the position, like some macOS versions do, while reads still work.
+    pub fn without_notifications(mut self) -> Self {
+        self.no_notifications = true;
+        self
+    }
+
+    /// Current simulated position in tenth of millimetres.
+    pub fn position(&self) -> u16 {
+        self.reading().0
+    }
+
+    /// Whether the simulated motor is running.
+    pub fn is_moving(&self) -> bool {
+        self.reading().1 != 0
+    }
+
+    /// Whether `Idasen` is connected to the simulated desk.
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().connected
+    }
+
+    /// Drop the connection, as happens when the computer goes to sleep.
+    pub fn disconnect(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.connected = false;
+        state.subscribed = false;
+    }
+
+    /// Hold the physical paddle up or down for `duration`. Commands written meanwhile are
+    /// overridden, like on the real desk.
+    pub fn press_paddle(&self, up: bool, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.advance(now);
+        let direction = if up { 1.0 } else { -1.0 };
+        state.paddle = Some((direction, now + duration));
+    }
+
+    /// Move the desk as if the physical paddle was used.
+    pub fn set_position(&self, position: u16) {
+        let mut state = self.state.lock().unwrap();
+        state.advance(Instant::now());
+        state.position = position.clamp(MIN_HEIGHT, MAX_HEIGHT) as f64;
+    }
+
+    // Position in tenth of millimetres and speed in hundredths of millimetres per second.
+    fn reading(&self) -> (u16, i16) {
+        let mut state = self.state.lock().unwrap();
+        state.advance(Instant::now());
+        (state.position.round() as u16, (state.speed * 10.0) as i16)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let (position, speed) = self.reading();
+        position_speed_to_bytes(PositionSpeed { position, speed }).to_vec()
+    }
+
+    // `None` if the notification gets lost, otherwise how long it takes to arrive.
+    fn notification_delay(&self) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        if self.packet_loss > 0.0 && state.random() < self.packet_loss {
+            return None;
+        }
+        let jitter = self.notification_jitter.mul_f64(state.random());
+        Some(self.notification_latency + jitter)
+    }
+
+    fn write_lost(&self, write_type: WriteType) -> bool {
+        write_type == WriteType::WithoutResponse
+            && self.write_loss > 0.0
+            && self.state.lock().unwrap().random() < self.write_loss
+    }
+
+    fn ensure_connected(&self) -> Result<(), Error> {
+        if self.state.lock().unwrap().connected {
+            Ok(())
+        } else {
+            Err(btleplug::Error::NotConnected.into())
+        }
+    }
+
+    fn device_info(&self, uuid: Uuid) -> Option<&str> {
+        self.device_info
+            .iter()
+            .find(|(offered, _)| *offered == uuid)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Offering a characteristic again replaces its value.
+    fn offer_device_info(&mut self, uuid: Uuid, value: String) {
+        self.device_info.retain(|(offered, _)| *offered != uuid);
+        self.device_info.push((uuid, value));
+    }
+}
+
+impl State {
+    // Uniformly distributed in 0..1, from a xorshift generator.
+    fn random(&mut self) -> f64 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn advance(&mut self, now: Instant) {
+        while self.updated < now {
+            let commanded = matches!(self.command, Some((_, until)) if until > self.updated)
+                || matches!(self.reference, Some((_, until)) if until > self.updated)
+                || matches!(self.paddle, Some((_, until)) if until > self.updated);
+            if self.speed == 0.0 && !commanded {
+                // Nothing to simulate while the desk rests.
+                self.updated = now;
+                break;
+            }
+
+            let step = SIMULATION_STEP.min(now - self.updated);
+            self.updated += step;
+            let seconds = step.as_secs_f64();
+
+            let target_speed = match (self.paddle, self.command, self.reference) {
+                (Some((direction, until)), _, _) | (_, Some((direction, until)), _)
+                    if self.updated <= until =>
+                {
+                    direction * TRAVEL_SPEED
+                }
+                (_, _, Some((target, until))) if self.updated <= until => {
+                    let remaining = target - self.position;
+                    if remaining.abs() <= self.speed.abs() * seconds {
+                        // Arrived.
+                        self.position = target;
+                        self.speed = 0.0;
+                        self.reference = None;
+                        continue;
+                    }
+                    // As fast as still allows stopping at the target.
+                    let speed = (2.0 * DECELERATION * remaining.abs()).sqrt();
+                    remaining.signum() * speed.min(TRAVEL_SPEED)
+                }
+                _ => 0.0,
+            };
+            let rate = if target_speed.abs() > self.speed.abs() {
+                ACCELERATION
+            } else {
+                DECELERATION
+            };
+            let change = (target_speed - self.speed).clamp(-rate * seconds, rate * seconds);
+            self.speed += change;
+            self.position += self.speed * seconds;
+
+            if self.position <= MIN_HEIGHT as f64 || self.position >= MAX_HEIGHT as f64 {
+                self.position = self.position.clamp(MIN_HEIGHT as f64, MAX_HEIGHT as f64);
+                self.speed = 0.0;
+                self.command = None;
+                self.reference = None;
+            }
+        }
+    }
+
+    fn command(&mut self, data: &[u8]) {
+        let now = Instant::now();
+        self.advance(now);
+        if data == UP {
+            self.command = Some((1.0, now + COMMAND_HOLD));
+            self.reference = None;
+        } else if data == DOWN {
+            self.command = Some((-1.0, now + COMMAND_HOLD));
+            self.reference = None;
+        } else if data == STOP {
+            self.command = None;
+            self.reference = None;
+        }
+    }
+
+    fn reference(&mut self, data: &[u8]) {
+        let now = Instant::now();
+        self.advance(now);
+        if let [low, high] = *data {
+            let target = u16::from_le_bytes([low, high]).saturating_add(MIN_HEIGHT);
+            self.command = None;
+            self.reference = Some((target.min(MAX_HEIGHT) as f64, now + COMMAND_HOLD));
+        }
+    }
+}
+
+#[async_trait]
+impl DeskTransport for MockDesk {
+    fn address(&self) -> BDAddr {
+        self.address
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.state.lock().unwrap().connected = true;
+        // Discovering the characteristics.
+        tokio::time::sleep(self.latency).await;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        MockDesk::disconnect(self);
+        Ok(())
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        uuid == CONTROL_UUID
+            || uuid == POSITION_UUID
+            || uuid == REFERENCE_INPUT_UUID
+            || self.device_info(uuid).is_some()
+    }
+
+    fn characteristics(&self) -> Vec<Characteristic> {
+        let mut characteristics = vec![
+            Characteristic {
+                uuid: CONTROL_UUID,
+                service_uuid: CONTROL_SERVICE_UUID,
+                properties: CharPropFlags::WRITE | CharPropFlags::WRITE_WITHOUT_RESPONSE,
+            },
+            Characteristic {
+                uuid: POSITION_UUID,
+                service_uuid: Uuid::from_u128(0x99fa0020_338a_1024_8a49_009c0215f78a),
+                properties: CharPropFlags::READ | CharPropFlags::NOTIFY,
+            },
+            Characteristic {
+                uuid: REFERENCE_INPUT_UUID,
+                service_uuid: Uuid::from_u128(0x99fa0030_338a_1024_8a49_009c0215f78a),
+                properties: CharPropFlags::WRITE | CharPropFlags::WRITE_WITHOUT_RESPONSE,
+            },
+        ];
+        for (uuid, _) in &self.device_info {
+            characteristics.push(Characteristic {
+                uuid: *uuid,
+                // Device Information
+                service_uuid: Uuid::from_u128(0x0000180a_0000_1000_8000_00805f9b34fb),
+                properties: CharPropFlags::READ,
+            });
+        }
+        characteristics
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        self.ensure_connected()?;
+        tokio::time::sleep(self.latency).await;
+        if uuid == POSITION_UUID {
+            Ok(self.encode())
+        } else if let Some(value) = self.device_info(uuid) {
+            Ok(value.as_bytes().to_vec())
+        } else {
+            Err(Error::CharacteristicsNotFound(uuid.to_string()))
+        }
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8], write_type: WriteType) -> Result<(), Error> {
+        self.ensure_connected()?;
+        tokio::time::sleep(self.latency).await;
+        if uuid == CONTROL_UUID {
+            if !self.write_lost(write_type) {
+                self.state.lock().unwrap().command(data);
+            }
+            Ok(())
+        } else if uuid == REFERENCE_INPUT_UUID {
+            if !self.write_lost(write_type) {
+                self.state.lock().unwrap().reference(data);
+            }
+            Ok(())
+        } else {
+            Err(Error::CharacteristicsNotFound(uuid.to_string()))
+        }
+    }
+
+    async fn subscribe(&self, uuid: Uuid) -> Result<(), Error> {
+        self.ensure_connected()?;
+        if self.no_notifications {
+            let error = btleplug::Error::NotSupported("notifications".to_string());
+            Err(Error::BtlePlugError(error))
+        } else if uuid == POSITION_UUID {
+            self.state.lock().unwrap().subscribed = true;
+            Ok(())
+        } else {
+            Err(Error::CharacteristicsNotFound(uuid.to_string()))
+        }
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        self.ensure_connected()?;
+        let mut interval = tokio::time::interval(NOTIFICATION_INTERVAL);
+        // A notification held back by latency doesn't make the following ones come in a burst.
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Ok(Box::pin(Notifications {
+            desk: self.clone(),
+            interval,
+            last: None,
+            in_flight: None,
+        }))
+    }
+
+    async fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.ensure_connected()?;
+        Ok(self.rssi)
+    }
+
+    async fn name(&self) -> Result<Option<String>, Error> {
+        Ok(self.name.clone())
+    }
+}
+
+// Emits the position whenever it changed since the previous tick.
+struct Notifications {
+    desk: MockDesk,
+    interval: tokio::time::Interval,
+    last: Option<Vec<u8>>,
+    // Sampled notification waiting for its simulated latency to pass.
+    in_flight: Option<(Pin<Box<Sleep>>, ValueNotification)>,
+}
+
+impl Stream for Notifications {
+    type Item = ValueNotification;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some((delay, _)) = &mut self.in_flight {
+                if delay.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                let (_, notification) = self.in_flight.take().unwrap();
+                return Poll::Ready(Some(notification));
+            }
+            if self.interval.poll_tick(cx).is_pending() {
+                return Poll::Pending;
+            }
+            if !self.desk.state.lock().unwrap().subscribed {
+                continue;
+            }
+            let value = self.desk.encode();
+            if self.last.as_ref() != Some(&value) {
+                self.last = Some(value.clone());
+                let notification = ValueNotification {
+                    uuid: POSITION_UUID,
+                    value,
+                };
+                match self.desk.notification_delay() {
+                    None => continue,
+                    Some(delay) if delay == Duration::from_secs(0) => {
+                        return Poll::Ready(Some(notification))
+                    }
+                    Some(delay) => {
+                        self.in_flight = Some((Box::pin(tokio::time::sleep(delay)), notification))
+                    }
+                }
+            }
+        }
+    }
+}
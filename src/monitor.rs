@@ -0,0 +1,185 @@
+use crate::desk::COMMAND_RUNS;
+use crate::{
+    DeskEvent, DeskTransport, Direction, Error, Idasen, PositionSpeed, MAX_HEIGHT, MIN_HEIGHT,
+};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+// Heights above this count as standing, unless set with `Monitor::with_standing_above`.
+pub(crate) const STANDING_ABOVE: u16 = (MIN_HEIGHT + MAX_HEIGHT) / 2;
+
+/// Whether the desk is at sitting or standing height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Posture {
+    Sitting,
+    Standing,
+}
+
+/// Latest position and speed of a desk, kept up to date from notifications by a background
+/// task, see `Idasen::spawn_monitor`.
+///
+/// The getters don't talk to the desk, so they can be called from a UI on every frame. When
+/// the notifications end because the connection dropped, the task reconnects; if that fails
+/// it stops and `join` returns the error. A dropped monitor's task ends with the next update.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Idasen, Posture};
+/// use std::sync::Arc;
+///
+/// let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+/// let mut monitor = desk.spawn_monitor().await.unwrap();
+/// assert_eq!(monitor.posture(), Posture::Sitting);
+///
+/// desk.up().await.unwrap();
+/// while !monitor.is_moving() {
+///     monitor.changed().await.unwrap();
+/// }
+/// assert!(monitor.speed() > 0);
+/// monitor.stop();
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Monitor {
+    state: watch::Receiver<PositionSpeed>,
+    standing_above: u16,
+    // Posture as of the last `next_events`.
+    reported_posture: Posture,
+    // Position as of the last `changed_by`.
+    reported_position: u16,
+    // Whether the desk was moving as of the last `next_events`.
+    reported_moving: bool,
+    last_command: Arc<Mutex<Option<Instant>>>,
+    task: JoinHandle<Result<(), Error>>,
+}
+
+impl<T: DeskTransport + 'static> Idasen<T> {
+    /// Start following the desk's position in the background.
+    pub async fn spawn_monitor(self: &Arc<Self>) -> Result<Monitor, Error> {
+        let initial = self.read_position_and_speed().await?;
+        let (sender, state) = watch::channel(initial);
+        let task = tokio::spawn(follow(self.clone(), sender));
+        let mut monitor = Monitor {
+            state,
+            standing_above: STANDING_ABOVE,
+            reported_posture: Posture::Sitting,
+            reported_position: initial.position,
+            reported_moving: initial.speed != 0,
+            last_command: self.last_command(),
+            task,
+        };
+        monitor.reported_posture = monitor.posture();
+        Ok(monitor)
+    }
+}
+
+impl Monitor {
+    /// Count heights above `height` as standing.
+    pub fn with_standing_above(mut self, height: u16) -> Self {
+        self.standing_above = height;
+        self.reported_posture = self.posture();
+        self
+    }
+
+    pub fn position_and_speed(&self) -> PositionSpeed {
+        *self.state.borrow()
+    }
+
+    /// Desk height in tenth millimeters.
+    pub fn position(&self) -> u16 {
+        self.position_and_speed().position
+    }
+
+    pub fn speed(&self) -> i16 {
+        self.position_and_speed().speed
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.speed() != 0
+    }
+
+    pub fn posture(&self) -> Posture {
+        if self.position() > self.standing_above {
+            Posture::Standing
+        } else {
+            Posture::Sitting
+        }
+    }
+
+    /// Wait for the next update. Returns `None` once the task has stopped.
+    pub async fn changed(&mut self) -> Option<PositionSpeed> {
+        self.state.changed().await.ok()?;
+        Some(self.position_and_speed())
+    }
+
+    /// Wait until the desk moved more than `delta` tenth of millimetres from the position
+    /// returned last time, or from where it was when the monitor started, e.g. to only log
+    /// deliberate height changes. Returns `None` once the task has stopped.
+    pub async fn changed_by(&mut self, delta: u16) -> Option<PositionSpeed> {
+        loop {
+            let current = self.changed().await?;
+            if (current.position as i32 - self.reported_position as i32).abs() > delta as i32 {
+                self.reported_position = current.position;
+                return Some(current);
+            }
+        }
+    }
+
+    /// Wait for the next update and describe it as a `DeskEvent::PositionChanged`, followed by
+    /// a `DeskEvent::MovedByHand` if the desk started moving without a command from `Idasen`
+    /// and a `DeskEvent::PostureChanged` if the desk crossed the standing height. Returns `None`
+    /// once the task has stopped.
+    pub async fn next_events(&mut self) -> Option<Vec<DeskEvent>> {
+        let PositionSpeed { position, speed } = self.changed().await?;
+        let mut events = vec![DeskEvent::PositionChanged { position, speed }];
+        let started = speed != 0 && !self.reported_moving;
+        self.reported_moving = speed != 0;
+        let commanded = matches!(
+            *self.last_command.lock().unwrap(),
+            Some(at) if at.elapsed() < COMMAND_RUNS
+        );
+        if let (true, false, Some(direction)) = (started, commanded, Direction::of(speed)) {
+            events.push(DeskEvent::MovedByHand { direction });
+        }
+        let posture = self.posture();
+        if posture != self.reported_posture {
+            self.reported_posture = posture;
+            events.push(DeskEvent::PostureChanged { posture });
+        }
+        Some(events)
+    }
+
+    /// Stop the background task.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    /// Wait for the background task to stop, returning why it did.
+    pub async fn join(self) -> Result<(), Error> {
+        self.task.await.unwrap_or(Err(Error::DeskTaskStopped))
+    }
+}
+
+async fn follow<T: DeskTransport>(
+    desk: Arc<Idasen<T>>,
+    state: watch::Sender<PositionSpeed>,
+) -> Result<(), Error> {
+    loop {
+        let notifications = desk.position_and_speed_stream().await?;
+        tokio::pin!(notifications);
+        while let Some(value) = notifications.next().await {
+            if state.send(value).is_err() {
+                return Ok(());
+            }
+        }
+        // The notifications end with the connection, reading reconnects and resubscribes.
+        if state.send(desk.read_position_and_speed().await?).is_err() {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{DeskTransport, Error, Idasen, MoveOptions};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// A move running on a task of its own, see `Idasen::start_move_to`.
+///
+/// Dropping the handle lets the move finish, `cancel` stops the desk where it is.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Error, Idasen, MoveStatus};
+/// use std::sync::Arc;
+///
+/// let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+/// let moving = desk.start_move_to(12000);
+/// tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+/// assert_eq!(moving.status(), MoveStatus::Moving);
+///
+/// // The user hit the stop button.
+/// moving.cancel();
+/// assert_eq!(moving.status(), MoveStatus::Cancelled);
+/// assert!(matches!(moving.wait().await, Err(Error::MoveInterrupted)));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MoveHandle {
+    task: JoinHandle<Result<(), Error>>,
+    status: Arc<Mutex<MoveStatus>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveStatus {
+    Moving,
+    /// The desk is at the target.
+    Reached,
+    /// The move failed, `MoveHandle::wait` returns why.
+    Failed,
+    Cancelled,
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Start moving the desk to `target_position` and return right away, e.g. to keep a GUI
+    /// responsive and offer a stop button.
+    pub fn start_move_to(self: &Arc<Self>, target_position: u16) -> MoveHandle {
+        self.start_move_to_with(target_position, MoveOptions::new())
+    }
+
+    /// Like `start_move_to`, moving as set in `options`.
+    pub fn start_move_to_with(
+        self: &Arc<Self>,
+        target_position: u16,
+        options: MoveOptions<'static>,
+    ) -> MoveHandle {
+        let desk = self.clone();
+        let status = Arc::new(Mutex::new(MoveStatus::Moving));
+        let task_status = status.clone();
+        let task = tokio::spawn(async move {
+            let result = desk.move_to_with(target_position, options).await;
+            *task_status.lock().unwrap() = match result {
+                Ok(()) => MoveStatus::Reached,
+                Err(_) => MoveStatus::Failed,
+            };
+            result
+        });
+        MoveHandle { task, status }
+    }
+}
+
+impl MoveHandle {
+    pub fn status(&self) -> MoveStatus {
+        *self.status.lock().unwrap()
+    }
+
+    /// Stop the desk, unless the move already ended.
+    pub fn cancel(&self) {
+        let mut status = self.status.lock().unwrap();
+        if *status == MoveStatus::Moving {
+            self.task.abort();
+            *status = MoveStatus::Cancelled;
+        }
+    }
+
+    /// Wait for the move to end. A cancelled move fails with `Error::MoveInterrupted`.
+    pub async fn wait(self) -> Result<(), Error> {
+        self.task.await.unwrap_or(Err(Error::MoveInterrupted))
+    }
+}
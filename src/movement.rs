@@ -0,0 +1,662 @@
+#[cfg(feature = "progress")]
+use crate::LengthUnit;
+use crate::{
+    position_to_bytes, DeskEvent, DeskTransport, Error, Height, Idasen, PositionSpeed, Profile,
+    CONTROL_UUID, REFERENCE_INPUT_UUID, WAKE_UP,
+};
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressStyle};
+use std::cmp::{max, min, Ordering};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+// Default interval of the move loop, about as often as the desk sends notifications.
+const TICK: Duration = Duration::from_millis(100);
+// Moves at least this long count for `Idasen::load_factor`, in tenth of millimetres.
+const LOAD_DISTANCE: i16 = 200;
+// How long the motor runs for each nudge of a slow approach.
+const SLOW_PULSE: Duration = Duration::from_millis(100);
+// How often the target of a native move is written again, the desk stops soon without.
+const REFERENCE_INTERVAL: Duration = Duration::from_millis(200);
+// Time the desk gets to start a native move before resting counts as blocked.
+const REFERENCE_START: Duration = Duration::from_secs(1);
+// How far from its target the desk may end a native move, in tenth of millimetres.
+const REFERENCE_TOLERANCE: i32 = 50;
+// Longest a move may take, a full stroke of the desk takes about 15 seconds.
+const MOVE_TIMEOUT: Duration = Duration::from_secs(60);
+// How long the desk may move away from the target before it counts as moved by hand. Coasting
+// past the target ends well before.
+const OVERRIDE_AFTER: Duration = Duration::from_secs(1);
+
+/// How `Idasen::move_to_with` moves the desk.
+///
+/// ```no_run
+/// # async fn example(desk: idasen::Idasen<impl idasen::DeskTransport>) -> Result<(), idasen::Error> {
+/// use idasen::MoveOptions;
+/// use std::time::Duration;
+///
+/// let options = MoveOptions::new()
+///     .tolerance(50)
+///     .watchdog(Duration::from_secs(2))
+///     .on_progress(|position, speed| println!("{} {}", position, speed));
+/// desk.move_to_with(7200, options).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MoveOptions<'a> {
+    tuning: Tuning,
+    profile: Option<Profile>,
+    on_override: OverridePolicy,
+    on_progress: Option<Box<dyn FnMut(u16, i16) + Send + 'a>>,
+}
+
+/// What a move does when someone uses the desk's paddle against it, see
+/// `MoveOptions::on_override`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverridePolicy {
+    /// Fail with `Error::ManualOverride`, leaving the desk to the paddle.
+    Abort,
+    /// Wait for the paddle to be released, then go on to the target.
+    Resume,
+    /// Keep commanding the desk towards the target.
+    Ignore,
+}
+
+/// Settings of the move loop, set one by one on `MoveOptions` or all at once with
+/// `MoveOptions::tuning`, e.g. from a `Config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Tuning {
+    /// See `MoveOptions::tolerance`.
+    pub tolerance: u16,
+    /// See `MoveOptions::tick`.
+    pub tick: Duration,
+    /// See `MoveOptions::braking_margin`.
+    pub braking_distance: u16,
+    pub braking_time: Duration,
+    /// See `MoveOptions::watchdog`.
+    pub watchdog: Option<Duration>,
+    /// See `MoveOptions::slow_approach`.
+    pub slow_approach: Option<u16>,
+    /// See `MoveOptions::timeout`.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            tolerance: 10,
+            tick: TICK,
+            braking_distance: 50,
+            braking_time: Duration::from_millis(500),
+            watchdog: None,
+            slow_approach: None,
+            timeout: Some(MOVE_TIMEOUT),
+        }
+    }
+}
+
+impl<'a> MoveOptions<'a> {
+    /// Stop within a millimetre of the target, without a watchdog and giving up after a
+    /// minute.
+    pub fn new() -> Self {
+        Self {
+            tuning: Tuning::default(),
+            profile: None,
+            on_override: OverridePolicy::Abort,
+            on_progress: None,
+        }
+    }
+
+    /// Replace all settings of the move loop.
+    pub fn tuning(mut self, tuning: Tuning) -> Self {
+        self.tuning = tuning;
+        self
+    }
+
+    /// Stop once the desk is within `tolerance` tenth of millimetres of the target.
+    pub fn tolerance(mut self, tolerance: u16) -> Self {
+        self.tuning.tolerance = tolerance;
+        self
+    }
+
+    /// Adjust the motor every `tick`, 100 ms by default. Shorter ticks react faster but cost
+    /// more Bluetooth traffic.
+    pub fn tick(mut self, tick: Duration) -> Self {
+        self.tuning.tick = tick;
+        self
+    }
+
+    /// Near the target, the desk is only nudged on with the motor stopped every tick. This
+    /// starts `distance` tenth of millimetres or `time` at the current speed from the target,
+    /// whichever is further; 5 mm or half a second by default. Heavily loaded desks need a
+    /// larger margin, light ones settle faster with a smaller one.
+    pub fn braking_margin(mut self, distance: u16, time: Duration) -> Self {
+        self.tuning.braking_distance = distance;
+        self.tuning.braking_time = time;
+        self
+    }
+
+    /// Stop and fail with `Error::DeskNotMoving` if the position doesn't change for `timeout`,
+    /// e.g. because the desk is blocked.
+    pub fn watchdog(mut self, timeout: Duration) -> Self {
+        self.tuning.watchdog = Some(timeout);
+        self
+    }
+
+    /// Stop and fail with `Error::MoveTimeout` if the desk hasn't reached the target after
+    /// `timeout`, e.g. because a degraded connection keeps it from settling. A minute by
+    /// default, `None` waits forever.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.tuning.timeout = timeout;
+        self
+    }
+
+    /// Cover the last `distance` tenth of millimetres before the target in short nudges from
+    /// rest, checking where the desk settled after each one, and only finish once it rests
+    /// within the tolerance. Much slower, but the desk can't overshoot by more than a nudge,
+    /// e.g. when aligning it with the armrests of a wheelchair. 30 mm is a good start.
+    pub fn slow_approach(mut self, distance: u16) -> Self {
+        self.tuning.slow_approach = Some(distance);
+        self
+    }
+
+    /// Predict where the desk comes to a halt from its speed and the stop distance measured by
+    /// `Idasen::profile`, and stop at the right moment to end up there. More precise than
+    /// slowing down near the target, which is only done when neither this nor
+    /// `Idasen::known_profile` gives a profile.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Handle the desk moving away from the target for more than a second, i.e. someone
+    /// pressing the paddle, as set in `policy`. Moves abort by default instead of fighting the
+    /// user.
+    pub fn on_override(mut self, policy: OverridePolicy) -> Self {
+        self.on_override = policy;
+        self
+    }
+
+    /// Call `on_progress` with the position and speed, both in tenth of millimetres, on every
+    /// step of the move.
+    pub fn on_progress(mut self, on_progress: impl FnMut(u16, i16) + Send + 'a) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+}
+
+impl Default for MoveOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MoveOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MoveOptions")
+            .field("tuning", &self.tuning)
+            .field("profile", &self.profile)
+            .field("on_override", &self.on_override)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Move desk to a desired position. The precision is decent, usually less than 1mm off.
+    pub async fn move_to(&self, target_position: u16) -> Result<(), Error> {
+        self.move_to_with(target_position, MoveOptions::new()).await
+    }
+
+    /// Like `move_to`, leaving the desk alone if it is within `deadband` tenth of millimetres
+    /// of the target, e.g. for a scheduler that would otherwise wake the motor for a 2 mm
+    /// correction on every run. Returns whether the desk was moved.
+    pub async fn move_to_if_needed(
+        &self,
+        target_position: u16,
+        deadband: u16,
+    ) -> Result<bool, Error> {
+        let range = self.height_range();
+        if !range.contains(&target_position) {
+            return Err(Error::out_of_range(target_position, &range));
+        }
+        let position = self.position().await?;
+        if (position as i32 - target_position as i32).abs() <= deadband as i32 {
+            return Ok(false);
+        }
+        self.move_to(target_position).await?;
+        Ok(true)
+    }
+
+    /// Move desk up by `distance` tenth of millimetres, or as far up as it goes.
+    pub async fn move_up_by(&self, distance: u16) -> Result<(), Error> {
+        let position = self.read_position().await?;
+        let top = *self.height_range().end();
+        self.move_to(position.saturating_add(distance).min(top))
+            .await
+    }
+
+    /// Move desk down by `distance` tenth of millimetres, or as far down as it goes.
+    pub async fn move_down_by(&self, distance: u16) -> Result<(), Error> {
+        let position = self.read_position().await?;
+        let bottom = *self.height_range().start();
+        self.move_to(position.saturating_sub(distance).max(bottom))
+            .await
+    }
+
+    /// Move desk to a height given in any unit, like `move_to`.
+    ///
+    /// ```no_run
+    /// # async fn example(desk: idasen::Idasen<impl idasen::DeskTransport>) -> Result<(), idasen::Error> {
+    /// desk.move_to_height(idasen::Height::from_cm(72.5)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn move_to_height(&self, height: Height) -> Result<(), Error> {
+        self.move_to(height.into()).await
+    }
+
+    /// Move desk to `percent` of its travel, 0 being the lowest position it may move to and 100
+    /// the highest, e.g. for a slider. The travel is the `height_range`, narrowed down by the
+    /// `restriction` if there is one. Fails with `Error::PositionNotInRange` outside `0..=100`,
+    /// reporting the height the percentage would be at.
+    pub async fn move_to_percent(&self, percent: f32) -> Result<(), Error> {
+        let travel = self.travel();
+        let span = travel.end().saturating_sub(*travel.start());
+        let offset = span as f32 * percent / 100.0;
+        if !(0.0..=100.0).contains(&percent) {
+            // Saturates at the bounds of `u16`.
+            let requested = (*travel.start() as f32 + offset).round() as u16;
+            return Err(Error::out_of_range(requested, &travel));
+        }
+        self.move_to(travel.start() + offset.round() as u16).await
+    }
+
+    /// Where the desk is within its travel, in percent, see `move_to_percent`. A desk outside
+    /// the restriction is reported at 0 or 100.
+    pub async fn position_percent(&self) -> Result<f32, Error> {
+        let position = self.position().await?;
+        let travel = self.travel();
+        let span = travel.end().saturating_sub(*travel.start()).max(1);
+        let offset = position.clamp(*travel.start(), *travel.end()) - travel.start();
+        Ok((offset as f32 * 100.0 / span as f32).min(100.0))
+    }
+
+    fn travel(&self) -> RangeInclusive<u16> {
+        let range = self.height_range();
+        match self.restriction() {
+            Some(restriction) => {
+                let start = max(*range.start(), *restriction.range.start());
+                start..=min(*range.end(), *restriction.range.end()).max(start)
+            }
+            None => range,
+        }
+    }
+
+    #[cfg(feature = "progress")]
+    pub async fn move_to_with_progress(&self, target_position: u16) -> Result<(), Error> {
+        let style = ProgressStyle::default_bar().template("{spinner} {wide_bar} [{msg}]");
+        self.move_to_with_progress_style(target_position, style, LengthUnit::Centimetres)
+            .await
+    }
+
+    /// Like `move_to_with_progress`, with the bar drawn in `style`. The `{msg}` placeholder
+    /// shows the current position in `unit`.
+    #[cfg(feature = "progress")]
+    pub async fn move_to_with_progress_style(
+        &self,
+        target_position: u16,
+        style: ProgressStyle,
+        unit: LengthUnit,
+    ) -> Result<(), Error> {
+        let initial_position = (target_position as i16 - self.read_position().await? as i16).abs();
+        let progress = ProgressBar::new(initial_position as u64);
+        progress.set_style(style);
+        let options = MoveOptions::new().on_progress(|position, speed| {
+            progress.inc(speed as u64);
+            progress.set_message(unit.format(position));
+        });
+        self.move_to_with(target_position, options).await?;
+        progress.finish();
+        Ok(())
+    }
+
+    /// Move desk to a desired position as set in `options`.
+    ///
+    /// Dropping the future, e.g. in a `select!` or on a timeout, stops the desk where it is.
+    pub async fn move_to_with(
+        &self,
+        target_position: u16,
+        options: MoveOptions<'_>,
+    ) -> Result<(), Error> {
+        let range = self.height_range();
+        if !range.contains(&target_position) {
+            return Err(Error::out_of_range(target_position, &range));
+        }
+        self.check_move(target_position)?;
+        self.emit(DeskEvent::MoveStarted {
+            target: target_position,
+        });
+        let mut moving = StopOnDrop(Some(self));
+        let result = self.run_move(target_position, options).await;
+        moving.0 = None;
+        self.end_move(&result);
+        result
+    }
+
+    /// Move desk to a desired position by writing it to `REFERENCE_INPUT_UUID`, leaving the
+    /// acceleration and braking to the desk's controller. Smoother than `move_to` and not
+    /// thrown off by a slow Bluetooth stack, but only as precise as the desk itself.
+    ///
+    /// Fails with `Error::CharacteristicsNotFound` on desks without the characteristic and
+    /// with `Error::DeskNotMoving` if the desk came to rest away from the target, e.g. because
+    /// it was blocked. Dropping the future stops the desk, like `move_to_with`.
+    pub async fn move_to_native(&self, target_position: u16) -> Result<(), Error> {
+        let range = self.height_range();
+        if !range.contains(&target_position) {
+            return Err(Error::out_of_range(target_position, &range));
+        }
+        if !self.has_characteristic(REFERENCE_INPUT_UUID) {
+            return Err(Error::CharacteristicsNotFound(format!(
+                "Reference input {}",
+                REFERENCE_INPUT_UUID
+            )));
+        }
+        self.check_move(target_position)?;
+        self.emit(DeskEvent::MoveStarted {
+            target: target_position,
+        });
+        let mut moving = StopOnDrop(Some(self));
+        let result = self.run_native_move(target_position).await;
+        moving.0 = None;
+        self.end_move(&result);
+        result
+    }
+
+    fn end_move(&self, result: &Result<(), Error>) {
+        self.move_ended();
+        self.emit(match result {
+            Ok(()) => DeskEvent::MoveCompleted,
+            Err(_) => DeskEvent::MoveAborted,
+        });
+    }
+
+    async fn run_native_move(&self, target_position: u16) -> Result<(), Error> {
+        let reference = position_to_bytes(self.calibration().remove(target_position));
+        self.write_raw(CONTROL_UUID, &WAKE_UP, self.command_write_type())
+            .await?;
+        self.stop().await?;
+        let started = Instant::now();
+        loop {
+            self.write_raw(REFERENCE_INPUT_UUID, &reference, self.command_write_type())
+                .await?;
+            tokio::time::sleep(REFERENCE_INTERVAL).await;
+            let current = self.read_position_and_speed().await?;
+            if started.elapsed() > MOVE_TIMEOUT {
+                return self.time_out(current.position).await;
+            }
+            if current.speed != 0 {
+                continue;
+            }
+            if (current.position as i32 - target_position as i32).abs() <= REFERENCE_TOLERANCE {
+                return Ok(());
+            }
+            if started.elapsed() >= REFERENCE_START {
+                self.stop().await?;
+                return Err(Error::DeskNotMoving);
+            }
+        }
+    }
+
+    async fn time_out(&self, last_position: u16) -> Result<(), Error> {
+        self.stop().await?;
+        Err(Error::MoveTimeout { last_position })
+    }
+
+    async fn wait_until_resting(&self, tick: Duration) -> Result<PositionSpeed, Error> {
+        loop {
+            tokio::time::sleep(tick).await;
+            let current = self.read_position_and_speed().await?;
+            if current.speed == 0 {
+                return Ok(current);
+            }
+        }
+    }
+
+    async fn run_move(
+        &self,
+        target_position: u16,
+        mut options: MoveOptions<'_>,
+    ) -> Result<(), Error> {
+        let profile = options.profile.or_else(|| self.known_profile());
+        let mut current = self.read_position_and_speed().await?;
+        let notifications = match self.position_and_speed_stream().await {
+            Ok(notifications) => Some(notifications),
+            // The position is read every tick instead.
+            Err(Error::CannotSubscribePosition) => None,
+            Err(error) => return Err(error),
+        };
+        tokio::pin!(notifications);
+        let mut last_position = current.position;
+        let mut last_moved_at = Instant::now();
+        let started = last_moved_at;
+        let mut next_tick = last_moved_at;
+        let mut current_at = last_moved_at;
+        let start_position = current.position;
+        let mut top_speed = 0;
+        let mut moving_away_since = None;
+        let target_position = target_position as i16;
+        loop {
+            if matches!(options.tuning.timeout, Some(timeout) if started.elapsed() > timeout) {
+                return self.time_out(current.position).await;
+            }
+            let current_position = current.position as i16;
+            if current.position != last_position {
+                last_position = current.position;
+                last_moved_at = Instant::now();
+            } else if let Some(watchdog) = options.tuning.watchdog {
+                if last_moved_at.elapsed() > watchdog {
+                    self.stop().await?;
+                    return Err(Error::DeskNotMoving);
+                }
+            }
+            let going_up = match target_position.cmp(&current_position) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                Ordering::Equal => return Ok(()),
+            };
+            let remaining_distance = (target_position - current_position).abs();
+
+            if current.speed != 0 && (current.speed > 0) != going_up {
+                let since = *moving_away_since.get_or_insert_with(Instant::now);
+                if since.elapsed() > OVERRIDE_AFTER {
+                    match options.on_override {
+                        OverridePolicy::Abort => return Err(Error::ManualOverride),
+                        OverridePolicy::Resume => {
+                            current = self.wait_until_resting(options.tuning.tick).await?;
+                            moving_away_since = None;
+                            last_moved_at = Instant::now();
+                            continue;
+                        }
+                        OverridePolicy::Ignore => {}
+                    }
+                }
+            } else {
+                moving_away_since = None;
+            }
+
+            // The desk reports hundredths of millimetres per second.
+            let speed = current.speed.saturating_abs() / 10;
+            top_speed = max(top_speed, speed);
+
+            if let Some(on_progress) = options.on_progress.as_mut() {
+                on_progress(current.position, speed);
+            }
+
+            let coasting =
+                profile.and_then(|profile| coasting_distance(&profile, going_up, current.speed));
+            let approaching_slowly = matches!(
+                options.tuning.slow_approach,
+                Some(distance) if remaining_distance < distance as i16
+            );
+            let within_tolerance = remaining_distance <= options.tuning.tolerance as i16;
+            if within_tolerance && (speed == 0 || !approaching_slowly) {
+                // Close enough.
+                self.stop().await?;
+                // Shorter moves end before reaching full speed.
+                if (start_position as i16 - current_position).abs() >= LOAD_DISTANCE {
+                    self.record_top_speed(current.position > start_position, top_speed as u16);
+                }
+                break;
+            } else if approaching_slowly {
+                // Nudge the desk on from rest only, so every nudge starts from a known position.
+                if speed == 0 && !within_tolerance {
+                    self.keep_moving(going_up).await?;
+                    tokio::time::sleep(SLOW_PULSE).await;
+                }
+                self.stop().await?;
+            } else if let Some(delay) = coasting.and_then(|coasting| {
+                // Account for the distance covered since the position was received.
+                let travelled = (speed as f64 * current_at.elapsed().as_secs_f64()) as i16;
+                stop_delay(
+                    remaining_distance - coasting as i16 - travelled,
+                    speed,
+                    options.tuning.tick,
+                )
+            }) {
+                // The desk reaches the stop point before the next tick, and coasts the rest of
+                // the way from there.
+                tokio::time::sleep(delay).await;
+                self.stop().await?;
+            } else {
+                self.keep_moving(going_up).await?;
+
+                // If we're either:
+                // * less than the braking distance, or:
+                // * less than the braking time from target, unless the stop point is predicted
+                // then we need to stop every iteration so that we don't overshoot
+                let braking_distance = options.tuning.braking_distance as i16;
+                let stop_within = if profile.is_some() {
+                    braking_distance
+                } else {
+                    let braking_time = speed as f64 * options.tuning.braking_time.as_secs_f64();
+                    max(braking_time as i16, braking_distance)
+                };
+                if remaining_distance < stop_within {
+                    self.stop().await?;
+                }
+            }
+
+            // Keep the latest notification until the next tick. They only come while the desk
+            // moves, read once it has stopped.
+            next_tick = max(next_tick + options.tuning.tick, Instant::now());
+            let mut latest = None;
+            if let Some(mut notifications) = notifications.as_mut().as_pin_mut() {
+                while let Ok(Some(notification)) =
+                    tokio::time::timeout_at(next_tick, notifications.next()).await
+                {
+                    latest = Some((notification, Instant::now()));
+                }
+            }
+            tokio::time::sleep_until(next_tick).await;
+            let (position, received_at) = match latest {
+                Some(latest) => latest,
+                None => (self.read_position_and_speed().await?, Instant::now()),
+            };
+            current = position;
+            current_at = received_at;
+        }
+
+        Ok(())
+    }
+}
+
+// Stops the desk when a move is dropped before it ended.
+pub(crate) struct StopOnDrop<'a, T: DeskTransport>(pub(crate) Option<&'a Idasen<T>>);
+
+impl<T: DeskTransport> Drop for StopOnDrop<'_, T> {
+    fn drop(&mut self) {
+        if let Some(desk) = self.0.take() {
+            desk.stop_in_background();
+            desk.move_ended();
+            desk.emit(DeskEvent::MoveAborted);
+        }
+    }
+}
+
+// Tenth of millimetres the desk travels after a stop at `speed`, as reported by the desk, or
+// `None` unless it moves towards the target at a measurable speed. The braking distance grows
+// with the square of the speed.
+fn coasting_distance(profile: &Profile, going_up: bool, speed: i16) -> Option<u16> {
+    let direction = if going_up { &profile.up } else { &profile.down };
+    if speed.unsigned_abs() / 10 == 0 || (speed > 0) != going_up || direction.speed == 0 {
+        return None;
+    }
+    let ratio = (speed.unsigned_abs() as f64 / 10.0) / direction.speed as f64;
+    Some((direction.stop_distance as f64 * ratio * ratio).round() as u16)
+}
+
+// Time until the desk moving at `speed` covers `distance`, if that is within `tick`. `None`
+// at rest, as the desk then never gets there.
+fn stop_delay(distance: i16, speed: i16, tick: Duration) -> Option<Duration> {
+    if distance <= 0 {
+        return Some(Duration::default());
+    }
+    if speed <= 0 {
+        return None;
+    }
+    let delay = Duration::from_secs_f64(distance as f64 / speed as f64);
+    if delay < tick {
+        Some(delay)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DirectionProfile;
+    use std::time::UNIX_EPOCH;
+
+    fn profile() -> Profile {
+        let direction = DirectionProfile {
+            speed: 380,
+            acceleration: 1900,
+            stop_distance: 40,
+        };
+        Profile {
+            up: direction,
+            down: direction,
+            at: UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn crawling_desk_has_no_stop_point() {
+        // Ramping up or down, the desk reports speeds below a tenth of a millimetre per second.
+        assert_eq!(coasting_distance(&profile(), true, 5), None);
+        assert_eq!(coasting_distance(&profile(), false, -5), None);
+        assert_eq!(stop_delay(30, 5 / 10, Duration::from_millis(100)), None);
+    }
+
+    #[test]
+    fn stop_point_scales_with_the_speed() {
+        assert_eq!(coasting_distance(&profile(), true, 3800), Some(40));
+        assert_eq!(coasting_distance(&profile(), true, 1900), Some(10));
+        assert_eq!(coasting_distance(&profile(), true, -3800), None);
+        assert_eq!(
+            stop_delay(19, 380, Duration::from_millis(100)),
+            Some(Duration::from_millis(50))
+        );
+    }
+}
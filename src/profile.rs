@@ -0,0 +1,237 @@
+use crate::storage::SingleFile;
+use crate::{DeskTransport, Error, Idasen, Storage};
+use std::convert::TryFrom;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+// Distance travelled in each direction while profiling, in tenth of millimetres.
+const SEGMENT_DISTANCE: u16 = 600;
+// Fraction of the travel speed taken as the end of acceleration.
+const ACCELERATED: f64 = 0.9;
+// Give up if the desk doesn't cover the segment in this time, e.g. because it is blocked.
+const SEGMENT_TIMEOUT: Duration = Duration::from_secs(20);
+const STORAGE_KEY: &str = "profile";
+// Estimates for a desk that wasn't profiled, roughly how an unloaded desk moves.
+const TYPICAL: DirectionProfile = DirectionProfile {
+    speed: 380,
+    acceleration: 1900,
+    stop_distance: 30,
+};
+
+/// How the desk moves under its current load, measured by `Idasen::profile`.
+///
+/// ```
+/// use idasen::{DirectionProfile, Profile};
+///
+/// let path = std::env::temp_dir().join("idasen-profile-doctest");
+/// let direction = DirectionProfile { speed: 380, acceleration: 1900, stop_distance: 20 };
+/// let profile = Profile { up: direction, down: direction, at: std::time::UNIX_EPOCH };
+/// profile.save(&path).unwrap();
+/// assert_eq!(Profile::load(&path).unwrap(), Some(profile));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Profile {
+    pub up: DirectionProfile,
+    pub down: DirectionProfile,
+    pub at: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirectionProfile {
+    // tenth mm per second
+    pub speed: u16,
+    // tenth mm per second squared, until reaching most of `speed`
+    pub acceleration: u16,
+    // tenth mm travelled after the stop command, from the last position read before it
+    pub stop_distance: u16,
+}
+
+impl Profile {
+    /// Time to move from `from` to `to`, both in tenth of millimetres.
+    pub fn travel_time(&self, from: u16, to: u16) -> Duration {
+        let direction = if to > from { &self.up } else { &self.down };
+        direction.travel_time(distance(from, to))
+    }
+
+    /// Read a profile saved by `save`. Returns `None` if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        Self::load_from(SingleFile(path.as_ref().to_path_buf()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_to(SingleFile(path.as_ref().to_path_buf()))
+    }
+
+    /// Read a profile saved by `save_to`, stored under the key `profile`.
+    pub fn load_from(storage: impl Storage) -> io::Result<Option<Self>> {
+        let content = match storage.read(STORAGE_KEY)? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid desk profile");
+        let mut fields = content.split_whitespace();
+        let mut next = || -> io::Result<u64> {
+            fields
+                .next()
+                .ok_or_else(invalid)?
+                .parse()
+                .map_err(|_| invalid())
+        };
+        let mut direction = || -> io::Result<DirectionProfile> {
+            let number = |value: u64| u16::try_from(value).map_err(|_| invalid());
+            Ok(DirectionProfile {
+                speed: number(next()?)?,
+                acceleration: number(next()?)?,
+                stop_distance: number(next()?)?,
+            })
+        };
+        let up = direction()?;
+        let down = direction()?;
+        let seconds = next()?;
+        Ok(Some(Self {
+            up,
+            down,
+            at: UNIX_EPOCH + Duration::from_secs(seconds),
+        }))
+    }
+
+    pub fn save_to(&self, storage: impl Storage) -> io::Result<()> {
+        let seconds = self
+            .at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let line =
+            |d: &DirectionProfile| format!("{} {} {}\n", d.speed, d.acceleration, d.stop_distance);
+        storage.write(
+            STORAGE_KEY,
+            &format!("{}{}{}\n", line(&self.up), line(&self.down), seconds),
+        )
+    }
+}
+
+impl DirectionProfile {
+    /// Time to travel `distance` tenth of millimetres, accelerating from rest.
+    ///
+    /// ```
+    /// use idasen::DirectionProfile;
+    /// use std::time::Duration;
+    ///
+    /// let direction = DirectionProfile { speed: 400, acceleration: 2000, stop_distance: 30 };
+    /// // 40 tenth mm to reach full speed in 0.2 s, then 360 more at full speed.
+    /// assert_eq!(direction.travel_time(400), Duration::from_millis(1100));
+    /// ```
+    pub fn travel_time(&self, distance: u16) -> Duration {
+        if self.speed == 0 || self.acceleration == 0 {
+            return Duration::default();
+        }
+        let speed = self.speed as f64;
+        let acceleration = self.acceleration as f64;
+        let distance = distance as f64;
+        let accelerating = speed * speed / (2.0 * acceleration);
+        let seconds = if distance < accelerating {
+            (2.0 * distance / acceleration).sqrt()
+        } else {
+            speed / acceleration + (distance - accelerating) / speed
+        };
+        Duration::from_secs_f64(seconds)
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Estimate how long moving to `target_position` takes, e.g. to show an ETA before the
+    /// move starts. Uses the `known_profile`, or a typical desk's if there is none.
+    pub async fn estimate_travel_time(&self, target_position: u16) -> Result<Duration, Error> {
+        let position = self.read_position().await?;
+        let profile = self.known_profile().unwrap_or(Profile {
+            up: TYPICAL,
+            down: TYPICAL,
+            at: UNIX_EPOCH,
+        });
+        Ok(profile.travel_time(position, target_position))
+    }
+
+    /// Measure speed, acceleration and stop distance by moving the desk 6 cm up and back down.
+    /// Needs that much room above the current position. The result becomes the
+    /// `known_profile`.
+    pub async fn profile(&self) -> Result<Profile, Error> {
+        let position = self.read_position().await?;
+        let range = self.height_range();
+        let room = *range.start()..=range.end() - SEGMENT_DISTANCE;
+        if !room.contains(&position) {
+            return Err(Error::out_of_range(position, &room));
+        }
+
+        let up = self.profile_direction(true).await?;
+        let down = self.profile_direction(false).await?;
+        let profile = Profile {
+            up,
+            down,
+            at: SystemTime::now(),
+        };
+        self.use_profile(profile);
+        Ok(profile)
+    }
+
+    async fn profile_direction(&self, going_up: bool) -> Result<DirectionProfile, Error> {
+        let start = self.read_position().await?;
+        let started_at = Instant::now();
+        let mut samples = vec![(Duration::default(), start)];
+        let mut travelled = 0;
+        while travelled < SEGMENT_DISTANCE {
+            if started_at.elapsed() > SEGMENT_TIMEOUT {
+                self.stop().await?;
+                return Err(Error::DeskNotMoving);
+            }
+            self.keep_moving(going_up).await?;
+            let position = self.read_position().await?;
+            samples.push((started_at.elapsed(), position));
+            travelled = distance(start, position);
+        }
+
+        // Measured from the position read right before the stop, as the move loop sees it.
+        self.stop().await?;
+        let (_, stopped_at) = samples[samples.len() - 1];
+        let mut last = self.read_position().await?;
+        loop {
+            let position = self.read_position().await?;
+            if position == last {
+                break;
+            }
+            last = position;
+        }
+
+        // The second half of the segment is covered at full speed.
+        let (half_time, half_position) = samples[samples.len() / 2];
+        let (end_time, end_position) = samples[samples.len() - 1];
+        let speed = distance(half_position, end_position) as f64
+            / (end_time - half_time).as_secs_f64().max(f64::EPSILON);
+
+        let accelerated_at = samples
+            .windows(2)
+            .find(|pair| {
+                let seconds = (pair[1].0 - pair[0].0).as_secs_f64().max(f64::EPSILON);
+                distance(pair[0].1, pair[1].1) as f64 / seconds >= speed * ACCELERATED
+            })
+            .map(|pair| pair[1].0)
+            .unwrap_or(end_time);
+
+        Ok(DirectionProfile {
+            speed: speed.round() as u16,
+            acceleration: (speed * ACCELERATED / accelerated_at.as_secs_f64().max(f64::EPSILON))
+                .min(u16::MAX as f64)
+                .round() as u16,
+            stop_distance: distance(stopped_at, last),
+        })
+    }
+}
+
+fn distance(from: u16, to: u16) -> u16 {
+    (to as i32 - from as i32).unsigned_abs() as u16
+}
@@ -0,0 +1,147 @@
+//! The desk's Bluetooth protocol: characteristic UUIDs, command payloads and the position
+//! encoding, usable without a Bluetooth stack, e.g. to decode a packet capture.
+//!
+//! The constants and functions are also re-exported at the crate root.
+
+use uuid::Uuid;
+
+/// Linak service holding `CONTROL_UUID`, advertised by the desk.
+pub const CONTROL_SERVICE_UUID: Uuid = Uuid::from_u128(0x99fa0001_338a_1024_8a49_009c0215f78a);
+/// Characteristic accepting the movement commands.
+pub const CONTROL_UUID: Uuid = Uuid::from_bytes( [ 0x99, 0xfa, 0x00, 0x02, 0x33, 0x8a, 0x10, 0x24, 0x8a, 0x49, 0x00, 0x9c, 0x02, 0x15, 0xf7, 0x8a, ]);
+/// Characteristic reporting the position and speed, readable and notifying.
+pub const POSITION_UUID: Uuid = Uuid::from_bytes([ 0x99, 0xfa, 0x00, 0x21, 0x33, 0x8a, 0x10, 0x24, 0x8a, 0x49, 0x00, 0x9c, 0x02, 0x15, 0xf7, 0x8a, ]);
+/// Linak DPG characteristic the app reads and writes the desk's settings through, e.g. the
+/// memory positions and the reminder. Its commands aren't documented, experiment with
+/// `Idasen::subscribe_raw` and `Idasen::write_raw`.
+pub const DPG_UUID: Uuid = Uuid::from_u128(0x99fa0011_338a_1024_8a49_009c0215f78a);
+/// Characteristic accepting a target position, which the desk then drives to on its own as
+/// long as the target is written again every few hundred milliseconds.
+pub const REFERENCE_INPUT_UUID: Uuid = Uuid::from_u128(0x99fa0031_338a_1024_8a49_009c0215f78a);
+/// Firmware Revision String of the standard Device Information service.
+pub const FIRMWARE_REVISION_UUID: Uuid = Uuid::from_u128(0x00002a26_0000_1000_8000_00805f9b34fb);
+/// Manufacturer Name String of the standard Device Information service.
+pub const MANUFACTURER_NAME_UUID: Uuid = Uuid::from_u128(0x00002a29_0000_1000_8000_00805f9b34fb);
+/// Model Number String of the standard Device Information service.
+pub const MODEL_NUMBER_UUID: Uuid = Uuid::from_u128(0x00002a24_0000_1000_8000_00805f9b34fb);
+/// Serial Number String of the standard Device Information service.
+pub const SERIAL_NUMBER_UUID: Uuid = Uuid::from_u128(0x00002a25_0000_1000_8000_00805f9b34fb);
+
+/// Control payloads written to `CONTROL_UUID`.
+pub const UP: [u8; 2] = [0x47, 0x00];
+pub const DOWN: [u8; 2] = [0x46, 0x00];
+pub const STOP: [u8; 2] = [0xFF, 0x00];
+/// Wakes the controller, which ignores the first command after idling otherwise.
+pub const WAKE_UP: [u8; 2] = [0xFE, 0x00];
+
+/// Command written to `CONTROL_UUID`.
+///
+/// ```
+/// use idasen::protocol::Command;
+///
+/// assert_eq!(Command::Up.to_bytes(), idasen::UP);
+/// assert_eq!(Command::from_bytes(&[0xFF, 0x00]), Some(Command::Stop));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    Up,
+    Down,
+    Stop,
+    WakeUp,
+}
+
+impl Command {
+    pub fn to_bytes(self) -> [u8; 2] {
+        match self {
+            Command::Up => UP,
+            Command::Down => DOWN,
+            Command::Stop => STOP,
+            Command::WakeUp => WAKE_UP,
+        }
+    }
+
+    /// The command a payload written to `CONTROL_UUID` stands for, `None` if it is unknown.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        [Command::Up, Command::Down, Command::Stop, Command::WakeUp]
+            .iter()
+            .copied()
+            .find(|command| command.to_bytes() == bytes)
+    }
+}
+
+pub const MIN_HEIGHT: u16 = 6200;
+pub const MAX_HEIGHT: u16 = 12700;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionSpeed {
+    // tenth mm
+    pub position: u16,
+    // hundredth mm per second, positive when going up
+    pub speed: i16,
+}
+
+/// convert tenth of millimeters to the bytes written to `REFERENCE_INPUT_UUID`
+///
+/// ```
+/// assert_eq!(idasen::position_to_bytes(7305), [0x51, 0x04]);
+/// assert_eq!(idasen::position_to_bytes(idasen::MIN_HEIGHT), [0x00, 0x00]);
+/// ```
+pub fn position_to_bytes(position: u16) -> [u8; 2] {
+    position.saturating_sub(MIN_HEIGHT).to_le_bytes()
+}
+
+/// convert desk response from bytes to tenth of millimeters and hundredths of millimetres per
+/// second
+///
+/// Panics if `bytes` is shorter than 4 bytes, positions too high for a `u16` are cut off at
+/// `u16::MAX`. Use `try_bytes_to_position_speed` for input that may be malformed.
+///
+/// ```
+/// assert_eq!(idasen::bytes_to_position_speed(&[0x64, 0x19, 0x00, 0x00]), idasen::PositionSpeed{ position: idasen::MAX_HEIGHT, speed: 0 });
+/// assert_eq!(idasen::bytes_to_position_speed(&[0x00, 0x00, 0x00, 0x00]), idasen::PositionSpeed{ position: idasen::MIN_HEIGHT, speed: 0 });
+/// assert_eq!(idasen::bytes_to_position_speed(&[0x51, 0x04, 0x00, 0x00]), idasen::PositionSpeed{ position: 7305, speed: 0 });
+/// assert_eq!(idasen::bytes_to_position_speed(&[0x08, 0x08, 0x00, 0x00]), idasen::PositionSpeed{ position: 8256, speed: 0 });
+/// assert_eq!(idasen::bytes_to_position_speed(&[0x64, 0x18, 0x00, 0x00]), idasen::PositionSpeed{ position: 12444, speed: 0 });
+/// ```
+pub fn bytes_to_position_speed(bytes: &[u8]) -> PositionSpeed {
+    let position = u16::from_le_bytes([bytes[0], bytes[1]]).saturating_add(MIN_HEIGHT);
+    let speed = i16::from_le_bytes([bytes[2], bytes[3]]);
+    PositionSpeed {
+        position,
+        speed
+    }
+}
+
+/// Like `bytes_to_position_speed`, `None` if `bytes` is shorter than 4 bytes or the position
+/// doesn't fit a `u16`, e.g. for a packet capture or a fuzzer. Bytes after the first 4 are
+/// ignored.
+///
+/// ```
+/// assert_eq!(idasen::try_bytes_to_position_speed(&[0x51, 0x04, 0x00]), None);
+/// assert_eq!(idasen::try_bytes_to_position_speed(&[0xFF, 0xFF, 0x00, 0x00]), None);
+/// assert_eq!(idasen::try_bytes_to_position_speed(&[0x51, 0x04, 0x10, 0x00]), Some(idasen::PositionSpeed{ position: 7305, speed: 16 }));
+/// ```
+pub fn try_bytes_to_position_speed(bytes: &[u8]) -> Option<PositionSpeed> {
+    match *bytes {
+        [position_low, position_high, speed_low, speed_high, ..] => Some(PositionSpeed {
+            position: u16::from_le_bytes([position_low, position_high]).checked_add(MIN_HEIGHT)?,
+            speed: i16::from_le_bytes([speed_low, speed_high]),
+        }),
+        _ => None,
+    }
+}
+
+/// convert tenth of millimeters and a speed to the bytes the desk reports, the inverse of
+/// `bytes_to_position_speed`
+///
+/// ```
+/// let position_speed = idasen::PositionSpeed { position: 7305, speed: -120 };
+/// let bytes = idasen::position_speed_to_bytes(position_speed);
+/// assert_eq!(idasen::bytes_to_position_speed(&bytes), position_speed);
+/// ```
+pub fn position_speed_to_bytes(position_speed: PositionSpeed) -> [u8; 4] {
+    let [position_low, position_high] = position_to_bytes(position_speed.position);
+    let [speed_low, speed_high] = position_speed.speed.to_le_bytes();
+    [position_low, position_high, speed_low, speed_high]
+}
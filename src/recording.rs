@@ -0,0 +1,185 @@
+use crate::{DeskTransport, Error, Idasen};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+// How long the desk has to rest before the height counts as a step of the routine.
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// Heights the desk moved to and when, recorded with `Recording` and moved through again with
+/// `Idasen::replay`, e.g. for a demo rig, an exercise routine or a bug report about a move.
+///
+/// Saved as text, one step per line: the milliseconds since the start and the position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Routine {
+    pub steps: Vec<RoutineStep>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoutineStep {
+    /// When the desk started moving to `position`, since the start of the routine.
+    pub at: Duration,
+    /// Height the desk came to rest at, in tenth of millimetres.
+    pub position: u16,
+}
+
+/// The desk's moves being recorded into a `Routine`, also those made with the paddle.
+///
+/// Positions are received on a task of its own from the desk's notifications; every height
+/// the desk rested at for a second becomes a step. Dropping the recording discards it.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Idasen, Recording};
+///
+/// let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+/// let recording = Recording::start(&desk).await.unwrap();
+/// desk.move_to(9000).await.unwrap();
+/// tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+/// let routine = recording.stop().await.unwrap();
+///
+/// desk.move_to(7000).await.unwrap();
+/// desk.replay(&routine).await.unwrap();
+/// assert!(desk.position().await.unwrap() > 8900);
+/// # });
+/// ```
+pub struct Recording<'a, T: DeskTransport> {
+    desk: &'a Idasen<T>,
+    started: Instant,
+    // Positions and when they were received, since `started`.
+    samples: Arc<Mutex<Vec<(Duration, u16)>>>,
+    task: JoinHandle<()>,
+}
+
+impl<'a, T: DeskTransport + 'static> Recording<'a, T> {
+    /// Start recording from the desk's current position.
+    pub async fn start(desk: &'a Idasen<T>) -> Result<Recording<'a, T>, Error> {
+        let notifications = desk.position_and_speed_stream().await?;
+        let position = desk.read_position().await?;
+        let started = Instant::now();
+        let samples = Arc::new(Mutex::new(vec![(Duration::from_secs(0), position)]));
+        let received = samples.clone();
+        let task = tokio::spawn(async move {
+            tokio::pin!(notifications);
+            while let Some(position_speed) = notifications.next().await {
+                let sample = (started.elapsed(), position_speed.position);
+                received.lock().unwrap().push(sample);
+            }
+        });
+        Ok(Self {
+            desk,
+            started,
+            samples,
+            task,
+        })
+    }
+}
+
+impl<T: DeskTransport> Recording<'_, T> {
+    /// End the recording with the desk at its current position.
+    pub async fn stop(self) -> Result<Routine, Error> {
+        let position = self.desk.read_position().await?;
+        self.task.abort();
+        let mut samples = self.samples.lock().unwrap().clone();
+        samples.push((self.started.elapsed() + SETTLE, position));
+        Ok(Routine {
+            steps: steps(&samples),
+        })
+    }
+}
+
+impl<T: DeskTransport> Drop for Recording<'_, T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// The heights rested at, each with the time the desk started moving there.
+fn steps(samples: &[(Duration, u16)]) -> Vec<RoutineStep> {
+    let mut steps: Vec<RoutineStep> = Vec::new();
+    let mut moving_since = None;
+    for (index, &(at, position)) in samples.iter().enumerate() {
+        let since = *moving_since.get_or_insert(at);
+        let settled = match samples.get(index + 1) {
+            Some(&(next, _)) => next.saturating_sub(at) >= SETTLE,
+            None => true,
+        };
+        if settled {
+            moving_since = None;
+            if steps.last().map(|step| step.position) != Some(position) {
+                steps.push(RoutineStep {
+                    at: since,
+                    position,
+                });
+            }
+        }
+    }
+    steps
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Move through the steps of `routine`, each starting as long after the start as it did
+    /// when recorded, or once the previous move is done if that took longer.
+    pub async fn replay(&self, routine: &Routine) -> Result<(), Error> {
+        let started = Instant::now();
+        for step in &routine.steps {
+            tokio::time::sleep_until(started + step.at).await;
+            self.move_to(step.position).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Routine {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Time from the start until the last move starts.
+    pub fn duration(&self) -> Duration {
+        self.steps.last().map(|step| step.at).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Routine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{} {}", step.at.as_millis(), step.position)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Routine {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid routine step");
+        let steps = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (at, position) = line.trim().split_once(' ').ok_or_else(invalid)?;
+                Ok(RoutineStep {
+                    at: Duration::from_millis(at.parse().map_err(|_| invalid())?),
+                    position: position.trim().parse().map_err(|_| invalid())?,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { steps })
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{DeskTransport, Error, Idasen};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// Limits on movement for kiosks, showrooms and automated tests against real hardware, so
+/// buggy client code can't run the desk away. See `Idasen::restrict`.
+///
+/// Moves to targets outside `range` fail with `Error::PositionNotInRange`, and so do `up` and
+/// `down` once the desk is at the edge of the range. A move starting less than `cooldown` after
+/// the previous one ended fails with `Error::CoolingDown`. `write_raw` is not restricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Restriction {
+    pub range: RangeInclusive<u16>,
+    pub cooldown: Duration,
+}
+
+/// Limit on how often moves may start, for desks controlled by chat bots, the HTTP `server` or
+/// other untrusted input, so repeated commands can't thrash the motors. See
+/// `Idasen::limit_rate`.
+///
+/// A move to a target starting less than `min_interval` after the previous one started, or
+/// after `max_per_minute` moves started within the last minute, fails with
+/// `Error::CoolingDown`. `up` and `down` count as a move when they start the desk from rest,
+/// repeating them while the desk runs, as holding a button does, doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RateLimit {
+    pub min_interval: Duration,
+    pub max_per_minute: u32,
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    /// Restrict movement to `band` tenth of millimetres above and below the current position,
+    /// with `cooldown` between moves.
+    pub async fn restrict_around(
+        &self,
+        band: u16,
+        cooldown: Duration,
+    ) -> Result<Restriction, Error> {
+        let position = self.read_position().await?;
+        let limits = self.height_range();
+        let restriction = Restriction {
+            range: position.saturating_sub(band).max(*limits.start())
+                ..=position.saturating_add(band).min(*limits.end()),
+            cooldown,
+        };
+        self.restrict(restriction.clone());
+        Ok(restriction)
+    }
+}
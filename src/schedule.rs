@@ -0,0 +1,1625 @@
+use crate::{DeskTransport, Error, Idasen};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::Instant;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// How far ahead to look for a workday before giving up, covers a year of holidays.
+const MAX_DAYS_AHEAD: i64 = 400;
+
+const DEFAULT_SNOOZE: Duration = Duration::from_secs(10 * 60);
+
+const DEFAULT_AUTO_STAND_CHECK: Duration = Duration::from_secs(60);
+
+const DEFAULT_PRESENCE_CHECK: Duration = Duration::from_secs(10);
+
+// Absences shorter than this are a phone briefly dropping out of range, not the user leaving.
+const DEFAULT_AWAY_FOR: Duration = Duration::from_secs(5 * 60);
+
+// Tokio timers don't advance while the machine is suspended, so long waits are split up and
+// the wall clock is consulted again after each one.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Local time within a day, interpreted in the schedule's time zone.
+///
+/// Written as `HH:MM`, also when serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct TimeOfDay {
+    hour: u8,
+    minute: u8,
+}
+
+impl TimeOfDay {
+    /// Returns `None` if the hour or minute is out of range.
+    pub fn new(hour: u8, minute: u8) -> Option<Self> {
+        if hour < 24 && minute < 60 {
+            Some(Self { hour, minute })
+        } else {
+            None
+        }
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    fn seconds_from_midnight(&self) -> i64 {
+        self.hour as i64 * 3600 + self.minute as i64 * 60
+    }
+
+    fn from_seconds_from_midnight(seconds: i64) -> Self {
+        Self {
+            hour: (seconds / 3600) as u8,
+            minute: (seconds % 3600 / 60) as u8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    fn from_days_since_epoch(days: i64) -> Self {
+        // 1970-01-01 was a Thursday.
+        match (days + 3).rem_euclid(7) {
+            0 => Weekday::Monday,
+            1 => Weekday::Tuesday,
+            2 => Weekday::Wednesday,
+            3 => Weekday::Thursday,
+            4 => Weekday::Friday,
+            5 => Weekday::Saturday,
+            _ => Weekday::Sunday,
+        }
+    }
+}
+
+/// Calendar date in the proleptic Gregorian calendar.
+///
+/// Written as `YYYY-MM-DD`, also when serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Date {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// Returns `None` if the date does not exist.
+    ///
+    /// ```
+    /// use idasen::schedule::Date;
+    ///
+    /// assert!(Date::new(2024, 2, 29).is_some());
+    /// assert!(Date::new(2023, 2, 29).is_none());
+    /// assert!(Date::new(2023, 13, 1).is_none());
+    /// ```
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Self> {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let days_in_month = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        if (1..=days_in_month).contains(&day) {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        Weekday::from_days_since_epoch(self.days_since_epoch())
+    }
+
+    fn days_since_epoch(&self) -> i64 {
+        // https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    pub(crate) fn from_days_since_epoch(days: i64) -> Self {
+        // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u8;
+        let year = (year_of_era + era * 400 + (month <= 2) as i64) as i32;
+        Self { year, month, day }
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError("time of day");
+        let (hour, minute) = s.split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        Self::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError("date");
+        // The year may be negative, so split from the end.
+        let mut fields = s.rsplitn(3, '-');
+        let day = fields.next().ok_or_else(invalid)?;
+        let month = fields.next().ok_or_else(invalid)?;
+        let year = fields.next().ok_or_else(invalid)?;
+        Self::new(
+            year.parse().map_err(|_| invalid())?,
+            month.parse().map_err(|_| invalid())?,
+            day.parse().map_err(|_| invalid())?,
+        )
+        .ok_or_else(invalid)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<String> for TimeOfDay {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<String> for Date {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Date> for String {
+    fn from(date: Date) -> Self {
+        date.to_string()
+    }
+}
+
+/// Text that isn't a valid `TimeOfDay`, `Date` or `DeskCommand`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid {0}")]
+pub struct ParseError(pub(crate) &'static str);
+
+/// Offset of local time from UTC.
+///
+/// Implement it on top of a time zone database (e.g. `chrono-tz`) to get daylight saving
+/// transitions right; `Utc` and `FixedOffset` cover the simple cases.
+pub trait TimeZone: Send + Sync {
+    /// Seconds east of UTC in effect at the given instant.
+    fn utc_offset(&self, at: SystemTime) -> i32;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Utc;
+
+impl TimeZone for Utc {
+    fn utc_offset(&self, _at: SystemTime) -> i32 {
+        0
+    }
+}
+
+/// Constant offset in seconds east of UTC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FixedOffset(pub i32);
+
+impl TimeZone for FixedOffset {
+    fn utc_offset(&self, _at: SystemTime) -> i32 {
+        self.0
+    }
+}
+
+impl<F> TimeZone for F
+where
+    F: Fn(SystemTime) -> i32 + Send + Sync,
+{
+    fn utc_offset(&self, at: SystemTime) -> i32 {
+        self(at)
+    }
+}
+
+/// Days, and optionally hours within them, on which the schedule is active.
+///
+/// ```
+/// use idasen::schedule::{Date, TimeOfDay, Workdays};
+///
+/// let workdays = Workdays::weekdays()
+///     .with_holiday(Date::new(2024, 12, 25).unwrap())
+///     .hours(TimeOfDay::new(8, 0).unwrap(), TimeOfDay::new(17, 0).unwrap());
+/// assert!(workdays.contains(Date::new(2024, 12, 24).unwrap()));
+/// assert!(!workdays.contains(Date::new(2024, 12, 25).unwrap()));
+/// // Saturday
+/// assert!(!workdays.contains(Date::new(2024, 12, 28).unwrap()));
+///
+/// assert!(workdays.is_active(Date::new(2024, 12, 24).unwrap(), TimeOfDay::new(9, 0).unwrap()));
+/// assert!(!workdays.is_active(Date::new(2024, 12, 24).unwrap(), TimeOfDay::new(20, 0).unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Workdays {
+    days: Vec<Weekday>,
+    holidays: Vec<Date>,
+    hours: Option<(TimeOfDay, TimeOfDay)>,
+}
+
+impl Workdays {
+    pub fn every_day() -> Self {
+        Self::only(&[
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+        ])
+    }
+
+    /// Monday to Friday.
+    pub fn weekdays() -> Self {
+        Self::only(&[
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ])
+    }
+
+    pub fn only(days: &[Weekday]) -> Self {
+        Self {
+            days: days.to_vec(),
+            holidays: Vec::new(),
+            hours: None,
+        }
+    }
+
+    /// Exclude a single date, regardless of its weekday.
+    pub fn with_holiday(mut self, date: Date) -> Self {
+        self.holidays.push(date);
+        self
+    }
+
+    /// Only be active between `from` and `to` on workdays, which may wrap past midnight.
+    /// Scheduled moves outside these hours don't fire and auto-stand doesn't kick in.
+    pub fn hours(mut self, from: TimeOfDay, to: TimeOfDay) -> Self {
+        self.hours = Some((from, to));
+        self
+    }
+
+    /// Whether `date` is a workday, regardless of the active hours.
+    pub fn contains(&self, date: Date) -> bool {
+        self.days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    /// Whether `date` is a workday and `time` falls within the active hours.
+    pub fn is_active(&self, date: Date, time: TimeOfDay) -> bool {
+        self.contains(date) && self.within_hours(time)
+    }
+
+    fn within_hours(&self, time: TimeOfDay) -> bool {
+        match self.hours {
+            Some(hours) => within(hours, time),
+            None => true,
+        }
+    }
+}
+
+// Whether `time` falls in the `[from, to)` window, which may wrap past midnight.
+fn within((from, to): (TimeOfDay, TimeOfDay), time: TimeOfDay) -> bool {
+    if from <= to {
+        from <= time && time < to
+    } else {
+        from <= time || time < to
+    }
+}
+
+impl Default for Workdays {
+    fn default() -> Self {
+        Self::every_day()
+    }
+}
+
+/// Move the desk to `position` every day at `at`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub at: TimeOfDay,
+    pub position: u16,
+}
+
+/// Raise the desk after the user has been sitting for too long.
+///
+/// The desk counts as sitting while it is below `sitting_below`. The timer restarts whenever
+/// the desk is raised or the optional presence callback reports the user away.
+#[derive(Clone)]
+pub struct AutoStand {
+    sitting_below: u16,
+    after: Duration,
+    standing: u16,
+    check_every: Duration,
+    quiet_hours: Option<(TimeOfDay, TimeOfDay)>,
+    presence: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl AutoStand {
+    /// Move to `standing` after sitting below `sitting_below` for `after`.
+    pub fn new(sitting_below: u16, after: Duration, standing: u16) -> Self {
+        Self {
+            sitting_below,
+            after,
+            standing,
+            check_every: DEFAULT_AUTO_STAND_CHECK,
+            quiet_hours: None,
+            presence: None,
+        }
+    }
+
+    /// How often the desk position is read, once a minute by default.
+    pub fn check_every(mut self, interval: Duration) -> Self {
+        self.check_every = interval;
+        self
+    }
+
+    /// Never raise the desk between `from` and `to`, which may wrap past midnight.
+    ///
+    /// ```
+    /// use idasen::schedule::{AutoStand, TimeOfDay};
+    /// use std::time::Duration;
+    ///
+    /// let rule = AutoStand::new(8000, Duration::from_secs(3600), 11000)
+    ///     .quiet_hours(TimeOfDay::new(22, 0).unwrap(), TimeOfDay::new(7, 0).unwrap());
+    /// assert!(rule.is_quiet(TimeOfDay::new(23, 30).unwrap()));
+    /// assert!(rule.is_quiet(TimeOfDay::new(6, 59).unwrap()));
+    /// assert!(!rule.is_quiet(TimeOfDay::new(7, 0).unwrap()));
+    /// ```
+    pub fn quiet_hours(mut self, from: TimeOfDay, to: TimeOfDay) -> Self {
+        self.quiet_hours = Some((from, to));
+        self
+    }
+
+    /// Only count sitting time while `present` returns `true`, e.g. backed by the host's idle
+    /// detection.
+    pub fn presence(mut self, present: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.presence = Some(Arc::new(present));
+        self
+    }
+
+    pub fn is_quiet(&self, time: TimeOfDay) -> bool {
+        match self.quiet_hours {
+            Some(quiet_hours) => within(quiet_hours, time),
+            None => false,
+        }
+    }
+
+    fn user_present(&self) -> bool {
+        match &self.presence {
+            Some(present) => present(),
+            None => true,
+        }
+    }
+}
+
+impl fmt::Debug for AutoStand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoStand")
+            .field("sitting_below", &self.sitting_below)
+            .field("after", &self.after)
+            .field("standing", &self.standing)
+            .field("check_every", &self.check_every)
+            .field("quiet_hours", &self.quiet_hours)
+            .finish()
+    }
+}
+
+/// Move the desk to a preset position when the user arrives.
+///
+/// Arrival is signalled either by `Executor::arrived`, e.g. from the host app's own presence
+/// detection, or by a polled presence callback, e.g. whether the user's phone was recently
+/// seen advertising by the adapter. With a callback, the user counts as arriving when it
+/// returns `true` after having returned `false` for at least `away_for`. Either way the
+/// desk only moves on the schedule's workdays.
+#[derive(Clone)]
+pub struct Arrival {
+    position: u16,
+    away_for: Duration,
+    check_every: Duration,
+    presence: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl Arrival {
+    /// Move to `position` when the user arrives.
+    pub fn new(position: u16) -> Self {
+        Self {
+            position,
+            away_for: DEFAULT_AWAY_FOR,
+            check_every: DEFAULT_PRESENCE_CHECK,
+            presence: None,
+        }
+    }
+
+    /// Poll `present` to detect arrivals.
+    pub fn presence(mut self, present: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.presence = Some(Arc::new(present));
+        self
+    }
+
+    /// How long the user must have been away for their return to count, 5 minutes by
+    /// default.
+    pub fn away_for(mut self, away_for: Duration) -> Self {
+        self.away_for = away_for;
+        self
+    }
+
+    /// How often the presence callback is polled, every 10 seconds by default.
+    pub fn check_every(mut self, interval: Duration) -> Self {
+        self.check_every = interval;
+        self
+    }
+
+    // Whether the user just came back after being away long enough.
+    fn came_back(&self, present: bool, away_since: &mut Option<Instant>) -> bool {
+        if !present {
+            away_since.get_or_insert_with(Instant::now);
+            return false;
+        }
+        match away_since.take() {
+            Some(since) => since.elapsed() >= self.away_for,
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for Arrival {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arrival")
+            .field("position", &self.position)
+            .field("away_for", &self.away_for)
+            .field("check_every", &self.check_every)
+            .finish()
+    }
+}
+
+/// Set of daily rules driving the desk to fixed positions.
+///
+/// ```
+/// use idasen::schedule::{FixedOffset, Schedule, TimeOfDay, Workdays};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let schedule = Schedule::new()
+///     .at(TimeOfDay::new(9, 0).unwrap(), 11000)
+///     .at(TimeOfDay::new(12, 30).unwrap(), 7200);
+///
+/// // Thursday, 1970-01-01 10:00 UTC
+/// let now = UNIX_EPOCH + Duration::from_secs(10 * 3600);
+/// let (at, rule) = schedule.next_after(now).unwrap();
+/// assert_eq!(rule.position, 7200);
+/// assert_eq!(at, UNIX_EPOCH + Duration::from_secs(12 * 3600 + 30 * 60));
+///
+/// // after the last rule of the day it wraps to the next morning
+/// let now = UNIX_EPOCH + Duration::from_secs(13 * 3600);
+/// let (at, rule) = schedule.next_after(now).unwrap();
+/// assert_eq!(rule.position, 11000);
+/// assert_eq!(at, UNIX_EPOCH + Duration::from_secs(33 * 3600));
+///
+/// // on weekdays only in UTC+2, Friday evening is followed by Monday 9:00 local time
+/// let schedule = schedule.timezone(FixedOffset(2 * 3600)).workdays(Workdays::weekdays());
+/// let friday_evening = UNIX_EPOCH + Duration::from_secs(24 * 3600 + 20 * 3600);
+/// let (at, _) = schedule.next_after(friday_evening).unwrap();
+/// assert_eq!(at, UNIX_EPOCH + Duration::from_secs(4 * 24 * 3600 + 7 * 3600));
+/// ```
+#[derive(Clone)]
+pub struct Schedule {
+    rules: Vec<Rule>,
+    workdays: Workdays,
+    timezone: Arc<dyn TimeZone>,
+    snooze: Duration,
+    max_skips_per_day: Option<u32>,
+    auto_stand: Option<AutoStand>,
+    arrival: Option<Arrival>,
+}
+
+impl Schedule {
+    /// Create an empty schedule active every day, in UTC.
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            workdays: Workdays::default(),
+            timezone: Arc::new(Utc),
+            snooze: DEFAULT_SNOOZE,
+            max_skips_per_day: None,
+            auto_stand: None,
+            arrival: None,
+        }
+    }
+
+    /// Add a rule moving the desk to `position` at the given time of day.
+    pub fn at(mut self, at: TimeOfDay, position: u16) -> Self {
+        self.add(Rule { at, position });
+        self
+    }
+
+    /// Restrict the schedule to the given days.
+    pub fn workdays(mut self, workdays: Workdays) -> Self {
+        self.workdays = workdays;
+        self
+    }
+
+    /// Interpret rule times in the given time zone instead of UTC.
+    pub fn timezone(mut self, timezone: impl TimeZone + 'static) -> Self {
+        self.timezone = Arc::new(timezone);
+        self
+    }
+
+    /// How long `Executor::snooze` postpones the upcoming move, 10 minutes by default.
+    pub fn snooze_duration(mut self, snooze: Duration) -> Self {
+        self.snooze = snooze;
+        self
+    }
+
+    /// Limit how many moves can be skipped per day, unlimited by default.
+    pub fn max_skips_per_day(mut self, skips: u32) -> Self {
+        self.max_skips_per_day = Some(skips);
+        self
+    }
+
+    /// Raise the desk automatically after prolonged sitting on workdays.
+    pub fn auto_stand(mut self, auto_stand: AutoStand) -> Self {
+        self.auto_stand = Some(auto_stand);
+        self
+    }
+
+    /// Move the desk when the user arrives.
+    pub fn on_arrival(mut self, arrival: Arrival) -> Self {
+        self.arrival = Some(arrival);
+        self
+    }
+
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+        self.rules.sort_by_key(|rule| rule.at);
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Return the first rule firing strictly after `time`, together with the moment it fires.
+    pub fn next_after(&self, time: SystemTime) -> Option<(SystemTime, &Rule)> {
+        let utc = seconds_since_epoch(time)?;
+        let local = utc + self.timezone.utc_offset(time) as i64;
+        let today = local.div_euclid(SECONDS_PER_DAY);
+
+        for day in today..today + MAX_DAYS_AHEAD {
+            if !self.workdays.contains(Date::from_days_since_epoch(day)) {
+                continue;
+            }
+            for rule in self
+                .rules
+                .iter()
+                .filter(|rule| self.workdays.within_hours(rule.at))
+            {
+                let fires_at =
+                    self.local_to_utc(day * SECONDS_PER_DAY + rule.at.seconds_from_midnight());
+                if fires_at > utc {
+                    return Some((UNIX_EPOCH + Duration::from_secs(fires_at as u64), rule));
+                }
+            }
+        }
+        None
+    }
+
+    fn local_day(&self, time: SystemTime) -> i64 {
+        self.local_seconds(time).div_euclid(SECONDS_PER_DAY)
+    }
+
+    fn local_time_of_day(&self, time: SystemTime) -> TimeOfDay {
+        TimeOfDay::from_seconds_from_midnight(self.local_seconds(time).rem_euclid(SECONDS_PER_DAY))
+    }
+
+    fn local_seconds(&self, time: SystemTime) -> i64 {
+        seconds_since_epoch(time).unwrap_or_default() + self.timezone.utc_offset(time) as i64
+    }
+
+    fn local_to_utc(&self, local: i64) -> i64 {
+        // Re-evaluate the offset at the first guess so times right after a DST shift land on
+        // the new offset.
+        let guess = local - self.timezone.utc_offset(to_system_time(local)) as i64;
+        local - self.timezone.utc_offset(to_system_time(guess)) as i64
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schedule")
+            .field("rules", &self.rules)
+            .field("workdays", &self.workdays)
+            .field("snooze", &self.snooze)
+            .field("max_skips_per_day", &self.max_skips_per_day)
+            .field("auto_stand", &self.auto_stand)
+            .field("arrival", &self.arrival)
+            .finish()
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> Option<i64> {
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+fn to_system_time(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)
+}
+
+/// Emitted by the `Executor` while it drives the desk.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ScheduleEvent {
+    /// A rule fired and the desk started moving.
+    MoveStarted { rule: Rule },
+
+    /// The desk reached the rule's position.
+    MoveCompleted { rule: Rule },
+
+    /// Moving the desk failed, the executor carries on with the next rule.
+    MoveFailed { rule: Rule, error: Error },
+
+    /// The upcoming move was postponed.
+    Snoozed { rule: Rule, until: SystemTime },
+
+    /// The move was due but had been skipped.
+    Skipped { rule: Rule },
+
+    /// The user has been sitting too long and the desk started rising.
+    AutoStandStarted { sitting_for: Duration },
+
+    /// The desk reached the auto-stand position.
+    AutoStandCompleted,
+
+    /// Raising the desk failed, the next attempt comes after another full sitting period.
+    AutoStandFailed { error: Error },
+
+    /// The user arrived and the desk started moving to the arrival position.
+    ArrivalStarted,
+
+    /// The desk reached the arrival position.
+    ArrivalCompleted,
+
+    /// Moving the desk on arrival failed.
+    ArrivalFailed { error: Error },
+}
+
+#[derive(Debug)]
+struct Pending {
+    // Rules firing at or before this moment are done with.
+    after: SystemTime,
+    // Original fire time of the snoozed move and the moment it was postponed to.
+    snoozed: Option<(SystemTime, SystemTime)>,
+    // Original fire time of the skipped move.
+    skipped: Option<SystemTime>,
+    // Local day and the number of skips used on it.
+    skips: (i64, u32),
+    // Set by `Executor::arrived` until `run` moves the desk.
+    arrived: bool,
+}
+
+/// Drives a desk according to a `Schedule`.
+///
+/// `snooze` and `skip` act on the upcoming move and can be called from another task while
+/// `run` is in progress, e.g. by sharing the executor through an `Arc`.
+pub struct Executor {
+    schedule: Schedule,
+    events: mpsc::UnboundedSender<ScheduleEvent>,
+    pending: Mutex<Pending>,
+    changed: Notify,
+}
+
+impl Executor {
+    /// Create the executor together with the receiving end of its event channel.
+    pub fn new(schedule: Schedule) -> (Self, mpsc::UnboundedReceiver<ScheduleEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        let executor = Self {
+            schedule,
+            events,
+            pending: Mutex::new(Pending {
+                after: SystemTime::now(),
+                snoozed: None,
+                skipped: None,
+                skips: (0, 0),
+                arrived: false,
+            }),
+            changed: Notify::new(),
+        };
+        (executor, receiver)
+    }
+
+    /// Return the upcoming move and the moment it fires, taking snoozing into account.
+    pub fn next_move(&self) -> Option<(SystemTime, Rule)> {
+        let pending = self.pending.lock().unwrap();
+        self.upcoming(&pending)
+            .map(|(_, fires_at, rule)| (fires_at, rule))
+    }
+
+    /// Postpone the upcoming move by the schedule's snooze duration.
+    pub fn snooze(&self) -> Option<SystemTime> {
+        self.snooze_for(self.schedule.snooze)
+    }
+
+    /// Postpone the upcoming move by `duration`, counted from when it is due. Returns the new
+    /// fire time, or `None` if nothing is scheduled.
+    pub fn snooze_for(&self, duration: Duration) -> Option<SystemTime> {
+        let mut pending = self.pending.lock().unwrap();
+        let (original, fires_at, rule) = self.upcoming(&pending)?;
+        let until = fires_at.max(SystemTime::now()) + duration;
+        pending.snoozed = Some((original, until));
+        drop(pending);
+
+        self.changed.notify_one();
+        self.emit(ScheduleEvent::Snoozed { rule, until });
+        Some(until)
+    }
+
+    /// Skip the upcoming move and return it, or `None` if nothing is scheduled. Skipping the
+    /// same move again doesn't count twice. Fails once the schedule's daily skip limit is used
+    /// up.
+    pub fn skip(&self) -> Result<Option<Rule>, Error> {
+        let mut pending = self.pending.lock().unwrap();
+        let (original, rule) = match self.upcoming(&pending) {
+            Some((original, _, rule)) => (original, rule),
+            None => return Ok(None),
+        };
+        if pending.skipped == Some(original) {
+            return Ok(Some(rule));
+        }
+
+        let day = self.schedule.local_day(original);
+        if pending.skips.0 != day {
+            pending.skips = (day, 0);
+        }
+        if let Some(max_skips) = self.schedule.max_skips_per_day {
+            if pending.skips.1 >= max_skips {
+                return Err(Error::SkipLimitReached);
+            }
+        }
+        pending.skips.1 += 1;
+        pending.skipped = Some(original);
+        drop(pending);
+
+        self.changed.notify_one();
+        Ok(Some(rule))
+    }
+
+    /// Signal that the user arrived, `run` moves the desk if the schedule has an `Arrival`.
+    pub fn arrived(&self) {
+        if self.schedule.arrival.is_some() {
+            self.pending.lock().unwrap().arrived = true;
+            self.changed.notify_one();
+        }
+    }
+
+    /// Run the schedule until the future is dropped. Returns immediately if there is nothing
+    /// to do.
+    pub async fn run<T: DeskTransport>(&self, desk: &Idasen<T>) {
+        let auto_stand = self.schedule.auto_stand.as_ref();
+        let arrival = self.schedule.arrival.as_ref();
+        let mut sitting_since = None;
+        let mut away_since = None;
+        let mut next_check = Instant::now();
+        let mut next_presence_check = Instant::now();
+
+        self.pending.lock().unwrap().after = SystemTime::now();
+        loop {
+            let arrived = std::mem::take(&mut self.pending.lock().unwrap().arrived);
+            if let (true, Some(arrival)) = (arrived, arrival) {
+                self.arrive(desk, arrival).await;
+                continue;
+            }
+
+            let upcoming = self.upcoming(&self.pending.lock().unwrap());
+            let rule_delay = upcoming.as_ref().map(|(_, fires_at, _)| {
+                fires_at
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+            });
+            let check_delay =
+                auto_stand.map(|_| next_check.saturating_duration_since(Instant::now()));
+            let presence_delay = arrival
+                .filter(|arrival| arrival.presence.is_some())
+                .map(|_| next_presence_check.saturating_duration_since(Instant::now()));
+            // An arrival can be signalled at any time, so there is always something to wait for.
+            let idle_delay = arrival.map(|_| MAX_WAIT);
+            let delay = match [rule_delay, check_delay, presence_delay, idle_delay]
+                .iter()
+                .flatten()
+                .min()
+            {
+                Some(delay) => *delay,
+                None => return,
+            };
+            if tokio::time::timeout(delay.min(MAX_WAIT), self.changed.notified())
+                .await
+                .is_ok()
+            {
+                // Snoozed or skipped in the meantime, look at the upcoming move again.
+                continue;
+            }
+
+            if let Some(auto_stand) = auto_stand {
+                if Instant::now() >= next_check {
+                    next_check = Instant::now() + auto_stand.check_every;
+                    self.check_sitting(desk, auto_stand, &mut sitting_since)
+                        .await;
+                    continue;
+                }
+            }
+
+            if let Some(arrival) = arrival {
+                if let Some(present) = &arrival.presence {
+                    if Instant::now() >= next_presence_check {
+                        next_presence_check = Instant::now() + arrival.check_every;
+                        if arrival.came_back(present(), &mut away_since) {
+                            self.arrive(desk, arrival).await;
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            if let Some((original, fires_at, rule)) = upcoming {
+                if SystemTime::now() >= fires_at {
+                    self.fire(desk, original, rule).await;
+                }
+            }
+        }
+    }
+
+    async fn fire<T: DeskTransport>(&self, desk: &Idasen<T>, original: SystemTime, rule: Rule) {
+        let skipped = {
+            let mut pending = self.pending.lock().unwrap();
+            let skipped = pending.skipped == Some(original);
+            pending.after = original;
+            pending.snoozed = None;
+            pending.skipped = None;
+            skipped
+        };
+
+        if skipped {
+            self.emit(ScheduleEvent::Skipped { rule });
+        } else {
+            self.emit(ScheduleEvent::MoveStarted { rule: rule.clone() });
+            match desk.move_to(rule.position).await {
+                Ok(()) => self.emit(ScheduleEvent::MoveCompleted { rule }),
+                Err(error) => self.emit(ScheduleEvent::MoveFailed { rule, error }),
+            }
+        }
+
+        // A move running past the next rule skips it rather than firing late.
+        let mut pending = self.pending.lock().unwrap();
+        pending.after = pending.after.max(SystemTime::now());
+    }
+
+    async fn check_sitting<T: DeskTransport>(
+        &self,
+        desk: &Idasen<T>,
+        auto_stand: &AutoStand,
+        sitting_since: &mut Option<Instant>,
+    ) {
+        let position = match desk.position().await {
+            Ok(position) => position,
+            // Keep the timer as it is until the desk can be read again.
+            Err(_) => return,
+        };
+        if position >= auto_stand.sitting_below || !auto_stand.user_present() {
+            *sitting_since = None;
+            return;
+        }
+
+        let sitting_for = sitting_since.get_or_insert_with(Instant::now).elapsed();
+        let now = SystemTime::now();
+        let today = Date::from_days_since_epoch(self.schedule.local_day(now));
+        let time_of_day = self.schedule.local_time_of_day(now);
+        if sitting_for < auto_stand.after
+            || !self.schedule.workdays.is_active(today, time_of_day)
+            || auto_stand.is_quiet(time_of_day)
+        {
+            return;
+        }
+
+        self.emit(ScheduleEvent::AutoStandStarted { sitting_for });
+        match desk.move_to(auto_stand.standing).await {
+            Ok(()) => {
+                *sitting_since = None;
+                self.emit(ScheduleEvent::AutoStandCompleted);
+            }
+            Err(error) => {
+                // Retrying on every check would hammer a desk that refuses to move.
+                *sitting_since = Some(Instant::now());
+                self.emit(ScheduleEvent::AutoStandFailed { error });
+            }
+        }
+    }
+
+    async fn arrive<T: DeskTransport>(&self, desk: &Idasen<T>, arrival: &Arrival) {
+        let now = SystemTime::now();
+        let today = Date::from_days_since_epoch(self.schedule.local_day(now));
+        if !self
+            .schedule
+            .workdays
+            .is_active(today, self.schedule.local_time_of_day(now))
+        {
+            return;
+        }
+
+        self.emit(ScheduleEvent::ArrivalStarted);
+        match desk.move_to(arrival.position).await {
+            Ok(()) => self.emit(ScheduleEvent::ArrivalCompleted),
+            Err(error) => self.emit(ScheduleEvent::ArrivalFailed { error }),
+        }
+    }
+
+    // Original fire time, effective fire time and the rule of the upcoming move.
+    fn upcoming(&self, pending: &Pending) -> Option<(SystemTime, SystemTime, Rule)> {
+        let (original, rule) = self.schedule.next_after(pending.after)?;
+        let fires_at = match pending.snoozed {
+            Some((snoozed, until)) if snoozed == original => until,
+            _ => original,
+        };
+        Some((original, fires_at, rule.clone()))
+    }
+
+    fn emit(&self, event: ScheduleEvent) {
+        // Nobody listening is not an error, the schedule keeps running.
+        let _ = self.events.send(event);
+    }
+}
+
+/// Phase of a sit/stand `Cycle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Phase {
+    Sitting,
+    Standing,
+}
+
+impl Phase {
+    /// The phase that follows this one.
+    pub fn next(self) -> Self {
+        match self {
+            Phase::Sitting => Phase::Standing,
+            Phase::Standing => Phase::Sitting,
+        }
+    }
+}
+
+/// Alternate between sitting and standing for fixed periods, e.g. 45 minutes sitting and 15
+/// minutes standing. Run it with a `CycleExecutor`.
+///
+/// Each period is counted from when the desk reached its position.
+///
+/// ```
+/// use idasen::schedule::{Cycle, Phase};
+/// use std::time::Duration;
+///
+/// let cycle = Cycle::new(7200, Duration::from_secs(45 * 60), 11000, Duration::from_secs(15 * 60))
+///     .before_transition(|phase| println!("Time for {:?}", phase));
+/// assert_eq!(cycle.position(Phase::Standing), 11000);
+/// ```
+#[derive(Clone)]
+pub struct Cycle {
+    sitting: u16,
+    sitting_for: Duration,
+    standing: u16,
+    standing_for: Duration,
+    before_transition: Option<Arc<dyn Fn(Phase) + Send + Sync>>,
+}
+
+impl Cycle {
+    /// Sit at `sitting` for `sitting_for`, then stand at `standing` for `standing_for`, and
+    /// so on.
+    pub fn new(sitting: u16, sitting_for: Duration, standing: u16, standing_for: Duration) -> Self {
+        Self {
+            sitting,
+            sitting_for,
+            standing,
+            standing_for,
+            before_transition: None,
+        }
+    }
+
+    /// Call `hook` with the upcoming phase right before the desk starts moving to it, e.g. to
+    /// show a notification.
+    pub fn before_transition(mut self, hook: impl Fn(Phase) + Send + Sync + 'static) -> Self {
+        self.before_transition = Some(Arc::new(hook));
+        self
+    }
+
+    pub fn position(&self, phase: Phase) -> u16 {
+        match phase {
+            Phase::Sitting => self.sitting,
+            Phase::Standing => self.standing,
+        }
+    }
+
+    pub fn duration(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Sitting => self.sitting_for,
+            Phase::Standing => self.standing_for,
+        }
+    }
+}
+
+impl fmt::Debug for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cycle")
+            .field("sitting", &self.sitting)
+            .field("sitting_for", &self.sitting_for)
+            .field("standing", &self.standing)
+            .field("standing_for", &self.standing_for)
+            .finish()
+    }
+}
+
+/// Emitted by the `CycleExecutor` while it drives the desk.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CycleEvent {
+    /// The phase is over and the desk started moving to the next one.
+    TransitionStarted {
+        phase: Phase,
+    },
+
+    /// The desk reached the phase's position.
+    TransitionCompleted {
+        phase: Phase,
+    },
+
+    /// Moving the desk failed, the phase is counted anyway.
+    TransitionFailed {
+        phase: Phase,
+        error: Error,
+    },
+
+    Paused,
+
+    Resumed,
+}
+
+#[derive(Debug)]
+enum Timer {
+    Ends(Instant),
+    // Time left in the phase when it was paused.
+    Paused(Duration),
+}
+
+/// Drives a desk through a `Cycle`.
+///
+/// `pause` and `resume` can be called from another task while `run` is in progress, e.g. by
+/// sharing the executor through an `Arc`. Pausing freezes the time left in the current phase.
+pub struct CycleExecutor {
+    cycle: Cycle,
+    events: mpsc::UnboundedSender<CycleEvent>,
+    state: Mutex<(Phase, Timer)>,
+    changed: Notify,
+}
+
+impl CycleExecutor {
+    /// Create the executor together with the receiving end of its event channel.
+    pub fn new(cycle: Cycle) -> (Self, mpsc::UnboundedReceiver<CycleEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        let executor = Self {
+            cycle,
+            events,
+            state: Mutex::new((Phase::Sitting, Timer::Ends(Instant::now()))),
+            changed: Notify::new(),
+        };
+        (executor, receiver)
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.state.lock().unwrap().0
+    }
+
+    /// Time left in the current phase.
+    pub fn time_left(&self) -> Duration {
+        match self.state.lock().unwrap().1 {
+            Timer::Ends(at) => at.saturating_duration_since(Instant::now()),
+            Timer::Paused(left) => left,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self.state.lock().unwrap().1, Timer::Paused(_))
+    }
+
+    /// Stop counting down the current phase. Does nothing if already paused.
+    pub fn pause(&self) {
+        {
+            let mut state = self.state.lock().unwrap();
+            match state.1 {
+                Timer::Ends(at) => {
+                    state.1 = Timer::Paused(at.saturating_duration_since(Instant::now()))
+                }
+                Timer::Paused(_) => return,
+            }
+        }
+        self.changed.notify_one();
+        self.emit(CycleEvent::Paused);
+    }
+
+    /// Carry on counting down the current phase. Does nothing if not paused.
+    pub fn resume(&self) {
+        {
+            let mut state = self.state.lock().unwrap();
+            match state.1 {
+                Timer::Paused(left) => state.1 = Timer::Ends(Instant::now() + left),
+                Timer::Ends(_) => return,
+            }
+        }
+        self.changed.notify_one();
+        self.emit(CycleEvent::Resumed);
+    }
+
+    /// Run the cycle until the future is dropped, starting by moving the desk to the sitting
+    /// position.
+    pub async fn run<T: DeskTransport>(&self, desk: &Idasen<T>) {
+        self.transition(desk, Phase::Sitting).await;
+        loop {
+            let ends = match self.state.lock().unwrap().1 {
+                Timer::Ends(at) => Some(at),
+                Timer::Paused(_) => None,
+            };
+            match ends {
+                Some(at) => {
+                    let wait = at.saturating_duration_since(Instant::now());
+                    if tokio::time::timeout(wait, self.changed.notified())
+                        .await
+                        .is_ok()
+                    {
+                        // Paused in the meantime.
+                        continue;
+                    }
+                }
+                None => {
+                    self.changed.notified().await;
+                    continue;
+                }
+            }
+            let next = self.phase().next();
+            self.transition(desk, next).await;
+        }
+    }
+
+    async fn transition<T: DeskTransport>(&self, desk: &Idasen<T>, phase: Phase) {
+        if let Some(hook) = &self.cycle.before_transition {
+            hook(phase);
+        }
+        self.emit(CycleEvent::TransitionStarted { phase });
+        match desk.move_to(self.cycle.position(phase)).await {
+            Ok(()) => self.emit(CycleEvent::TransitionCompleted { phase }),
+            Err(error) => self.emit(CycleEvent::TransitionFailed { phase, error }),
+        }
+
+        let duration = self.cycle.duration(phase);
+        let mut state = self.state.lock().unwrap();
+        state.0 = phase;
+        state.1 = match state.1 {
+            Timer::Paused(_) => Timer::Paused(duration),
+            Timer::Ends(_) => Timer::Ends(Instant::now() + duration),
+        };
+    }
+
+    fn emit(&self, event: CycleEvent) {
+        // Nobody listening is not an error, the cycle keeps running.
+        let _ = self.events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = SECONDS_PER_DAY as u64;
+    const HOUR: u64 = 3600;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    // Offset switching from `before` to `after` seconds east of UTC at the given instant.
+    fn transition(at: u64, before: i32, after: i32) -> impl TimeZone {
+        move |time: SystemTime| {
+            if seconds_since_epoch(time).unwrap() < at as i64 {
+                before
+            } else {
+                after
+            }
+        }
+    }
+
+    #[test]
+    fn rule_right_after_spring_forward_uses_new_offset() {
+        // Clocks jump from 02:00 +01:00 to 03:00 +02:00, at 01:00 UTC on day 10.
+        let schedule = Schedule::new()
+            .at(TimeOfDay::new(3, 30).unwrap(), 11000)
+            .timezone(transition(10 * DAY + HOUR, 3600, 7200));
+
+        let (fires_at, _) = schedule.next_after(at(10 * DAY - 3 * HOUR)).unwrap();
+        assert_eq!(fires_at, at(10 * DAY + HOUR + HOUR / 2));
+    }
+
+    #[test]
+    fn rule_right_after_fall_back_uses_new_offset() {
+        // Clocks go back from 03:00 +02:00 to 02:00 +01:00, at 01:00 UTC on day 10.
+        let schedule = Schedule::new()
+            .at(TimeOfDay::new(0, 30).unwrap(), 7200)
+            .at(TimeOfDay::new(3, 30).unwrap(), 11000)
+            .timezone(transition(10 * DAY + HOUR, 7200, 3600));
+
+        // 00:30 local is still on the old offset, 22:30 UTC the day before.
+        let (fires_at, rule) = schedule.next_after(at(10 * DAY - 6 * HOUR)).unwrap();
+        assert_eq!(rule.position, 7200);
+        assert_eq!(fires_at, at(10 * DAY - HOUR - HOUR / 2));
+
+        // 03:30 local is after the transition, 02:30 UTC.
+        let (fires_at, rule) = schedule.next_after(fires_at).unwrap();
+        assert_eq!(rule.position, 11000);
+        assert_eq!(fires_at, at(10 * DAY + 2 * HOUR + HOUR / 2));
+    }
+
+    // Executor considering everything up to `time` done, as if `run` had got that far.
+    fn executor_at(schedule: Schedule, time: SystemTime) -> Executor {
+        let (executor, _) = Executor::new(schedule);
+        executor.pending.lock().unwrap().after = time;
+        executor
+    }
+
+    fn done_until(executor: &Executor, time: SystemTime) {
+        let mut pending = executor.pending.lock().unwrap();
+        pending.after = time;
+        pending.snoozed = None;
+        pending.skipped = None;
+    }
+
+    // Runs at 9:00 and 12:00 UTC, from 1970-01-01 00:00.
+    fn two_rules() -> Schedule {
+        Schedule::new()
+            .at(TimeOfDay::new(9, 0).unwrap(), 11000)
+            .at(TimeOfDay::new(12, 0).unwrap(), 7200)
+    }
+
+    #[test]
+    fn time_of_day_and_date_round_trip_through_text() {
+        let time: TimeOfDay = "09:05".parse().unwrap();
+        assert_eq!(time, TimeOfDay::new(9, 5).unwrap());
+        assert_eq!(time.to_string(), "09:05");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(date, Date::new(2024, 2, 29).unwrap());
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert!("2023-02-29".parse::<Date>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializing_validates_time_of_day() {
+        use serde::de::{value, Deserialize, IntoDeserializer};
+
+        let parse = |s: &str| TimeOfDay::deserialize(s.into_deserializer());
+        let valid: Result<_, value::Error> = parse("17:30");
+        assert_eq!(valid.unwrap(), TimeOfDay::new(17, 30).unwrap());
+        let invalid: Result<_, value::Error> = parse("17:60");
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn next_move_is_the_first_upcoming_rule() {
+        let executor = executor_at(two_rules(), at(0));
+        let (fires_at, rule) = executor.next_move().unwrap();
+        assert_eq!(fires_at, at(9 * HOUR));
+        assert_eq!(rule.position, 11000);
+
+        let (executor, _) = Executor::new(Schedule::new());
+        assert_eq!(executor.next_move(), None);
+    }
+
+    #[test]
+    fn snooze_postpones_upcoming_move_from_when_it_is_due() {
+        let now = SystemTime::now();
+        let executor = executor_at(
+            Schedule::new().at(TimeOfDay::new(9, 0).unwrap(), 11000),
+            now,
+        );
+        let (due, _) = executor.next_move().unwrap();
+
+        let until = executor.snooze_for(Duration::from_secs(600)).unwrap();
+        assert_eq!(until, due + Duration::from_secs(600));
+        assert_eq!(executor.next_move().unwrap().0, until);
+
+        // Snoozing again postpones further.
+        let until = executor.snooze_for(Duration::from_secs(600)).unwrap();
+        assert_eq!(until, due + Duration::from_secs(1200));
+
+        let (executor, _) = Executor::new(Schedule::new());
+        assert_eq!(executor.snooze(), None);
+    }
+
+    #[test]
+    fn skip_returns_skipped_rule_and_counts_once() {
+        let executor = executor_at(two_rules().max_skips_per_day(1), at(0));
+        assert_eq!(executor.skip().unwrap().unwrap().position, 11000);
+        // Same move, doesn't use up another skip.
+        assert_eq!(executor.skip().unwrap().unwrap().position, 11000);
+
+        let (executor, _) = Executor::new(Schedule::new());
+        assert_eq!(executor.skip().unwrap(), None);
+    }
+
+    #[test]
+    fn skip_limit_resets_on_the_next_day() {
+        let executor = executor_at(two_rules().max_skips_per_day(1), at(0));
+        executor.skip().unwrap();
+
+        // The 9:00 move went by, 12:00 the same day is over the limit.
+        done_until(&executor, at(9 * HOUR));
+        assert!(matches!(executor.skip(), Err(Error::SkipLimitReached)));
+
+        // Next morning the limit starts over.
+        done_until(&executor, at(12 * HOUR));
+        assert_eq!(executor.next_move().unwrap().0, at(DAY + 9 * HOUR));
+        assert_eq!(executor.skip().unwrap().unwrap().position, 11000);
+    }
+
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn fire_moves_desk_and_reports_events() {
+        use crate::mock::MockDesk;
+
+        run(async {
+            let mock = MockDesk::new(7000);
+            let desk = Idasen::new(mock.clone()).await.unwrap();
+            let (executor, mut events) = Executor::new(Schedule::new());
+            let rule = Rule {
+                at: TimeOfDay::new(9, 0).unwrap(),
+                position: 7400,
+            };
+
+            let fire = executor.fire(&desk, SystemTime::now(), rule.clone());
+            tokio::time::timeout(Duration::from_secs(20), fire)
+                .await
+                .unwrap();
+
+            match events.recv().await {
+                Some(ScheduleEvent::MoveStarted { rule: started }) => assert_eq!(started, rule),
+                other => panic!("unexpected event {:?}", other),
+            }
+            match events.recv().await {
+                Some(ScheduleEvent::MoveCompleted { rule: completed }) => {
+                    assert_eq!(completed, rule)
+                }
+                other => panic!("unexpected event {:?}", other),
+            }
+            assert!((mock.position() as i32 - 7400).abs() <= 10);
+        });
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn failed_auto_stand_waits_another_sitting_period() {
+        use crate::mock::MockDesk;
+
+        run(async {
+            let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+            let schedule = Schedule::new().workdays(Workdays::every_day());
+            let (executor, mut events) = Executor::new(schedule);
+            // Out of range, so the move fails right away.
+            let auto_stand = AutoStand::new(8000, Duration::from_secs(HOUR), 20000);
+            let mut sitting_since = Some(Instant::now() - Duration::from_secs(2 * HOUR));
+
+            executor
+                .check_sitting(&desk, &auto_stand, &mut sitting_since)
+                .await;
+            assert!(matches!(
+                events.recv().await,
+                Some(ScheduleEvent::AutoStandStarted { .. })
+            ));
+            assert!(matches!(
+                events.recv().await,
+                Some(ScheduleEvent::AutoStandFailed { .. })
+            ));
+
+            executor
+                .check_sitting(&desk, &auto_stand, &mut sitting_since)
+                .await;
+            assert!(events.try_recv().is_err());
+        });
+    }
+
+    #[test]
+    fn arrival_needs_a_long_enough_absence() {
+        run(async {
+            let arrival = Arrival::new(11000).away_for(Duration::from_secs(60));
+            let mut away_since = None;
+
+            // Present from the start is not an arrival.
+            assert!(!arrival.came_back(true, &mut away_since));
+
+            assert!(!arrival.came_back(false, &mut away_since));
+            tokio::time::advance(Duration::from_secs(30)).await;
+            assert!(!arrival.came_back(true, &mut away_since));
+
+            assert!(!arrival.came_back(false, &mut away_since));
+            tokio::time::advance(Duration::from_secs(60)).await;
+            assert!(!arrival.came_back(false, &mut away_since));
+            assert!(arrival.came_back(true, &mut away_since));
+            assert!(!arrival.came_back(true, &mut away_since));
+        });
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn signalled_arrival_moves_desk() {
+        use crate::mock::MockDesk;
+
+        run(async {
+            let mock = MockDesk::new(7000);
+            let desk = Idasen::new(mock.clone()).await.unwrap();
+            let schedule = Schedule::new().on_arrival(Arrival::new(7400));
+            let (executor, mut events) = Executor::new(schedule);
+
+            executor.arrived();
+            let _ = tokio::time::timeout(Duration::from_secs(20), executor.run(&desk)).await;
+
+            assert!(matches!(
+                events.recv().await,
+                Some(ScheduleEvent::ArrivalStarted)
+            ));
+            assert!(matches!(
+                events.recv().await,
+                Some(ScheduleEvent::ArrivalCompleted)
+            ));
+            assert!(events.try_recv().is_err());
+            assert!((mock.position() as i32 - 7400).abs() <= 10);
+        });
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn cycle_alternates_and_pausing_freezes_the_phase() {
+        use crate::mock::MockDesk;
+
+        run(async {
+            let mock = MockDesk::new(7000);
+            let desk = Idasen::new(mock.clone()).await.unwrap();
+            let transitions = Arc::new(Mutex::new(Vec::new()));
+            let seen = transitions.clone();
+            let cycle = Cycle::new(7400, Duration::from_secs(60), 8000, Duration::from_secs(30))
+                .before_transition(move |phase| seen.lock().unwrap().push(phase));
+            let (executor, _) = CycleExecutor::new(cycle);
+
+            let run = executor.run(&desk);
+            tokio::pin!(run);
+            let _ = tokio::time::timeout(Duration::from_secs(30), &mut run).await;
+            assert_eq!(executor.phase(), Phase::Sitting);
+            assert!((mock.position() as i32 - 7400).abs() <= 10);
+
+            executor.pause();
+            let left = executor.time_left();
+            let _ = tokio::time::timeout(Duration::from_secs(300), &mut run).await;
+            assert_eq!(executor.phase(), Phase::Sitting);
+            assert_eq!(executor.time_left(), left);
+
+            executor.resume();
+            let _ = tokio::time::timeout(Duration::from_secs(60), &mut run).await;
+            assert_eq!(executor.phase(), Phase::Standing);
+            assert!((mock.position() as i32 - 8000).abs() <= 10);
+            assert_eq!(
+                *transitions.lock().unwrap(),
+                vec![Phase::Sitting, Phase::Standing]
+            );
+        });
+    }
+}
@@ -0,0 +1,221 @@
+//! Plain HTTP API over a desk, enabled by the `server` feature, so stream decks, launcher
+//! scripts and phone shortcuts can control the desk without linking to the library.
+//!
+//! The server only listens on localhost and only answers requests naming localhost as their
+//! `Host` and coming without an `Origin`, so web pages can't reach it, not even by DNS
+//! rebinding. Requests and responses are plain text:
+//!
+//! - `GET /position` returns the position in tenth of millimetres.
+//! - `POST /move_to` with the target position as the body moves the desk there.
+//! - `POST /preset/{name}` moves the desk to the config's preset called `name`.
+//! - `POST /stop` stops the desk.
+//!
+//! Moves are answered with the position once the desk stopped. Commands go through
+//! `Idasen::execute`, so positions outside the config's limits are answered with
+//! `400 Bad Request`, unknown presets with `404 Not Found`, moves refused by a `RateLimit` with
+//! `429 Too Many Requests` and failures to reach the desk with `503 Service Unavailable`, the
+//! body being the error message. Requests taking longer than 10 seconds to arrive are answered
+//! with `408 Request Timeout` and requests longer than 8 KiB with `413 Payload Too Large`.
+//!
+//! ```no_run
+//! # tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(async {
+//! use idasen::{get_instance, server::Server, Config};
+//! use std::sync::Arc;
+//!
+//! let desk = Arc::new(get_instance().await?);
+//! let config = Config::load("idasen.toml")?.unwrap_or_default();
+//! // e.g. `curl -d 7200 localhost:8080/move_to`
+//! Server::bind(8080).await?.run(desk, config).await?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! # });
+//! ```
+
+use crate::{Config, DeskCommand, DeskTransport, Error, Idasen};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+
+// Longer requests are refused, the API only ever needs a path and a number.
+const MAX_REQUEST: u64 = 8 * 1024;
+// Keeps clients that never finish their request from holding on to a task.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Listening socket of the HTTP API, see the module documentation.
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+}
+
+impl Server {
+    /// Listen on `port` of localhost, 0 picks a free port, see `local_addr`.
+    pub async fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await?;
+        Ok(Self { listener })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answer requests with the presets and limits from `config` until accepting a connection
+    /// fails. Each connection is answered on a task of its own, so `POST /stop` doesn't wait
+    /// for the move in progress.
+    pub async fn run<T: DeskTransport>(
+        self,
+        desk: Arc<Idasen<T>>,
+        config: Config,
+    ) -> io::Result<()> {
+        let config = Arc::new(config);
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let desk = desk.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                // There is nobody to tell if the client went away.
+                let _ = answer(stream, &desk, &config).await;
+            });
+        }
+    }
+}
+
+async fn answer<T: DeskTransport>(
+    mut stream: TcpStream,
+    desk: &Idasen<T>,
+    config: &Config,
+) -> io::Result<()> {
+    let (read, mut write) = stream.split();
+    let mut read = BufReader::new(read.take(MAX_REQUEST));
+    let request = match time::timeout(READ_TIMEOUT, read_request(&mut read)).await {
+        Ok(request) => request?,
+        Err(_) => Err(408),
+    };
+    let (status, body) = match request {
+        Ok(request) => respond(request, desk, config).await,
+        Err(status) => (status, reason(status).to_lowercase()),
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+        status,
+        reason(status),
+        body.len() + 1,
+        body
+    );
+    write.write_all(response.as_bytes()).await?;
+    write.shutdown().await
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+// The status to refuse the request with if it isn't HTTP the server understands, is too long or
+// doesn't come from localhost.
+async fn read_request(
+    read: &mut (impl AsyncBufReadExt + Unpin),
+) -> io::Result<Result<Request, u16>> {
+    let mut line = String::new();
+    let mut consumed = read.read_line(&mut line).await? as u64;
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Ok(Err(400)),
+    };
+
+    let mut length = 0;
+    let mut local = false;
+    let mut origin = false;
+    loop {
+        let mut header = String::new();
+        let read_bytes = read.read_line(&mut header).await? as u64;
+        consumed += read_bytes;
+        if consumed >= MAX_REQUEST {
+            return Ok(Err(413));
+        }
+        if read_bytes == 0 {
+            return Ok(Err(400));
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                match value.parse() {
+                    Ok(value) => length = value,
+                    Err(_) => return Ok(Err(400)),
+                }
+            } else if name.eq_ignore_ascii_case("host") {
+                local = is_localhost(value);
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = true;
+            }
+        }
+    }
+    if origin || !local {
+        return Ok(Err(403));
+    }
+    if consumed + length as u64 > MAX_REQUEST {
+        return Ok(Err(413));
+    }
+    let mut body = vec![0; length];
+    read.read_exact(&mut body).await?;
+    Ok(String::from_utf8(body)
+        .map(|body| Request { method, path, body })
+        .map_err(|_| 400))
+}
+
+// Browsers send the host name of the page's URL, which differs for any page not served from
+// localhost, even if its name resolves to 127.0.0.1.
+fn is_localhost(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+    name.eq_ignore_ascii_case("localhost") || name == "127.0.0.1"
+}
+
+async fn respond<T: DeskTransport>(
+    request: Request,
+    desk: &Idasen<T>,
+    config: &Config,
+) -> (u16, String) {
+    let command = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/position") => DeskCommand::Position,
+        ("POST", "/move_to") => match request.body.trim().parse() {
+            Ok(position) => DeskCommand::MoveTo(position),
+            Err(_) => return (400, format!("invalid position: {}", request.body.trim())),
+        },
+        ("POST", "/stop") => DeskCommand::Stop,
+        ("POST", path) if path.starts_with("/preset/") => {
+            DeskCommand::Preset(path["/preset/".len()..].to_string())
+        }
+        (method, path) => return (404, format!("no such endpoint: {} {}", method, path)),
+    };
+    match desk.execute(command, config).await {
+        Ok(position) => (200, position.to_string()),
+        Err(error @ Error::PositionNotInRange { .. }) => (400, error.to_string()),
+        Err(error @ Error::UnknownPreset(_)) => (404, error.to_string()),
+        Err(error @ Error::CoolingDown) => (429, error.to_string()),
+        Err(error) => (503, error.to_string()),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        _ => "Service Unavailable",
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{CharPropFlags, Characteristic};
+use std::fmt;
+use uuid::Uuid;
+
+/// Services and characteristics the desk offered when connecting, see
+/// `Idasen::discovered_services`.
+///
+/// Prints one service per line followed by its characteristics and their properties, to
+/// report what an unfamiliar desk variant offers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveredServices {
+    pub services: Vec<DiscoveredService>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredService {
+    pub uuid: Uuid,
+    pub characteristics: Vec<DiscoveredCharacteristic>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveredCharacteristic {
+    pub uuid: Uuid,
+    pub properties: CharPropFlags,
+}
+
+impl DiscoveredServices {
+    /// Group characteristics by service, keeping the order they were discovered in.
+    pub fn new(characteristics: impl IntoIterator<Item = Characteristic>) -> Self {
+        let mut services: Vec<DiscoveredService> = Vec::new();
+        for characteristic in characteristics {
+            let discovered = DiscoveredCharacteristic {
+                uuid: characteristic.uuid,
+                properties: characteristic.properties,
+            };
+            match services
+                .iter_mut()
+                .find(|service| service.uuid == characteristic.service_uuid)
+            {
+                Some(service) => service.characteristics.push(discovered),
+                None => services.push(DiscoveredService {
+                    uuid: characteristic.service_uuid,
+                    characteristics: vec![discovered],
+                }),
+            }
+        }
+        Self { services }
+    }
+
+    pub fn characteristic(&self, uuid: Uuid) -> Option<&DiscoveredCharacteristic> {
+        self.services
+            .iter()
+            .flat_map(|service| service.characteristics.iter())
+            .find(|characteristic| characteristic.uuid == uuid)
+    }
+}
+
+impl fmt::Display for DiscoveredServices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for service in &self.services {
+            writeln!(f, "service {}", service.uuid)?;
+            for characteristic in &service.characteristics {
+                writeln!(
+                    f,
+                    "  {} {:?}",
+                    characteristic.uuid, characteristic.properties
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::monitor::STANDING_ABOVE;
+use crate::schedule::{Date, TimeZone, Utc};
+use crate::Posture;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Time spent sitting and standing on one local day, see `UsageStats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DailyUsage {
+    pub date: Date,
+    pub sitting: Duration,
+    pub standing: Duration,
+}
+
+/// Daily sitting and standing durations, accumulated from the positions it is fed, e.g. from a
+/// `Monitor` after every update.
+///
+/// The time between two positions counts towards the posture of the first one, split at
+/// local midnight in the configured time zone. Feed the position from time to time while the
+/// desk rests too, so the report includes the ongoing stretch.
+///
+/// ```
+/// use idasen::schedule::Date;
+/// use idasen::UsageStats;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let mut stats = UsageStats::new().with_standing_above(9000);
+/// let nine = UNIX_EPOCH + Duration::from_secs(9 * 3600);
+/// stats.record(7000, nine);
+/// stats.record(11000, nine + Duration::from_secs(45 * 60));
+/// stats.record(7000, nine + Duration::from_secs(60 * 60));
+///
+/// let day = stats.day(Date::new(1970, 1, 1).unwrap());
+/// assert_eq!(day.sitting, Duration::from_secs(45 * 60));
+/// assert_eq!(day.standing, Duration::from_secs(15 * 60));
+/// ```
+#[derive(Clone)]
+pub struct UsageStats {
+    standing_above: u16,
+    timezone: Arc<dyn TimeZone>,
+    // Sorted by date.
+    days: Vec<DailyUsage>,
+    // Posture of the last recorded position and when it was recorded.
+    current: Option<(Posture, SystemTime)>,
+}
+
+impl UsageStats {
+    /// Start with no recorded time, counting days in UTC.
+    pub fn new() -> Self {
+        Self {
+            standing_above: STANDING_ABOVE,
+            timezone: Arc::new(Utc),
+            days: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Count heights above `height` as standing, like `Monitor::with_standing_above`.
+    pub fn with_standing_above(mut self, height: u16) -> Self {
+        self.standing_above = height;
+        self
+    }
+
+    /// Split days at midnight in `timezone`.
+    pub fn timezone(mut self, timezone: impl TimeZone + 'static) -> Self {
+        self.timezone = Arc::new(timezone);
+        self
+    }
+
+    /// Record the desk at `position` at `at`. A time before the previous one starts over from
+    /// `at` without counting anything.
+    pub fn record(&mut self, position: u16, at: SystemTime) {
+        if let Some((posture, since)) = self.current {
+            self.add(posture, since, at);
+        }
+        let posture = if position > self.standing_above {
+            Posture::Standing
+        } else {
+            Posture::Sitting
+        };
+        self.current = Some((posture, at));
+    }
+
+    /// Posture of the last recorded position.
+    pub fn posture(&self) -> Option<Posture> {
+        self.current.map(|(posture, _)| posture)
+    }
+
+    /// Usage on `date`, zero if nothing was recorded on it.
+    pub fn day(&self, date: Date) -> DailyUsage {
+        match self.days.binary_search_by_key(&date, |usage| usage.date) {
+            Ok(index) => self.days[index],
+            Err(_) => DailyUsage {
+                date,
+                sitting: Duration::default(),
+                standing: Duration::default(),
+            },
+        }
+    }
+
+    /// Usage on every day with recorded time, oldest first.
+    pub fn days(&self) -> &[DailyUsage] {
+        &self.days
+    }
+
+    fn add(&mut self, posture: Posture, mut from: SystemTime, to: SystemTime) {
+        while from < to {
+            let since_epoch = match from.duration_since(UNIX_EPOCH) {
+                Ok(since_epoch) => since_epoch,
+                Err(_) => return,
+            };
+            let offset = self.timezone.utc_offset(from) as i64;
+            let day = (since_epoch.as_secs() as i64 + offset).div_euclid(SECONDS_PER_DAY);
+            let midnight = (day + 1) * SECONDS_PER_DAY - offset;
+            let midnight = UNIX_EPOCH + Duration::from_secs(midnight.max(0) as u64);
+            let end = if midnight > from {
+                midnight.min(to)
+            } else {
+                to
+            };
+
+            let usage = self.usage_mut(Date::from_days_since_epoch(day));
+            let spent = end.duration_since(from).unwrap_or_default();
+            match posture {
+                Posture::Sitting => usage.sitting += spent,
+                Posture::Standing => usage.standing += spent,
+            }
+            from = end;
+        }
+    }
+
+    fn usage_mut(&mut self, date: Date) -> &mut DailyUsage {
+        let index = match self.days.binary_search_by_key(&date, |usage| usage.date) {
+            Ok(index) => index,
+            Err(index) => {
+                self.days.insert(
+                    index,
+                    DailyUsage {
+                        date,
+                        sitting: Duration::default(),
+                        standing: Duration::default(),
+                    },
+                );
+                index
+            }
+        };
+        &mut self.days[index]
+    }
+}
+
+impl Default for UsageStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for UsageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UsageStats")
+            .field("standing_above", &self.standing_above)
+            .field("days", &self.days)
+            .field("current", &self.current)
+            .finish()
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Backend for the persisted state: the `Config` with its presets, the `Calibration`, the
+/// `Profile` and the `LastKnownPosition`.
+///
+/// Each is stored as a short text value under its own key, so a backend only has to map keys
+/// to strings. `FileStorage` keeps one file per key; implement the trait to keep them
+/// elsewhere, e.g. in NVRAM on a device with a read-only root filesystem.
+///
+/// ```
+/// use idasen::{Calibration, MemoryStorage};
+///
+/// let storage = MemoryStorage::new();
+/// assert_eq!(Calibration::load_from(&storage).unwrap(), None);
+///
+/// let calibration = Calibration::from_measurement(6200, 6450);
+/// calibration.save_to(&storage).unwrap();
+/// assert_eq!(Calibration::load_from(&storage).unwrap(), Some(calibration));
+/// ```
+pub trait Storage: Send + Sync {
+    /// Value stored under `key`, `None` if there is none.
+    fn read(&self, key: &str) -> io::Result<Option<String>>;
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()>;
+}
+
+impl<S: Storage + ?Sized> Storage for &S {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        (**self).read(key)
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        (**self).write(key, value)
+    }
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        (**self).read(key)
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        (**self).write(key, value)
+    }
+}
+
+impl<S: Storage + ?Sized> Storage for Arc<S> {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        (**self).read(key)
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        (**self).write(key, value)
+    }
+}
+
+/// Stores each key as a file of that name in a directory, created on the first write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory holding `Config::default_path`, `None` if it can't be determined.
+    pub fn in_config_dir() -> Option<Self> {
+        Some(Self::new(crate::Config::default_path()?.parent()?))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        read_file(self.dir.join(key))
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(key), value)
+    }
+}
+
+/// Keeps the values in memory only, for tests or when nothing may be written.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+// A single file standing in for every key, backing the `load` and `save` methods taking a
+// path.
+pub(crate) struct SingleFile(pub(crate) PathBuf);
+
+impl Storage for SingleFile {
+    fn read(&self, _key: &str) -> io::Result<Option<String>> {
+        read_file(&self.0)
+    }
+
+    fn write(&self, _key: &str, value: &str) -> io::Result<()> {
+        fs::write(&self.0, value)
+    }
+}
+
+// Content of the file, `None` if it does not exist.
+pub(crate) fn read_file(path: impl AsRef<Path>) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
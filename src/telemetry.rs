@@ -0,0 +1,63 @@
+use crate::{DeskTransport, Idasen};
+
+/// How far the desk travelled since it was connected, to estimate motor wear, see
+/// `Idasen::telemetry`.
+///
+/// Derived from the positions read from the desk and received by position streams, so moves
+/// made with the paddle count too. Without a stream, only the distance between the positions
+/// read is seen, and turns in between are missed.
+///
+/// ```
+/// # #[cfg(feature = "mock")]
+/// # tokio::runtime::Builder::new_current_thread().enable_time().start_paused(true).build().unwrap().block_on(async {
+/// use idasen::{mock::MockDesk, Idasen};
+///
+/// let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+/// desk.move_to(8000).await.unwrap();
+/// assert!(desk.telemetry().distance >= 990);
+/// # });
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Telemetry {
+    /// Distance travelled up and down in tenth of millimetres.
+    pub distance: u64,
+    /// Times the desk started moving the other way than before.
+    pub direction_changes: u32,
+}
+
+// Accumulates `Telemetry` from the positions seen.
+#[derive(Debug, Default)]
+pub(crate) struct Odometer {
+    telemetry: Telemetry,
+    last_position: Option<u16>,
+    going_up: Option<bool>,
+}
+
+impl Odometer {
+    pub(crate) fn record(&mut self, position: u16) {
+        if let Some(last_position) = self.last_position {
+            if position != last_position {
+                let going_up = position > last_position;
+                self.telemetry.distance +=
+                    (position.max(last_position) - position.min(last_position)) as u64;
+                if self.going_up == Some(!going_up) {
+                    self.telemetry.direction_changes += 1;
+                }
+                self.going_up = Some(going_up);
+            }
+        }
+        self.last_position = Some(position);
+    }
+}
+
+impl<T: DeskTransport> Idasen<T> {
+    pub fn telemetry(&self) -> Telemetry {
+        self.odometer().lock().unwrap().telemetry
+    }
+
+    /// Start counting from zero again, e.g. after logging the totals.
+    pub fn reset_telemetry(&self) {
+        self.odometer().lock().unwrap().telemetry = Telemetry::default();
+    }
+}
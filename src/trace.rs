@@ -0,0 +1,370 @@
+use crate::transport::{DeskTransport, NotificationStream};
+use crate::{BDAddr, Characteristic, Error, ValueNotification, WriteType};
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+/// Characteristic traffic between `Idasen` and a desk, recorded by `Recorder` and played
+/// back by `Replay`.
+///
+/// Saved as text, one event per line: the desk address first, then `characteristic`, `read`,
+/// `write` and `notify` lines with the characteristic UUID and the value in hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub address: BDAddr,
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// The characteristic was found while connecting.
+    Characteristic {
+        uuid: Uuid,
+    },
+    Read {
+        uuid: Uuid,
+        value: Vec<u8>,
+    },
+    Write {
+        uuid: Uuid,
+        value: Vec<u8>,
+    },
+    Notification {
+        uuid: Uuid,
+        value: Vec<u8>,
+    },
+}
+
+impl Trace {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Values written to a characteristic, in order.
+    pub fn writes(&self, uuid: Uuid) -> Vec<Vec<u8>> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Write {
+                    uuid: written,
+                    value,
+                } if *written == uuid => Some(value.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "address {}", self.address)?;
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Trace {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+        let address = lines
+            .next()
+            .and_then(|line| line.strip_prefix("address "))
+            .and_then(|address| address.trim().parse().ok())
+            .ok_or_else(|| invalid("missing desk address"))?;
+        let events = lines.map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Self { address, events })
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::Characteristic { uuid } => write!(f, "characteristic {}", uuid),
+            TraceEvent::Read { uuid, value } => write!(f, "read {} {}", uuid, hex(value)),
+            TraceEvent::Write { uuid, value } => write!(f, "write {} {}", uuid, hex(value)),
+            TraceEvent::Notification { uuid, value } => {
+                write!(f, "notify {} {}", uuid, hex(value))
+            }
+        }
+    }
+}
+
+impl FromStr for TraceEvent {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let kind = fields.next().unwrap_or_default();
+        let uuid = fields
+            .next()
+            .and_then(|uuid| uuid.parse().ok())
+            .ok_or_else(|| invalid("invalid characteristic UUID"))?;
+        if kind == "characteristic" {
+            return Ok(TraceEvent::Characteristic { uuid });
+        }
+
+        let value = unhex(fields.next().unwrap_or_default())
+            .ok_or_else(|| invalid("invalid characteristic value"))?;
+        match kind {
+            "read" => Ok(TraceEvent::Read { uuid, value }),
+            "write" => Ok(TraceEvent::Write { uuid, value }),
+            "notify" => Ok(TraceEvent::Notification { uuid, value }),
+            _ => Err(invalid("unknown trace event")),
+        }
+    }
+}
+
+/// `DeskTransport` writing all traffic of another transport to a trace file.
+///
+/// Each event is appended as it happens, so the trace survives a crash. Failing to write the
+/// trace doesn't interrupt controlling the desk.
+///
+/// ```no_run
+/// # async fn record() -> Result<(), Box<dyn std::error::Error>> {
+/// use idasen::trace::Recorder;
+/// use idasen::{get_desks, BleDesk, Error, Idasen};
+///
+/// let device = get_desks(None)
+///     .await?
+///     .pop()
+///     .ok_or(Error::CannotFindDevice { scan: None })?;
+/// let recorder = Recorder::create(BleDesk::new(device), "desk.trace")?;
+/// let desk = Idasen::new(recorder).await?;
+/// desk.move_to(7200).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Recorder<T: DeskTransport> {
+    inner: T,
+    file: Arc<Mutex<File>>,
+}
+
+impl<T: DeskTransport> Recorder<T> {
+    /// Record `inner`, replacing the file at `path`.
+    pub fn create(inner: T, path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "address {}", inner.address())?;
+        Ok(Self {
+            inner,
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&self, event: TraceEvent) {
+        record(&self.file, event);
+    }
+}
+
+fn record(file: &Mutex<File>, event: TraceEvent) {
+    let _ = writeln!(file.lock().unwrap(), "{}", event);
+}
+
+#[async_trait]
+impl<T: DeskTransport> DeskTransport for Recorder<T> {
+    fn address(&self) -> BDAddr {
+        self.inner.address()
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        self.inner.disconnect().await
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        let found = self.inner.has_characteristic(uuid);
+        if found {
+            self.record(TraceEvent::Characteristic { uuid });
+        }
+        found
+    }
+
+    fn characteristics(&self) -> Vec<Characteristic> {
+        self.inner.characteristics()
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        let value = self.inner.read(uuid).await?;
+        self.record(TraceEvent::Read {
+            uuid,
+            value: value.clone(),
+        });
+        Ok(value)
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8], write_type: WriteType) -> Result<(), Error> {
+        self.inner.write(uuid, data, write_type).await?;
+        self.record(TraceEvent::Write {
+            uuid,
+            value: data.to_vec(),
+        });
+        Ok(())
+    }
+
+    async fn subscribe(&self, uuid: Uuid) -> Result<(), Error> {
+        self.inner.subscribe(uuid).await
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        let file = self.file.clone();
+        let notifications = self.inner.notifications().await?;
+        Ok(Box::pin(notifications.map(move |notification| {
+            record(
+                &file,
+                TraceEvent::Notification {
+                    uuid: notification.uuid,
+                    value: notification.value.clone(),
+                },
+            );
+            notification
+        })))
+    }
+
+    async fn rssi(&self) -> Result<Option<i16>, Error> {
+        self.inner.rssi().await
+    }
+
+    async fn name(&self) -> Result<Option<String>, Error> {
+        self.inner.name().await
+    }
+
+    async fn manufacturer_data(&self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        self.inner.manufacturer_data().await
+    }
+}
+
+/// `DeskTransport` playing back a `Trace`, to turn a recording into a regression test.
+///
+/// Reads return the recorded values of the characteristic in order and fail with
+/// `Error::TraceExhausted` once they run out. Writes are accepted and kept, compare `writes`
+/// with `Trace::writes` to check the same commands were sent. Notifications are delivered
+/// all at once, in the recorded order.
+#[derive(Debug)]
+pub struct Replay {
+    trace: Trace,
+    reads: Mutex<HashMap<Uuid, VecDeque<Vec<u8>>>>,
+    writes: Mutex<Vec<(Uuid, Vec<u8>)>>,
+}
+
+impl Replay {
+    pub fn new(trace: Trace) -> Self {
+        let mut reads: HashMap<_, VecDeque<_>> = HashMap::new();
+        for event in &trace.events {
+            if let TraceEvent::Read { uuid, value } = event {
+                reads.entry(*uuid).or_default().push_back(value.clone());
+            }
+        }
+        Self {
+            trace,
+            reads: Mutex::new(reads),
+            writes: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(Trace::load(path)?))
+    }
+
+    /// Values written to a characteristic during the replay, in order.
+    pub fn writes(&self, uuid: Uuid) -> Vec<Vec<u8>> {
+        self.writes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(written, _)| *written == uuid)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DeskTransport for Replay {
+    fn address(&self) -> BDAddr {
+        self.trace.address
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        self.trace
+            .events
+            .contains(&TraceEvent::Characteristic { uuid })
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        self.reads
+            .lock()
+            .unwrap()
+            .get_mut(&uuid)
+            .and_then(VecDeque::pop_front)
+            .ok_or(Error::TraceExhausted)
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8], _write_type: WriteType) -> Result<(), Error> {
+        self.writes.lock().unwrap().push((uuid, data.to_vec()));
+        Ok(())
+    }
+
+    async fn subscribe(&self, _uuid: Uuid) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        let notifications: Vec<_> = self
+            .trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Notification { uuid, value } => Some(ValueNotification {
+                    uuid: *uuid,
+                    value: value.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        Ok(Box::pin(tokio_stream::iter(notifications)))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,261 @@
+use crate::{Device, Error, POSITION_UUID};
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, Characteristic, ValueNotification, WriteType};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Mutex;
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+pub type NotificationStream = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+
+/// The Bluetooth operations `Idasen` needs from a desk, addressed by characteristic UUID.
+///
+/// `BleDesk` implements it for btleplug peripherals; implement it on a fake to test code
+/// driving an `Idasen` without a desk nearby. A fake has to offer `CONTROL_UUID`, accepting
+/// the `UP`, `DOWN` and `STOP` payloads, and `POSITION_UUID`, answering reads and
+/// notifications in the format decoded by `bytes_to_position_speed`.
+///
+/// Transports are `'static` so that `Idasen` can stop the desk or disconnect from a task of
+/// its own once a future using them is dropped.
+#[async_trait]
+pub trait DeskTransport: Send + Sync + 'static {
+    /// Bluetooth MAC address identifying the desk.
+    fn address(&self) -> BDAddr;
+
+    /// Connect to the desk and discover its characteristics.
+    async fn connect(&self) -> Result<(), Error>;
+
+    /// Drop the connection, e.g. while the desk is idle. Reads and writes fail with
+    /// `btleplug::Error::NotConnected` until `connect` is called again. Does nothing unless
+    /// implemented.
+    async fn disconnect(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Whether the characteristic was found while connecting.
+    fn has_characteristic(&self, uuid: Uuid) -> bool;
+
+    /// All characteristics found while connecting. Reported by `Idasen::discovered_services`,
+    /// empty unless implemented.
+    fn characteristics(&self) -> Vec<Characteristic> {
+        Vec::new()
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error>;
+
+    async fn write(&self, uuid: Uuid, data: &[u8], write_type: WriteType) -> Result<(), Error>;
+
+    /// Enable notifications for the characteristic.
+    async fn subscribe(&self, uuid: Uuid) -> Result<(), Error>;
+
+    /// Stream of notifications from all subscribed characteristics.
+    async fn notifications(&self) -> Result<NotificationStream, Error>;
+
+    /// Signal strength of the desk in dBm, as last seen by the adapter. `None` unless
+    /// implemented.
+    async fn rssi(&self) -> Result<Option<i16>, Error> {
+        Ok(None)
+    }
+
+    /// Name the desk advertises, e.g. `Desk 1234`. `None` unless implemented.
+    async fn name(&self) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+
+    /// Manufacturer specific data the desk advertises, by company identifier. Empty unless
+    /// implemented.
+    async fn manufacturer_data(&self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        Ok(HashMap::new())
+    }
+}
+
+#[async_trait]
+impl<T: DeskTransport + ?Sized> DeskTransport for Box<T> {
+    fn address(&self) -> BDAddr {
+        (**self).address()
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        (**self).connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        (**self).disconnect().await
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        (**self).has_characteristic(uuid)
+    }
+
+    fn characteristics(&self) -> Vec<Characteristic> {
+        (**self).characteristics()
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        (**self).read(uuid).await
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8], write_type: WriteType) -> Result<(), Error> {
+        (**self).write(uuid, data, write_type).await
+    }
+
+    async fn subscribe(&self, uuid: Uuid) -> Result<(), Error> {
+        (**self).subscribe(uuid).await
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        (**self).notifications().await
+    }
+
+    async fn rssi(&self) -> Result<Option<i16>, Error> {
+        (**self).rssi().await
+    }
+
+    async fn name(&self) -> Result<Option<String>, Error> {
+        (**self).name().await
+    }
+
+    async fn manufacturer_data(&self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        (**self).manufacturer_data().await
+    }
+}
+
+/// `DeskTransport` over a btleplug peripheral.
+///
+/// The characteristics are looked up once in `connect`, so reads and writes don't search the
+/// peripheral's characteristics every time. Reconnecting reuses them if they still work.
+#[derive(Debug)]
+pub struct BleDesk<P: Device> {
+    peripheral: P,
+    characteristics: Mutex<Vec<Characteristic>>,
+}
+
+impl<P: Device> BleDesk<P> {
+    pub fn new(peripheral: P) -> Self {
+        Self {
+            peripheral,
+            characteristics: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn peripheral(&self) -> &P {
+        &self.peripheral
+    }
+
+    fn characteristic(&self, uuid: Uuid) -> Result<Characteristic, Error> {
+        self.characteristics
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|c| c.uuid == uuid)
+            .cloned()
+            .ok_or_else(|| Error::CharacteristicsNotFound(uuid.to_string()))
+    }
+}
+
+impl<P: Device> From<P> for BleDesk<P> {
+    fn from(peripheral: P) -> Self {
+        Self::new(peripheral)
+    }
+}
+
+#[async_trait]
+impl<P: Device + 'static> DeskTransport for BleDesk<P> {
+    fn address(&self) -> BDAddr {
+        self.peripheral.address()
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        self.peripheral.connect().await?;
+        // When reconnecting, the characteristics found before usually still work. A position
+        // read confirms that and is much quicker than discovering the services again.
+        if let Ok(position) = self.characteristic(POSITION_UUID) {
+            if self.peripheral.read(&position).await.is_ok() {
+                return Ok(());
+            }
+        }
+        self.peripheral.discover_services().await?;
+        *self.characteristics.lock().unwrap() =
+            self.peripheral.characteristics().into_iter().collect();
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), Error> {
+        Ok(self.peripheral.disconnect().await?)
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        self.characteristic(uuid).is_ok()
+    }
+
+    fn characteristics(&self) -> Vec<Characteristic> {
+        self.characteristics.lock().unwrap().clone()
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, Error> {
+        self.peripheral
+            .read(&self.characteristic(uuid)?)
+            .await
+            .map_err(gatt_error)
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8], write_type: WriteType) -> Result<(), Error> {
+        self.peripheral
+            .write(&self.characteristic(uuid)?, data, write_type)
+            .await
+            .map_err(gatt_error)
+    }
+
+    async fn subscribe(&self, uuid: Uuid) -> Result<(), Error> {
+        self.peripheral
+            .subscribe(&self.characteristic(uuid)?)
+            .await
+            .map_err(gatt_error)
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        Ok(self.peripheral.notifications().await?)
+    }
+
+    async fn rssi(&self) -> Result<Option<i16>, Error> {
+        let properties = self.peripheral.properties().await?;
+        Ok(properties.and_then(|properties| properties.rssi))
+    }
+
+    async fn name(&self) -> Result<Option<String>, Error> {
+        let properties = self.peripheral.properties().await?;
+        Ok(properties.and_then(|properties| properties.local_name))
+    }
+
+    async fn manufacturer_data(&self) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        let properties = self.peripheral.properties().await?;
+        Ok(properties
+            .map(|properties| properties.manufacturer_data)
+            .unwrap_or_default())
+    }
+}
+
+// Windows reports characteristics that need an encrypted link as access denied, BlueZ as
+// not permitted or authorized, or with the ATT insufficient authentication error.
+fn gatt_error(error: btleplug::Error) -> Error {
+    let needs_pairing = match &error {
+        btleplug::Error::PermissionDenied => true,
+        btleplug::Error::Other(error) => {
+            let message = error.to_string();
+            [
+                "NotPermitted",
+                "NotAuthorized",
+                "Insufficient Authentication",
+            ]
+            .iter()
+            .any(|reason| message.contains(reason))
+        }
+        _ => false,
+    };
+    if needs_pairing {
+        Error::PairingRequired
+    } else {
+        Error::BtlePlugError(error)
+    }
+}
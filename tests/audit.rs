@@ -0,0 +1,71 @@
+use idasen::{AuditEntry, AuditLog, AuditQuery, BDAddr, DeskCommand, Origin};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn entry(client: &str, seconds: u64, command: DeskCommand) -> AuditEntry {
+    AuditEntry {
+        at: UNIX_EPOCH + Duration::from_secs(seconds),
+        desk: BDAddr::from([1, 2, 3, 4, 5, 6]),
+        origin: Origin::new(client, "ipc"),
+        command,
+        position: Some(7200),
+        error: None,
+    }
+}
+
+#[test]
+fn commands_survive_text_round_trip() {
+    let commands = [
+        DeskCommand::MoveTo(7200),
+        DeskCommand::MoveBy(-500),
+        DeskCommand::Preset("stand up".to_string()),
+        DeskCommand::Up,
+        DeskCommand::Stop,
+        DeskCommand::Unlock,
+    ];
+    for command in commands {
+        assert_eq!(command.to_string().parse::<DeskCommand>(), Ok(command));
+    }
+    assert!("move-to high".parse::<DeskCommand>().is_err());
+    assert!("stop now".parse::<DeskCommand>().is_err());
+}
+
+#[test]
+fn file_log_is_appended_and_queried_after_reopening() {
+    let path = std::env::temp_dir().join("idasen-audit-test");
+    let _ = std::fs::remove_file(&path);
+    let mut failed = entry("bob\tsmith", 3600, DeskCommand::Preset("stand".to_string()));
+    failed.position = None;
+    failed.error = Some("No preset named 'stand'.".to_string());
+    {
+        let log = AuditLog::open(&path).unwrap();
+        log.record(entry("alice", 60, DeskCommand::MoveTo(7200)))
+            .unwrap();
+        log.record(entry("alice", 120, DeskCommand::Position))
+            .unwrap();
+    }
+    let log = AuditLog::open(&path).unwrap();
+    log.record(failed).unwrap();
+
+    let all = log.query(&AuditQuery::new()).unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[2].origin.client, "bob smith");
+    assert_eq!(all[2].error.as_deref(), Some("No preset named 'stand'."));
+
+    let moves = log
+        .query(&AuditQuery::new().client("alice").moves_only())
+        .unwrap();
+    assert_eq!(moves, vec![entry("alice", 60, DeskCommand::MoveTo(7200))]);
+    let later = AuditQuery::new().since(UNIX_EPOCH + Duration::from_secs(100));
+    assert_eq!(log.query(&later).unwrap().len(), 2);
+
+    let mut csv = Vec::new();
+    log.export_csv(&AuditQuery::new().moves_only(), &mut csv)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "time,desk,interface,client,command,position,error\n\
+         60000,01:02:03:04:05:06,ipc,alice,move-to 7200,7200,\n\
+         3600000,01:02:03:04:05:06,ipc,bob smith,preset stand,,No preset named 'stand'.\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,25 @@
+#![cfg(all(feature = "blocking", feature = "mock"))]
+
+use idasen::blocking::Idasen;
+use idasen::mock::MockDesk;
+
+#[test]
+fn blocking_calls_drive_the_desk() {
+    let mock = MockDesk::new(7000);
+    let desk = Idasen::new(mock.clone()).unwrap();
+    assert_eq!(desk.position().unwrap(), 7000);
+
+    desk.move_to(7300).unwrap();
+    assert!((mock.position() as i32 - 7300).abs() <= 10);
+    let position = desk.position().unwrap();
+    assert_eq!(desk.last_known_position().unwrap().position, position);
+}
+
+#[test]
+fn dropping_the_blocking_desk_disconnects_it() {
+    let mock = MockDesk::new(7000);
+    let desk = Idasen::new(mock.clone()).unwrap();
+    desk.position().unwrap();
+    drop(desk);
+    assert!(!mock.is_connected());
+}
@@ -0,0 +1,75 @@
+use idasen::{BDAddr, Config, LengthUnit};
+use std::time::Duration;
+
+#[test]
+fn config_round_trips_through_a_file() {
+    let path = std::env::temp_dir()
+        .join("idasen-config-test")
+        .join("config.toml");
+    let mut config = Config {
+        address: Some(BDAddr::from([0xC2, 0x6D, 0x7E, 0x11, 0x22, 0x33])),
+        name: Some("Desk \"7412\"".to_string()),
+        address_cache: Some(std::env::temp_dir().join("idasen-address")),
+        unit: LengthUnit::Inches,
+        ..Config::default()
+    };
+    config.presets.push(("sit".to_string(), 7200));
+    config.presets.push(("stand \"high\"".to_string(), 11500));
+    config.tuning.watchdog = Some(Duration::from_secs(2));
+    config.tuning.slow_approach = Some(300);
+    config.tuning.timeout = None;
+
+    config.save(&path).unwrap();
+    assert_eq!(Config::load(&path).unwrap(), Some(config.clone()));
+    assert_eq!(config.preset("stand \"high\""), Some(11500));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert_eq!(Config::load(&path).unwrap(), None);
+}
+
+#[test]
+fn config_accepts_hand_written_toml() {
+    let config: Config = "
+        # written by hand
+        unit = \"cm\"  # the default
+
+        [presets]
+        sit = 7_200
+        \"stand up\" = 11500
+    "
+    .parse()
+    .unwrap();
+    assert_eq!(config.preset("sit"), Some(7200));
+    assert_eq!(config.preset("stand up"), Some(11500));
+
+    assert!("[tuning]\ntolerence = 5".parse::<Config>().is_err());
+    assert!("[limits]\nmin_height = 70000".parse::<Config>().is_err());
+}
+
+#[test]
+fn config_escapes_control_characters() {
+    let mut config = Config {
+        name: Some("Desk\n\t\u{1}\u{7f}".to_string()),
+        ..Config::default()
+    };
+    config.presets.push(("line\r\nbreak".to_string(), 7200));
+    let written = config.to_string();
+    assert!(written.contains(r#"name = "Desk\n\t\u0001\u007F""#));
+    assert!(written.contains(r#""line\u000D\nbreak" = 7200"#));
+    assert_eq!(written.parse::<Config>().unwrap(), config);
+
+    let config: Config = "[desk]\nname = \"café \\U0001F600\"".parse().unwrap();
+    assert_eq!(config.name.as_deref(), Some("café 😀"));
+    assert!("[desk]\nname = \"\\u00E\"".parse::<Config>().is_err());
+}
+
+#[test]
+fn config_rejects_contradicting_limits() {
+    let config: Config = "[limits]\nmin_height = 13000\nmax_height = 13500"
+        .parse()
+        .unwrap();
+    assert_eq!(config.min_height, 13000);
+    let error = "[limits]\nmin_height = 9000\nmax_height = 8000"
+        .parse::<Config>()
+        .unwrap_err();
+    assert!(error.to_string().contains("line 3"));
+}
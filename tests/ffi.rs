@@ -0,0 +1,18 @@
+#![cfg(feature = "cdylib")]
+
+use idasen::ffi::{idasen_connect, idasen_last_error, idasen_move_to};
+use std::ffi::CStr;
+use std::ptr;
+
+#[test]
+fn failures_are_reported_through_last_error() {
+    unsafe {
+        assert!(idasen_connect(b"not a mac\0".as_ptr() as *const _).is_null());
+        let error = CStr::from_ptr(idasen_last_error()).to_str().unwrap();
+        assert_eq!(error, "invalid MAC address");
+
+        assert_eq!(idasen_move_to(ptr::null(), 7200), -1);
+        let error = CStr::from_ptr(idasen_last_error()).to_str().unwrap();
+        assert_eq!(error, "desk is null");
+    }
+}
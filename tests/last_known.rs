@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use idasen::{
+    BDAddr, DeskTransport, Error, Idasen, LastKnownPosition, MemoryStorage, NotificationStream,
+    WriteType, CONTROL_UUID, MIN_HEIGHT, POSITION_UUID,
+};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+// Answers position reads from a script, repeating the last entry.
+struct ScriptedDesk {
+    reads: Mutex<Vec<[u8; 4]>>,
+}
+
+impl ScriptedDesk {
+    fn new(readings: &[(u16, i16)]) -> Self {
+        let mut reads: Vec<[u8; 4]> = readings
+            .iter()
+            .map(|(position, speed)| {
+                let position = (position - MIN_HEIGHT).to_le_bytes();
+                let speed = speed.to_le_bytes();
+                [position[0], position[1], speed[0], speed[1]]
+            })
+            .collect();
+        reads.reverse();
+        Self {
+            reads: Mutex::new(reads),
+        }
+    }
+}
+
+#[async_trait]
+impl DeskTransport for ScriptedDesk {
+    fn address(&self) -> BDAddr {
+        BDAddr::default()
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        uuid == CONTROL_UUID || uuid == POSITION_UUID
+    }
+
+    async fn read(&self, _uuid: Uuid) -> Result<Vec<u8>, Error> {
+        let mut reads = self.reads.lock().unwrap();
+        let value = if reads.len() > 1 {
+            reads.pop().unwrap()
+        } else {
+            reads[0]
+        };
+        Ok(value.to_vec())
+    }
+
+    async fn write(&self, _uuid: Uuid, _data: &[u8], _write_type: WriteType) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn subscribe(&self, _uuid: Uuid) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        Ok(Box::pin(tokio_stream::empty()))
+    }
+}
+
+fn run<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn rest_position_is_saved_after_moving_sample_with_same_position() {
+    let path = std::env::temp_dir().join("idasen-test-last-known-moving");
+    LastKnownPosition::now(7000).save(&path).unwrap();
+
+    run(async {
+        let desk = ScriptedDesk::new(&[(8000, 500), (8000, 0)]);
+        let mut desk = Idasen::new(desk).await.unwrap();
+        desk.persist_last_known_position(&path).unwrap();
+
+        desk.position().await.unwrap();
+        desk.position().await.unwrap();
+    });
+
+    let saved = LastKnownPosition::load(&path).unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(saved.position, 8000);
+}
+
+#[test]
+fn saved_position_is_known_before_first_read() {
+    let path = std::env::temp_dir().join("idasen-test-last-known-seed");
+    LastKnownPosition::now(9100).save(&path).unwrap();
+
+    run(async {
+        let mut desk = Idasen::new(ScriptedDesk::new(&[(9100, 0)])).await.unwrap();
+        assert_eq!(desk.last_known_position(), None);
+        desk.persist_last_known_position(&path).unwrap();
+        assert_eq!(desk.last_known_position().unwrap().position, 9100);
+    });
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn rest_position_is_saved_to_storage() {
+    let storage = Arc::new(MemoryStorage::new());
+
+    run(async {
+        let mut desk = Idasen::new(ScriptedDesk::new(&[(8300, 0)])).await.unwrap();
+        desk.persist_last_known_position_in(storage.clone())
+            .unwrap();
+        desk.position().await.unwrap();
+    });
+
+    let saved = LastKnownPosition::load_from(&storage).unwrap().unwrap();
+    assert_eq!(saved.position, 8300);
+}
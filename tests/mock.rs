@@ -0,0 +1,1343 @@
+#![cfg(feature = "mock")]
+
+use idasen::{
+    connect_first, mock::MockDesk, protocol::Command, BDAddr, CharPropFlags, Config, DeskCommand,
+    DeskEvent, DeskGroup, DeskTransport, DeviceInfo, Diagnostics, Direction, Error, Idasen,
+    Monitor, MoveOptions, MovePolicy, MoveStatus, OverridePolicy, Posture, RateLimit, Recording,
+    Restriction, Routine, ScanSummary, WriteType, CONTROL_UUID, FIRMWARE_REVISION_UUID, MAX_HEIGHT,
+    MIN_HEIGHT, POSITION_UUID, UP,
+};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+// Time is paused, the simulation skips ahead instead of waiting.
+fn run<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn move_to_reaches_target_going_up() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(20), desk.move_to(7500))
+            .await
+            .expect("move_to did not finish")
+            .unwrap();
+        assert!((mock.position() as i32 - 7500).abs() <= 10);
+    });
+}
+
+#[test]
+fn move_to_reaches_target_going_down() {
+    run(async {
+        let mock = MockDesk::new(9000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(20), desk.move_to(8700))
+            .await
+            .expect("move_to did not finish")
+            .unwrap();
+        assert!((mock.position() as i32 - 8700).abs() <= 10);
+    });
+}
+
+#[test]
+fn move_to_rejects_out_of_range() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        assert!(desk.move_to(idasen::MAX_HEIGHT + 1).await.is_err());
+    });
+}
+
+#[test]
+fn move_takes_same_simulated_time_on_every_run() {
+    let simulated_move = || {
+        run(async {
+            let mock = MockDesk::new(7000);
+            let desk = Idasen::new(mock.clone()).await.unwrap();
+            let started = Instant::now();
+            desk.move_to(8000).await.unwrap();
+            (started.elapsed(), mock.position())
+        })
+    };
+
+    let (took, position) = simulated_move();
+    // 100 mm at roughly 38 mm/s.
+    assert!(took > Duration::from_secs(2) && took < Duration::from_secs(5));
+    assert_eq!(simulated_move(), (took, position));
+}
+
+#[test]
+fn raw_access_reaches_characteristics() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let raw = desk.read_raw(POSITION_UUID).await.unwrap();
+        assert_eq!(idasen::bytes_to_position_speed(&raw).position, 7000);
+
+        desk.write_raw(CONTROL_UUID, &UP, WriteType::WithoutResponse)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(mock.is_moving());
+
+        let unknown = uuid::Uuid::from_u128(1);
+        assert!(matches!(
+            desk.read_raw(unknown).await,
+            Err(Error::CharacteristicsNotFound(_))
+        ));
+    });
+}
+
+#[test]
+fn profile_measures_simulated_motion() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        let profile = desk.profile().await.unwrap();
+        for direction in [profile.up, profile.down].iter() {
+            // The simulation travels at 38 mm/s and brakes at 380 mm/s².
+            assert!((300..=450).contains(&direction.speed), "{:?}", direction);
+            assert!(
+                (500..=4000).contains(&direction.acceleration),
+                "{:?}",
+                direction
+            );
+            assert!(
+                (5..=80).contains(&direction.stop_distance),
+                "{:?}",
+                direction
+            );
+        }
+    });
+}
+
+#[test]
+fn profile_needs_room_above() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(idasen::MAX_HEIGHT))
+            .await
+            .unwrap();
+        assert!(matches!(
+            desk.profile().await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+    });
+}
+
+#[test]
+fn spawned_desks_move_concurrently() {
+    run(async {
+        let first = MockDesk::new(7000);
+        let second = MockDesk::new(9000);
+        let first_handle = Idasen::new(first.clone()).await.unwrap().spawn();
+        let second_handle = Idasen::new(second.clone()).await.unwrap().spawn();
+
+        let started = Instant::now();
+        let first_move = first_handle.move_to(7500);
+        let second_move = second_handle.move_to(8500);
+        first_move.await.unwrap();
+        second_move.await.unwrap();
+        // Each move takes one to two seconds, they overlap.
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!((first.position() as i32 - 7500).abs() <= 10);
+        assert!((second.position() as i32 - 8500).abs() <= 10);
+    });
+}
+
+#[test]
+fn stop_interrupts_spawned_move() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let handle = Idasen::new(mock.clone()).await.unwrap().spawn();
+        let moving = handle.move_to(9000);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        handle.stop().await.unwrap();
+        assert!(matches!(moving.await, Err(Error::MoveInterrupted)));
+        assert!(handle.position().await.unwrap() < 8000);
+    });
+}
+
+#[test]
+fn spawned_moves_queue_or_are_rejected() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let handle = Idasen::new(mock.clone())
+            .await
+            .unwrap()
+            .spawn_with(MovePolicy::Queue);
+        let first = handle.move_to(8000);
+        let second = handle.move_to(7500);
+        first.await.unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+        second.await.unwrap();
+        assert!((mock.position() as i32 - 7500).abs() <= 10);
+
+        let handle = Idasen::new(mock.clone())
+            .await
+            .unwrap()
+            .spawn_with(MovePolicy::Reject);
+        let first = handle.move_to(8000);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(matches!(handle.move_to(7000).await, Err(Error::DeskBusy)));
+        first.await.unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+        handle.move_to(7500).await.unwrap();
+    });
+}
+
+#[test]
+fn disconnected_desk_reconnects_when_used() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.disconnect().await.unwrap();
+        assert!(!desk.is_connected());
+        assert!(!mock.is_connected());
+
+        assert_eq!(desk.position().await.unwrap(), 7000);
+        assert!(desk.is_connected());
+
+        desk.disconnect().await.unwrap();
+        desk.connect().await.unwrap();
+        assert!(mock.is_connected());
+    });
+}
+
+#[test]
+fn lost_connection_is_restored() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        mock.disconnect();
+        assert_eq!(desk.position().await.unwrap(), 7000);
+
+        mock.disconnect();
+        desk.move_to(7300).await.unwrap();
+        assert!((mock.position() as i32 - 7300).abs() <= 10);
+    });
+}
+
+fn received(events: &mut broadcast::Receiver<DeskEvent>) -> Vec<DeskEvent> {
+    std::iter::from_fn(|| events.try_recv().ok()).collect()
+}
+
+#[test]
+fn events_report_the_connection() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let mut events = desk.events();
+        mock.disconnect();
+        desk.position().await.unwrap();
+        assert_eq!(
+            received(&mut events),
+            [
+                DeskEvent::Disconnected,
+                DeskEvent::Connected,
+                DeskEvent::PositionChanged {
+                    position: 7000,
+                    speed: 0
+                }
+            ]
+        );
+
+        desk.disconnect().await.unwrap();
+        desk.connect().await.unwrap();
+        // The position didn't change.
+        desk.position().await.unwrap();
+        assert_eq!(
+            received(&mut events),
+            [DeskEvent::Disconnected, DeskEvent::Connected]
+        );
+    });
+}
+
+#[test]
+fn events_report_moves() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        let mut events = desk.events();
+        desk.move_to(7500).await.unwrap();
+        let moved = received(&mut events);
+        assert_eq!(moved[0], DeskEvent::MoveStarted { target: 7500 });
+        assert_eq!(moved.last(), Some(&DeskEvent::MoveCompleted));
+        assert!(moved.len() > 2);
+        assert!(moved[1..moved.len() - 1]
+            .iter()
+            .all(|event| matches!(event, DeskEvent::PositionChanged { .. })));
+
+        let moving = desk.move_to(11000);
+        assert!(tokio::time::timeout(Duration::from_secs(1), moving)
+            .await
+            .is_err());
+        assert_eq!(received(&mut events).last(), Some(&DeskEvent::MoveAborted));
+
+        // Moves refused up front don't start.
+        assert!(desk.move_to(20000).await.is_err());
+        assert!(received(&mut events).is_empty());
+    });
+}
+
+#[test]
+fn firmware_revision_is_read_when_offered() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        assert_eq!(desk.firmware_revision().await.unwrap(), None);
+        assert!(desk.firmware_advisories().await.unwrap().is_empty());
+
+        let mock = MockDesk::new(7000).with_firmware_revision("4.0.1\0");
+        let desk = Idasen::new(mock).await.unwrap();
+        assert_eq!(
+            desk.firmware_revision().await.unwrap().as_deref(),
+            Some("4.0.1")
+        );
+    });
+}
+
+#[test]
+fn device_info_reads_what_is_offered() {
+    run(async {
+        let mock = MockDesk::new(7000)
+            .with_device_info("LINAK", "DPG1C", "1234\0")
+            .with_firmware_revision("4.0.1");
+        let desk = Idasen::new(mock).await.unwrap();
+        let info = desk.device_info().await.unwrap();
+        assert_eq!(info.manufacturer.as_deref(), Some("LINAK"));
+        assert_eq!(info.model.as_deref(), Some("DPG1C"));
+        assert_eq!(info.serial_number.as_deref(), Some("1234"));
+        assert_eq!(info.firmware_revision.as_deref(), Some("4.0.1"));
+
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        assert_eq!(desk.device_info().await.unwrap(), DeviceInfo::default());
+    });
+}
+
+#[test]
+fn name_is_reported_by_the_transport() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000).with_name("Desk 7412"))
+            .await
+            .unwrap();
+        assert_eq!(desk.name().await.unwrap().as_deref(), Some("Desk 7412"));
+        assert!(desk.manufacturer_data().await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn rssi_is_reported_by_the_transport() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000).with_rssi(-62))
+            .await
+            .unwrap();
+        assert_eq!(desk.rssi().await.unwrap(), Some(-62));
+
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        assert_eq!(desk.rssi().await.unwrap(), None);
+    });
+}
+
+#[test]
+fn status_doesnt_read_the_firmware_revision_again() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        let started = Instant::now();
+        desk.status().await;
+        let without_firmware = started.elapsed();
+
+        let mock = MockDesk::new(7000).with_firmware_revision("4.0.1");
+        let desk = Idasen::new(mock).await.unwrap();
+        let started = Instant::now();
+        let status = desk.status().await;
+        assert_eq!(started.elapsed(), without_firmware);
+        assert_eq!(status.firmware_revision.as_deref(), Some("4.0.1"));
+    });
+}
+
+#[test]
+fn status_combines_position_speed_and_signal() {
+    run(async {
+        let desk = Arc::new(
+            Idasen::new(MockDesk::new(7000).with_rssi(-62))
+                .await
+                .unwrap(),
+        );
+        let status = desk.status().await;
+        assert!(status.connected);
+        assert_eq!(status.position, Some(7000));
+        assert_eq!(status.moving, None);
+        assert_eq!(status.rssi, Some(-62));
+
+        let moving = {
+            let desk = desk.clone();
+            tokio::spawn(async move { desk.move_to(9000).await })
+        };
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let status = desk.status().await;
+        assert_eq!(status.moving, Some(Direction::Up));
+        assert!(status.speed.unwrap() > 0);
+        moving.await.unwrap().unwrap();
+    });
+}
+
+#[test]
+fn diagnostics_describe_the_desk() {
+    run(async {
+        let mock = MockDesk::new(7000).with_firmware_revision("4.0.1");
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        mock.disconnect();
+        let diagnostics = Diagnostics::new().with_desk(&desk).await;
+
+        assert_eq!(diagnostics.firmware_revision.as_deref(), Some("4.0.1"));
+        assert_eq!(diagnostics.position.unwrap().position, 7000);
+        assert_eq!(diagnostics.counters.unwrap().reconnects, 1);
+        assert!(diagnostics.errors.is_empty());
+        assert!(diagnostics.to_string().contains("position: 7000"));
+    });
+}
+
+#[test]
+fn diagnose_jogs_the_desk_both_ways() {
+    run(async {
+        let mock = MockDesk::new(12000).with_rssi(-60);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let report = desk.diagnose().await;
+        assert_eq!(report.position, Some(12000));
+        assert!(report.jog_up.unwrap() > 100 && report.jog_down.unwrap() > 100);
+        assert!(report.is_healthy(-80));
+        assert!((mock.position() as i32 - 12000).abs() < 100);
+    });
+}
+
+#[test]
+fn diagnose_flags_a_desk_that_does_not_move() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000).with_write_loss(1.0))
+            .await
+            .unwrap();
+        let report = desk.diagnose().await;
+        assert_eq!(report.jog_up, Some(0));
+        assert!(report.stalled());
+        assert!(!report.is_healthy(-80));
+    });
+}
+
+#[test]
+fn read_latency_follows_the_adapter() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000).with_latency(Duration::from_millis(80)))
+            .await
+            .unwrap();
+        assert_eq!(desk.read_latency(), None);
+        for _ in 0..3 {
+            desk.read_position().await.unwrap();
+        }
+        let latency = desk.read_latency().unwrap();
+        assert!(latency >= Duration::from_millis(80) && latency < Duration::from_millis(100));
+    });
+}
+
+#[test]
+fn discovered_services_include_unused_characteristics() {
+    run(async {
+        let mock = MockDesk::new(7000).with_firmware_revision("4.0.1");
+        let desk = Idasen::new(mock).await.unwrap();
+        let services = desk.discovered_services();
+
+        assert_eq!(services.services.len(), 4);
+        let firmware = services.characteristic(FIRMWARE_REVISION_UUID).unwrap();
+        assert_eq!(firmware.properties, CharPropFlags::READ);
+        assert!(services.to_string().contains(&POSITION_UUID.to_string()));
+    });
+}
+
+#[test]
+fn position_stream_reports_manual_moves() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let positions = desk.position_stream().await.unwrap();
+        tokio::pin!(positions);
+
+        mock.set_position(9000);
+        assert_eq!(positions.next().await, Some(9000));
+    });
+}
+
+#[test]
+fn monitor_follows_the_desk() {
+    run(async {
+        let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+        let monitor = desk
+            .spawn_monitor()
+            .await
+            .unwrap()
+            .with_standing_above(9000);
+        assert_eq!(monitor.position(), 7000);
+        assert_eq!(monitor.posture(), Posture::Sitting);
+
+        desk.move_to(10000).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!((monitor.position() as i32 - 10000).abs() <= 10);
+        assert!(!monitor.is_moving());
+        assert_eq!(monitor.posture(), Posture::Standing);
+        monitor.stop();
+    });
+}
+
+#[test]
+fn monitor_reports_posture_changes_as_events() {
+    run(async {
+        let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+        let mut monitor = desk
+            .spawn_monitor()
+            .await
+            .unwrap()
+            .with_standing_above(9000);
+
+        desk.move_to(10000).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let events = monitor.next_events().await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0],
+            DeskEvent::PositionChanged { speed: 0, .. }
+        ));
+        assert_eq!(
+            events[1],
+            DeskEvent::PostureChanged {
+                posture: Posture::Standing
+            }
+        );
+        monitor.stop();
+    });
+}
+
+#[test]
+fn monitor_ignores_changes_below_delta() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+        let mut monitor = desk.spawn_monitor().await.unwrap();
+
+        mock.set_position(7020);
+        let small = tokio::time::timeout(Duration::from_secs(1), monitor.changed_by(50)).await;
+        assert!(small.is_err());
+
+        mock.set_position(7100);
+        let moved = monitor.changed_by(50).await.unwrap();
+        assert_eq!(moved.position, 7100);
+        monitor.stop();
+    });
+}
+
+#[test]
+fn move_to_with_reports_progress() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let mut positions = Vec::new();
+        let options = MoveOptions::new()
+            .tolerance(50)
+            .watchdog(Duration::from_secs(2))
+            .on_progress(|position, _| positions.push(position));
+        desk.move_to_with(8000, options).await.unwrap();
+
+        assert_eq!(positions.first(), Some(&7000));
+        assert!(positions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!((mock.position() as i32 - 8000).abs() <= 60);
+    });
+}
+
+#[test]
+fn move_to_with_stops_once_timed_out() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let options = MoveOptions::new().timeout(Some(Duration::from_secs(1)));
+        match desk.move_to_with(11000, options).await {
+            Err(Error::MoveTimeout { last_position }) => {
+                assert!(last_position > 7000 && last_position < 11000)
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(mock.position() < 11000);
+    });
+}
+
+#[test]
+fn hold_moves_until_released() {
+    run(async {
+        let mock = MockDesk::new(9000);
+        let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+        let hold = desk.hold_down();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        hold.release().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let released_at = mock.position();
+        assert!(released_at < 9000);
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(mock.position(), released_at);
+    });
+}
+
+// Events of the next update with the desk moving.
+async fn next_move(monitor: &mut Monitor) -> Vec<DeskEvent> {
+    loop {
+        let events = monitor.next_events().await.unwrap();
+        if matches!(events[0], DeskEvent::PositionChanged { speed, .. } if speed != 0) {
+            return events;
+        }
+    }
+}
+
+#[test]
+fn monitor_reports_the_desk_moved_by_hand() {
+    run(async {
+        let mock = MockDesk::new(9000);
+        let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+        let mut monitor = desk.spawn_monitor().await.unwrap();
+        let mut moves = Vec::new();
+        desk.up().await.unwrap();
+        moves.push(next_move(&mut monitor).await);
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        mock.press_paddle(false, Duration::from_secs(1));
+        moves.push(next_move(&mut monitor).await);
+
+        assert!(!moves[0]
+            .iter()
+            .any(|event| matches!(event, DeskEvent::MovedByHand { .. })));
+        assert!(moves[1].contains(&DeskEvent::MovedByHand {
+            direction: Direction::Down
+        }));
+    });
+}
+
+#[test]
+fn paddle_against_the_move_aborts_or_resumes_it() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let paddle = mock.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            paddle.press_paddle(false, Duration::from_secs(2));
+        });
+        let result = desk.move_to(11000).await;
+        assert!(matches!(result, Err(Error::ManualOverride)));
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(mock.position() < 7500);
+
+        let options = MoveOptions::new().on_override(OverridePolicy::Resume);
+        let paddle = mock.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            paddle.press_paddle(false, Duration::from_secs(2));
+        });
+        desk.move_to_with(9000, options).await.unwrap();
+        assert!((mock.position() as i32 - 9000).abs() <= 10);
+    });
+}
+
+#[test]
+fn move_to_with_adjusts_once_per_tick() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        let mut steps = Vec::new();
+        let options = MoveOptions::new()
+            .tick(Duration::from_millis(250))
+            .on_progress(|_, _| steps.push(Instant::now()));
+        desk.move_to_with(7500, options).await.unwrap();
+
+        assert!(steps.len() > 2);
+        assert!(steps
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] >= Duration::from_millis(250)));
+    });
+}
+
+#[test]
+fn profile_predicts_the_stop_point() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let profile = desk.profile().await.unwrap();
+        for &target in [8000, 7400, 6530].iter() {
+            let options = MoveOptions::new().tolerance(5).profile(profile);
+            desk.move_to_with(target, options).await.unwrap();
+            assert!((mock.position() as i32 - target as i32).abs() <= 5);
+        }
+    });
+}
+
+#[test]
+fn moves_use_the_known_profile() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.profile().await.unwrap();
+        for &target in [9500, 7420, 7000].iter() {
+            desk.move_to(target).await.unwrap();
+            assert!((mock.position() as i32 - target as i32).abs() <= 10);
+        }
+    });
+}
+
+#[test]
+fn rate_limit_refuses_rapid_moves() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        desk.limit_rate(RateLimit {
+            min_interval: Duration::from_secs(10),
+            max_per_minute: 2,
+        });
+        desk.move_to(7100).await.unwrap();
+        assert!(matches!(desk.move_to(7200).await, Err(Error::CoolingDown)));
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        desk.move_to(7200).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(matches!(desk.move_to(7300).await, Err(Error::CoolingDown)));
+
+        tokio::time::sleep(Duration::from_secs(40)).await;
+        desk.move_to(7300).await.unwrap();
+        desk.lift_rate_limit();
+        desk.move_to(7400).await.unwrap();
+    });
+}
+
+#[test]
+fn rate_limit_counts_moves_started_by_up_and_down() {
+    run(async {
+        let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+        desk.limit_rate(RateLimit {
+            min_interval: Duration::from_secs(10),
+            max_per_minute: 10,
+        });
+        let config = Config::default();
+        desk.execute(DeskCommand::Up, &config).await.unwrap();
+        desk.execute(DeskCommand::Up, &config).await.unwrap();
+        desk.stop().await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(matches!(
+            desk.execute(DeskCommand::Up, &config).await,
+            Err(Error::CoolingDown)
+        ));
+        assert!(matches!(desk.down().await, Err(Error::CoolingDown)));
+
+        // Holding a button repeats `up`, only the first one starts a move.
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let hold = desk.hold_up();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        hold.release().await.unwrap();
+    });
+}
+
+#[test]
+fn percent_maps_onto_the_restricted_travel() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.move_to_percent(50.0).await.unwrap();
+        assert!((mock.position() as i32 - 9450).abs() <= 10);
+        assert!((desk.position_percent().await.unwrap() - 50.0).abs() < 0.5);
+
+        desk.restrict(Restriction {
+            range: 7000..=8000,
+            cooldown: Duration::from_secs(0),
+        });
+        assert_eq!(desk.position_percent().await.unwrap(), 100.0);
+        desk.move_to_percent(20.0).await.unwrap();
+        assert!((mock.position() as i32 - 7200).abs() <= 10);
+        assert!(matches!(
+            desk.move_to_percent(101.0).await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+    });
+}
+
+#[test]
+fn wide_braking_margin_still_reaches_target() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let options = MoveOptions::new().braking_margin(200, Duration::from_secs(1));
+        desk.move_to_with(8000, options).await.unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+    });
+}
+
+#[test]
+fn travel_time_estimate_matches_the_move() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        desk.profile().await.unwrap();
+        let estimate = desk.estimate_travel_time(9000).await.unwrap();
+
+        let started = Instant::now();
+        desk.move_to(9000).await.unwrap();
+        let took = started.elapsed();
+        assert!(took > estimate, "{:?} {:?}", took, estimate);
+        assert!(
+            took - estimate < Duration::from_secs(2),
+            "{:?} {:?}",
+            took,
+            estimate
+        );
+    });
+}
+
+#[test]
+fn cached_position_is_reused_until_too_old() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let mut desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.cache_position(Duration::from_millis(500));
+        assert_eq!(desk.position().await.unwrap(), 7000);
+
+        mock.set_position(7100);
+        assert_eq!(desk.position().await.unwrap(), 7000);
+        assert_eq!(desk.read_position().await.unwrap(), 7100);
+
+        mock.set_position(7200);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(desk.position().await.unwrap(), 7200);
+    });
+}
+
+#[test]
+fn cached_position_is_fed_by_notifications() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let stream = desk.position_stream().await.unwrap();
+        tokio::pin!(stream);
+        mock.set_position(7100);
+        assert_eq!(stream.next().await, Some(7100));
+
+        mock.set_position(7200);
+        let max_age = Duration::from_secs(1);
+        assert_eq!(desk.position_cached(max_age).await.unwrap(), 7100);
+        tokio::time::sleep(max_age * 2).await;
+        assert_eq!(desk.position_cached(max_age).await.unwrap(), 7200);
+    });
+}
+
+#[test]
+fn load_factor_compares_moves_with_the_profile() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        desk.move_to(8000).await.unwrap();
+        assert_eq!(desk.load_factor(), None);
+
+        desk.profile().await.unwrap();
+        desk.move_to(7000).await.unwrap();
+        let load = desk.load_factor().unwrap();
+        assert!((0.9..=1.1).contains(&load), "{}", load);
+    });
+}
+
+#[test]
+fn calibrated_positions_match_the_measurement() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.calibrate(7300).await.unwrap();
+        assert_eq!(desk.position().await.unwrap(), 7300);
+
+        desk.move_to(8300).await.unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+        assert_eq!(desk.height_range(), MIN_HEIGHT + 300..=MAX_HEIGHT + 300);
+        // Calibrating again replaces the offset instead of adding to it.
+        desk.calibrate(mock.position()).await.unwrap();
+        assert_eq!(desk.calibration().offset, 0);
+    });
+}
+
+#[test]
+fn measured_range_limits_moves() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.calibrate(7300).await.unwrap();
+        let range = desk.measure_range().await.unwrap();
+        assert_eq!(range, MIN_HEIGHT..=MAX_HEIGHT);
+        assert_eq!(mock.position(), MAX_HEIGHT);
+
+        desk.use_range(7000..=10000);
+        assert_eq!(desk.height_range(), 7300..=10300);
+        assert!(matches!(
+            desk.move_to(10500).await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+    });
+}
+
+#[test]
+fn restriction_keeps_moves_in_the_band() {
+    run(async {
+        let mock = MockDesk::new(8000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let restriction = desk
+            .restrict_around(500, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(restriction.range, 7500..=8500);
+
+        assert!(matches!(
+            desk.move_to(9000).await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+        desk.move_to(8400).await.unwrap();
+        assert!(matches!(desk.move_to(8000).await, Err(Error::CoolingDown)));
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        mock.set_position(8500);
+        desk.position().await.unwrap();
+        assert!(matches!(
+            desk.up().await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+        desk.move_to(8000).await.unwrap();
+
+        desk.lift_restriction();
+        desk.move_to(9000).await.unwrap();
+    });
+}
+
+#[test]
+fn commands_are_checked_against_the_config() {
+    run(async {
+        let mock = MockDesk::new(8000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let config = Config {
+            min_height: 7000,
+            max_height: 10000,
+            ..Config::default()
+        };
+
+        let position = desk
+            .execute(DeskCommand::MoveBy(-500), &config)
+            .await
+            .unwrap();
+        assert!((position as i32 - 7500).abs() <= 10);
+        assert!(matches!(
+            desk.execute(DeskCommand::MoveTo(11000), &config).await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+        assert!(matches!(
+            desk.execute(DeskCommand::Preset("stand".to_string()), &config).await,
+            Err(Error::UnknownPreset(name)) if name == "stand"
+        ));
+
+        mock.set_position(10000);
+        assert!(matches!(
+            desk.execute(DeskCommand::Up, &config).await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+        assert_eq!(
+            desk.execute(DeskCommand::Position, &config).await.unwrap(),
+            10000
+        );
+    });
+}
+
+#[test]
+fn group_moves_all_desks_within_its_limits() {
+    run(async {
+        let second_address = BDAddr::from([0xEC, 0x86, 0xF6, 0x00, 0x00, 0x02]);
+        let second = MockDesk::new(9000).with_address(second_address);
+        let first = MockDesk::new(7000);
+        let config = Config {
+            max_height: 10000,
+            ..Config::default()
+        };
+        let mut group = DeskGroup::new("room", config);
+        group.add(Idasen::new(first.clone()).await.unwrap().spawn());
+        group.add(Idasen::new(second.clone()).await.unwrap().spawn());
+        assert!(!group.status().await.is_level(20));
+
+        assert!(matches!(
+            group.move_to(11000).await,
+            Err(Error::PositionNotInRange { .. })
+        ));
+        let results = group.move_to(8000).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert!((first.position() as i32 - 8000).abs() <= 10);
+        assert!((second.position() as i32 - 8000).abs() <= 10);
+
+        let status = group.status().await;
+        assert_eq!(status.reachable(), 2);
+        assert!(status.is_level(20));
+
+        let status = group.desk(second_address).unwrap().status().await.unwrap();
+        assert_eq!(status.mac_addr, second_address);
+    });
+}
+
+#[test]
+fn cannot_find_device_tells_what_was_searched() {
+    run(async {
+        let result = connect_first(Vec::<MockDesk>::new()).await;
+        assert!(matches!(
+            result,
+            Err(Error::CannotFindDevice { scan: None })
+        ));
+    });
+
+    let error = Error::CannotFindDevice {
+        scan: Some(ScanSummary {
+            adapters: 2,
+            mac: Some(BDAddr::from([0xEC, 0x86, 0xF6, 0, 0, 1])),
+            name: Some("Desk 7412".to_string()),
+            duration: Duration::from_secs(2),
+            peripherals_seen: 14,
+        }),
+    };
+    assert_eq!(
+        error.to_string(),
+        "Cannot find the device, searched 2 adapter(s) for 2.0 s and saw 14 peripheral(s), \
+         looking for EC:86:F6:00:00:01, looking for 'Desk 7412'."
+    );
+}
+
+#[test]
+fn connect_first_disconnects_the_other_candidates() {
+    run(async {
+        let candidates: Vec<_> = (1..=3)
+            .map(|index| {
+                MockDesk::new(7000).with_address(BDAddr::from([0xEC, 0x86, 0xF6, 0, 0, index]))
+            })
+            .collect();
+        let desk = connect_first(candidates.clone()).await.unwrap();
+        let connected: Vec<_> = candidates
+            .iter()
+            .filter(|mock| mock.is_connected())
+            .collect();
+        assert_eq!(connected.len(), 1);
+        assert_eq!(DeskTransport::address(connected[0]), desk.mac_addr);
+    });
+}
+
+#[test]
+fn dropping_the_desk_stops_and_disconnects_it() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.up().await.unwrap();
+        drop(desk);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(mock.position() < 7050);
+        assert!(!mock.is_connected());
+    });
+}
+
+#[test]
+fn lost_writes_never_reach_the_desk() {
+    run(async {
+        let mock = MockDesk::new(7000).with_write_loss(1.0);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.up().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(mock.position(), 7000);
+    });
+}
+
+#[test]
+fn typed_commands_move_the_desk() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.r#move(Direction::Up).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        desk.send(Command::Stop).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let stopped = mock.position();
+        assert!(stopped > 7000);
+        assert!(!mock.is_moving());
+
+        desk.send(Command::Down).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(mock.position() < stopped);
+    });
+}
+
+#[test]
+fn telemetry_adds_up_travel_and_turns() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        desk.move_to(8000).await.unwrap();
+        desk.move_to(7500).await.unwrap();
+        let telemetry = desk.telemetry();
+        assert!((1450..=1550).contains(&telemetry.distance));
+        assert!(telemetry.direction_changes >= 1);
+
+        desk.reset_telemetry();
+        assert_eq!(desk.telemetry().distance, 0);
+    });
+}
+
+#[test]
+fn recorded_routine_replays_the_same_moves() {
+    run(async {
+        let desk = Idasen::new(MockDesk::new(7000)).await.unwrap();
+        let recording = Recording::start(&desk).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        desk.move_to(8000).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        desk.move_to(7500).await.unwrap();
+        let routine = recording.stop().await.unwrap();
+        let positions: Vec<_> = routine.steps.iter().map(|step| step.position).collect();
+        assert_eq!(positions.len(), 3);
+        assert!((positions[1] as i32 - 8000).abs() <= 10);
+        assert!((positions[2] as i32 - 7500).abs() <= 10);
+        assert_eq!(routine.to_string().parse::<Routine>().unwrap(), routine);
+
+        let mock = MockDesk::new(9000);
+        let replica = Idasen::new(mock.clone()).await.unwrap();
+        let start = Instant::now();
+        replica.replay(&routine).await.unwrap();
+        assert!(start.elapsed() >= routine.duration());
+        assert!((mock.position() as i32 - positions[2] as i32).abs() <= 10);
+    });
+}
+
+#[test]
+fn move_within_the_deadband_is_skipped() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        assert!(!desk.move_to_if_needed(7020, 30).await.unwrap());
+        assert_eq!(mock.position(), 7000);
+
+        assert!(desk.move_to_if_needed(7500, 30).await.unwrap());
+        assert!((mock.position() as i32 - 7500).abs() <= 10);
+    });
+}
+
+#[test]
+fn moves_poll_the_position_without_notifications() {
+    run(async {
+        let mock = MockDesk::new(7000).without_notifications();
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        assert!(!desk.has_position_notifications());
+        desk.move_to(8000).await.unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+        assert!(matches!(
+            desk.position_stream().await,
+            Err(Error::CannotSubscribePosition)
+        ));
+    });
+}
+
+#[test]
+fn acknowledged_writes_survive_a_lossy_connection() {
+    run(async {
+        let mock = MockDesk::new(7000).with_write_loss(1.0);
+        let mut desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.write_type(WriteType::WithResponse);
+        desk.move_to(8000).await.unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+    });
+}
+
+#[test]
+fn notifications_arrive_late_on_a_slow_adapter() {
+    run(async {
+        let mock = MockDesk::new(7000)
+            .with_notification_latency(Duration::from_millis(300), Duration::from_millis(50));
+        let desk = Idasen::new(mock).await.unwrap();
+        let notifications = desk.position_and_speed_stream().await.unwrap();
+        tokio::pin!(notifications);
+
+        let start = Instant::now();
+        notifications.next().await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed <= Duration::from_millis(350));
+    });
+}
+
+#[test]
+fn move_to_reaches_target_with_all_notifications_lost() {
+    run(async {
+        let mock = MockDesk::new(7000).with_packet_loss(1.0);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(20), desk.move_to(8000))
+            .await
+            .expect("move_to did not finish")
+            .unwrap();
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+    });
+}
+
+#[test]
+fn slow_approach_ends_at_rest_without_overshooting() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let mut highest = 0;
+        let options = MoveOptions::new()
+            .slow_approach(300)
+            .on_progress(|position, _| highest = highest.max(position));
+        tokio::time::timeout(Duration::from_secs(60), desk.move_to_with(8000, options))
+            .await
+            .expect("move_to_with did not finish")
+            .unwrap();
+        assert!(highest <= 8010);
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+        assert!(!mock.is_moving());
+    });
+}
+
+#[test]
+fn status_reports_position_firmware_and_last_error() {
+    run(async {
+        let mock = MockDesk::new(11000).with_firmware_revision("4.2.0");
+        let desk = Idasen::new(mock).await.unwrap().spawn();
+        assert!(desk.move_to(20000).await.is_err());
+
+        let status = desk.status().await.unwrap();
+        assert!(status.connected);
+        assert_eq!(status.position, Some(11000));
+        assert_eq!(status.posture, Some(Posture::Standing));
+        assert_eq!(status.firmware_revision.as_deref(), Some("4.2.0"));
+        assert_eq!(
+            status.last_error,
+            Some(
+                Error::PositionNotInRange {
+                    requested: 20000,
+                    min: MIN_HEIGHT,
+                    max: MAX_HEIGHT
+                }
+                .to_string()
+            )
+        );
+    });
+}
+
+#[test]
+fn idle_desk_is_disconnected_and_reconnected_on_demand() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+        desk.spawn_idle_disconnect(Duration::from_secs(60));
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(desk.position().await.unwrap(), 7000);
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert!(mock.is_connected());
+
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert!(!mock.is_connected());
+        desk.move_to(8000).await.unwrap();
+        assert!(mock.is_connected());
+        assert!((mock.position() as i32 - 8000).abs() <= 10);
+    });
+}
+
+#[test]
+fn relative_moves_stay_in_range() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.move_up_by(300).await.unwrap();
+        assert!((mock.position() as i32 - 7300).abs() <= 10);
+
+        desk.move_down_by(2000).await.unwrap();
+        assert!((mock.position() as i32 - MIN_HEIGHT as i32).abs() <= 10);
+    });
+}
+
+#[test]
+fn native_move_lets_the_desk_drive_to_the_target() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        desk.move_to_native(9000).await.unwrap();
+        assert_eq!(mock.position(), 9000);
+        assert!(!mock.is_moving());
+
+        desk.move_to_native(6500).await.unwrap();
+        assert_eq!(mock.position(), 6500);
+    });
+}
+
+#[test]
+fn started_move_reports_its_status() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+        let moving = desk.start_move_to(8000);
+        assert_eq!(moving.status(), MoveStatus::Moving);
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(moving.status(), MoveStatus::Reached);
+        moving.cancel();
+        assert_eq!(moving.status(), MoveStatus::Reached);
+        moving.wait().await.unwrap();
+
+        let moving = desk.start_move_to(12000);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        moving.cancel();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!mock.is_moving());
+        assert!(matches!(moving.wait().await, Err(Error::MoveInterrupted)));
+    });
+}
+
+#[test]
+fn dropped_move_stops_the_desk() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap();
+        let moving = tokio::time::timeout(Duration::from_secs(1), desk.move_to(12000));
+        assert!(moving.await.is_err());
+        assert!(mock.is_moving());
+
+        // The desk would keep going for half a second after the last command.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!mock.is_moving());
+        let stopped_at = mock.position();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(mock.position(), stopped_at);
+    });
+}
+
+#[test]
+fn dropped_handle_move_stops_the_desk() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Idasen::new(mock.clone()).await.unwrap().spawn();
+        let moving = tokio::time::timeout(Duration::from_secs(1), desk.move_to(12000));
+        assert!(moving.await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!mock.is_moving());
+        assert!(mock.position() < 11000);
+    });
+}
+
+#[test]
+fn dropped_connect_disconnects() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let connecting = tokio::time::timeout(Duration::from_millis(10), Idasen::new(mock.clone()));
+        assert!(connecting.await.is_err());
+        assert!(mock.is_connected());
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!mock.is_connected());
+    });
+}
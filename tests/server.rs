@@ -0,0 +1,128 @@
+#![cfg(all(feature = "server", feature = "mock"))]
+
+use idasen::mock::MockDesk;
+use idasen::server::Server;
+use idasen::{Config, Idasen};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+fn run<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+async fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> String {
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    );
+    send(address, &request).await
+}
+
+async fn send(address: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[test]
+fn server_reads_and_moves_the_desk() {
+    run(async {
+        let mock = MockDesk::new(7000);
+        let desk = Arc::new(Idasen::new(mock.clone()).await.unwrap());
+        let mut config = Config::default();
+        config.presets.push(("stand".to_string(), 11000));
+        let server = Server::bind(0).await.unwrap();
+        let address = server.local_addr().unwrap();
+        tokio::spawn(server.run(desk, config));
+
+        let response = request(address, "GET", "/position", "").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n7000\n"));
+
+        let response = request(address, "POST", "/move_to", "9000").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!((mock.position() as i32 - 9000).abs() <= 10);
+
+        request(address, "POST", "/preset/stand", "").await;
+        assert!((mock.position() as i32 - 11000).abs() <= 10);
+    });
+}
+
+#[test]
+fn server_rejects_invalid_requests() {
+    run(async {
+        let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+        let server = Server::bind(0).await.unwrap();
+        let address = server.local_addr().unwrap();
+        tokio::spawn(server.run(desk, Config::default()));
+
+        let response = request(address, "POST", "/move_to", "20000").await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        let response = request(address, "POST", "/move_to", "high").await;
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        let response = request(address, "POST", "/preset/stand", "").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let response = request(address, "DELETE", "/position", "").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    });
+}
+
+#[test]
+fn server_only_answers_local_requests() {
+    run(async {
+        let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+        let server = Server::bind(0).await.unwrap();
+        let address = server.local_addr().unwrap();
+        tokio::spawn(server.run(desk, Config::default()));
+
+        let response = send(
+            address,
+            "GET /position HTTP/1.1\r\nHost: 127.0.0.1:8080\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let response = send(
+            address,
+            "GET /position HTTP/1.1\r\nHost: evil.example\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        let response = send(address, "GET /position HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        let request =
+            "POST /stop HTTP/1.1\r\nHost: localhost\r\nOrigin: http://evil.example\r\n\r\n";
+        let response = send(address, request).await;
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    });
+}
+
+#[test]
+fn server_refuses_slow_and_long_requests() {
+    run(async {
+        let desk = Arc::new(Idasen::new(MockDesk::new(7000)).await.unwrap());
+        let server = Server::bind(0).await.unwrap();
+        let address = server.local_addr().unwrap();
+        tokio::spawn(server.run(desk, Config::default()));
+
+        // Fits the limit on its own, but not together with the headers.
+        let request = "POST /move_to HTTP/1.1\r\nHost: localhost\r\nContent-Length: 8180\r\n\r\n";
+        let response = send(address, request).await;
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        let response = send(address, "GET /position HTTP/1.1\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    });
+}
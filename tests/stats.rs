@@ -0,0 +1,39 @@
+use idasen::schedule::{Date, FixedOffset};
+use idasen::{Posture, UsageStats};
+use std::time::{Duration, UNIX_EPOCH};
+
+const HOUR: u64 = 3600;
+
+#[test]
+fn usage_is_split_at_local_midnight() {
+    // 22:00 local in UTC+2 on 1970-01-01, standing until 02:00 the next day.
+    let mut stats = UsageStats::new()
+        .with_standing_above(9000)
+        .timezone(FixedOffset(2 * 3600));
+    let evening = UNIX_EPOCH + Duration::from_secs(20 * HOUR);
+    stats.record(11000, evening);
+    stats.record(7000, evening + Duration::from_secs(4 * HOUR));
+    assert_eq!(stats.posture(), Some(Posture::Sitting));
+
+    let first = stats.day(Date::new(1970, 1, 1).unwrap());
+    assert_eq!(first.standing, Duration::from_secs(2 * HOUR));
+    let second = stats.day(Date::new(1970, 1, 2).unwrap());
+    assert_eq!(second.standing, Duration::from_secs(2 * HOUR));
+    assert_eq!(second.sitting, Duration::default());
+    assert_eq!(stats.days().len(), 2);
+}
+
+#[test]
+fn going_back_in_time_counts_nothing() {
+    let mut stats = UsageStats::new();
+    let noon = UNIX_EPOCH + Duration::from_secs(12 * HOUR);
+    stats.record(7000, noon);
+    stats.record(7000, noon - Duration::from_secs(HOUR));
+    assert!(stats.days().is_empty());
+
+    stats.record(7000, noon);
+    assert_eq!(
+        stats.day(Date::new(1970, 1, 1).unwrap()).sitting,
+        Duration::from_secs(HOUR)
+    );
+}
@@ -0,0 +1,44 @@
+use idasen::{
+    Calibration, Config, DirectionProfile, FileStorage, LastKnownPosition, Profile, Storage,
+};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn file_storage_keeps_each_value_in_its_own_file() {
+    let dir = std::env::temp_dir().join("idasen-storage-test");
+    let storage = FileStorage::new(&dir);
+    assert_eq!(storage.read("profile").unwrap(), None);
+
+    let mut config = Config::default();
+    config.presets.push(("sit".to_string(), 7200));
+    let calibration = Calibration::from_measurement(6200, 6450);
+    let direction = DirectionProfile {
+        speed: 380,
+        acceleration: 1900,
+        stop_distance: 20,
+    };
+    let profile = Profile {
+        up: direction,
+        down: direction,
+        at: UNIX_EPOCH + Duration::from_secs(1000),
+    };
+    let last = LastKnownPosition {
+        position: 9100,
+        at: UNIX_EPOCH + Duration::from_secs(2000),
+    };
+
+    config.save_to(&storage).unwrap();
+    calibration.save_to(&storage).unwrap();
+    profile.save_to(&storage).unwrap();
+    last.save_to(&storage).unwrap();
+
+    assert_eq!(
+        Config::load(dir.join("config.toml")).unwrap(),
+        Some(config.clone())
+    );
+    assert_eq!(Config::load_from(&storage).unwrap(), Some(config));
+    assert_eq!(Calibration::load_from(&storage).unwrap(), Some(calibration));
+    assert_eq!(Profile::load_from(&storage).unwrap(), Some(profile));
+    assert_eq!(LastKnownPosition::load_from(&storage).unwrap(), Some(last));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,75 @@
+use idasen::trace::{Replay, Trace, TraceEvent};
+use idasen::{BDAddr, Error, Idasen, CONTROL_UUID, MIN_HEIGHT, POSITION_UUID, UP};
+use std::future::Future;
+
+fn run<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn trace() -> Trace {
+    Trace {
+        address: BDAddr::from([1, 2, 3, 4, 5, 6]),
+        events: vec![
+            TraceEvent::Characteristic { uuid: CONTROL_UUID },
+            TraceEvent::Characteristic {
+                uuid: POSITION_UUID,
+            },
+            TraceEvent::Read {
+                uuid: POSITION_UUID,
+                value: vec![0xE8, 0x03, 0x00, 0x00],
+            },
+            TraceEvent::Write {
+                uuid: CONTROL_UUID,
+                value: UP.to_vec(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn trace_survives_text_round_trip() {
+    let trace = trace();
+    assert_eq!(trace.to_string().parse::<Trace>().unwrap(), trace);
+    assert!("address nonsense\n".parse::<Trace>().is_err());
+}
+
+#[test]
+fn replay_answers_recorded_reads_then_runs_out() {
+    run(async {
+        let desk = Idasen::new(Replay::new(trace())).await.unwrap();
+        assert_eq!(desk.mac_addr, BDAddr::from([1, 2, 3, 4, 5, 6]));
+        assert_eq!(desk.position().await.unwrap(), MIN_HEIGHT + 1000);
+        assert!(matches!(desk.position().await, Err(Error::TraceExhausted)));
+    });
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn recorder_captures_traffic_for_replay() {
+    use idasen::mock::MockDesk;
+    use idasen::trace::Recorder;
+
+    let path = std::env::temp_dir().join("idasen-recorder-test.trace");
+    let recorded = run(async {
+        let recorder = Recorder::create(MockDesk::new(7000), &path).unwrap();
+        let desk = Idasen::new(recorder).await.unwrap();
+        let position = desk.position().await.unwrap();
+        desk.up().await.unwrap();
+        position
+    });
+
+    let trace = Trace::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(trace.writes(CONTROL_UUID), vec![UP.to_vec()]);
+
+    let replay = Replay::new(trace);
+    run(async {
+        let desk = Idasen::new(replay).await.unwrap();
+        assert_eq!(desk.position().await.unwrap(), recorded);
+    });
+}
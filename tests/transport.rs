@@ -0,0 +1,251 @@
+use async_trait::async_trait;
+use idasen::{
+    connect_first, AdapterSwitch, BDAddr, DeskTransport, Error, Failover, Idasen,
+    NotificationStream, ReconnectPolicy, WriteType, CONTROL_UUID, DOWN, MIN_HEIGHT, POSITION_UUID,
+    UP, WAKE_UP,
+};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+// Minimal fake written the way a downstream crate would, using only the public API.
+#[derive(Default)]
+struct FakeDesk {
+    writes: Arc<Mutex<Vec<Vec<u8>>>>,
+    // Some other peripheral matching the search.
+    not_a_desk: bool,
+    // The adapter it is reached through has gone away.
+    adapter_gone: Arc<AtomicBool>,
+    // The connection was lost, until connecting succeeds.
+    lost: Arc<AtomicBool>,
+    // Connection attempts still to fail.
+    failing_connects: Arc<AtomicU32>,
+    // Subscribing needs an encrypted link.
+    needs_pairing: bool,
+}
+
+impl FakeDesk {
+    fn check_adapter(&self) -> Result<(), Error> {
+        if self.adapter_gone.load(Ordering::SeqCst) {
+            return Err(Error::BtlePlugError(btleplug::Error::DeviceNotFound));
+        }
+        if self.lost.load(Ordering::SeqCst) {
+            return Err(Error::BtlePlugError(btleplug::Error::NotConnected));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeskTransport for FakeDesk {
+    fn address(&self) -> BDAddr {
+        BDAddr::from([1, 2, 3, 4, 5, 6])
+    }
+
+    async fn connect(&self) -> Result<(), Error> {
+        let failing = self.failing_connects.load(Ordering::SeqCst);
+        if failing > 0 {
+            self.failing_connects.store(failing - 1, Ordering::SeqCst);
+            return Err(Error::BtlePlugError(btleplug::Error::DeviceNotFound));
+        }
+        self.lost.store(false, Ordering::SeqCst);
+        self.check_adapter()
+    }
+
+    fn has_characteristic(&self, uuid: Uuid) -> bool {
+        !self.not_a_desk && (uuid == CONTROL_UUID || uuid == POSITION_UUID)
+    }
+
+    async fn read(&self, _uuid: Uuid) -> Result<Vec<u8>, Error> {
+        self.check_adapter()?;
+        // 1000 tenth mm above the lowest position, not moving.
+        Ok(vec![0xE8, 0x03, 0x00, 0x00])
+    }
+
+    async fn write(&self, _uuid: Uuid, data: &[u8], _write_type: WriteType) -> Result<(), Error> {
+        self.check_adapter()?;
+        self.writes.lock().unwrap().push(data.to_vec());
+        Ok(())
+    }
+
+    async fn subscribe(&self, _uuid: Uuid) -> Result<(), Error> {
+        if self.needs_pairing {
+            return Err(Error::PairingRequired);
+        }
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, Error> {
+        Ok(Box::pin(tokio_stream::empty()))
+    }
+}
+
+#[test]
+fn fake_transport_drives_idasen() {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let fake = FakeDesk::default();
+            let writes = fake.writes.clone();
+            let desk = Idasen::new(fake).await.unwrap();
+            assert_eq!(desk.mac_addr, BDAddr::from([1, 2, 3, 4, 5, 6]));
+            assert_eq!(desk.position().await.unwrap(), MIN_HEIGHT + 1000);
+
+            desk.up().await.unwrap();
+            assert_eq!(*writes.lock().unwrap(), vec![UP.to_vec()]);
+        });
+}
+
+#[test]
+fn idle_desk_is_woken_up_before_moving() {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(async {
+            let fake = FakeDesk::default();
+            let writes = fake.writes.clone();
+            let mut desk = Idasen::new(fake).await.unwrap();
+
+            tokio::time::sleep(Duration::from_secs(120)).await;
+            desk.up().await.unwrap();
+            desk.down().await.unwrap();
+            assert_eq!(
+                *writes.lock().unwrap(),
+                vec![WAKE_UP.to_vec(), UP.to_vec(), DOWN.to_vec()]
+            );
+
+            writes.lock().unwrap().clear();
+            desk.wake_up_after(None);
+            tokio::time::sleep(Duration::from_secs(120)).await;
+            desk.up().await.unwrap();
+            assert_eq!(*writes.lock().unwrap(), vec![UP.to_vec()]);
+        });
+}
+
+#[test]
+fn connect_first_skips_peripherals_that_are_no_desk() {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let other = || FakeDesk {
+                not_a_desk: true,
+                ..FakeDesk::default()
+            };
+            let desk = connect_first(vec![other(), FakeDesk::default(), other()]).await;
+            assert!(desk.is_ok());
+
+            let desk = connect_first(vec![other()]).await;
+            assert!(matches!(desk, Err(Error::CharacteristicsNotFound(_))));
+        });
+}
+
+#[test]
+fn failover_switches_to_another_adapter() {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let (first, second) = (FakeDesk::default(), FakeDesk::default());
+            let adapter_gone = first.adapter_gone.clone();
+            let writes = second.writes.clone();
+            let (failover, mut switches) = Failover::new(vec![first, second]);
+            let desk = Idasen::new(failover).await.unwrap();
+            assert!(switches.try_recv().is_err());
+
+            adapter_gone.store(true, Ordering::SeqCst);
+            assert_eq!(desk.position().await.unwrap(), MIN_HEIGHT + 1000);
+            assert_eq!(
+                switches.try_recv().unwrap(),
+                AdapterSwitch { from: 0, to: 1 }
+            );
+
+            desk.up().await.unwrap();
+            assert_eq!(*writes.lock().unwrap(), vec![UP.to_vec()]);
+        });
+}
+
+#[test]
+fn reconnect_policy_retries_with_backoff() {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(async {
+            let fake = FakeDesk::default();
+            let (lost, failing_connects) = (fake.lost.clone(), fake.failing_connects.clone());
+            let mut desk = Idasen::new(fake).await.unwrap();
+
+            // The default policy tries once.
+            lost.store(true, Ordering::SeqCst);
+            failing_connects.store(1, Ordering::SeqCst);
+            assert!(desk.position().await.is_err());
+            assert!(!desk.is_connected());
+            desk.reconnect().await.unwrap();
+            assert!(desk.is_connected());
+
+            lost.store(true, Ordering::SeqCst);
+            failing_connects.store(2, Ordering::SeqCst);
+            desk.reconnect_policy(ReconnectPolicy {
+                attempts: 3,
+                backoff: Duration::from_secs(1),
+            });
+            let started = tokio::time::Instant::now();
+            assert_eq!(desk.position().await.unwrap(), MIN_HEIGHT + 1000);
+            assert_eq!(started.elapsed(), Duration::from_secs(3));
+            assert!(desk.is_connected());
+            assert_eq!(desk.error_counters().reconnects, 2);
+
+            lost.store(true, Ordering::SeqCst);
+            failing_connects.store(3, Ordering::SeqCst);
+            match desk.position().await {
+                Err(Error::RetriesExhausted { attempts: 3, .. }) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        });
+}
+
+#[test]
+fn connect_with_retries_the_first_connect() {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(async {
+            let fake = FakeDesk::default();
+            fake.failing_connects.store(2, Ordering::SeqCst);
+            let policy = ReconnectPolicy {
+                attempts: 3,
+                backoff: Duration::from_millis(100),
+            };
+            let desk = Idasen::connect_with(fake, policy).await.unwrap();
+            assert_eq!(desk.position().await.unwrap(), MIN_HEIGHT + 1000);
+
+            let fake = FakeDesk {
+                not_a_desk: true,
+                ..FakeDesk::default()
+            };
+            let started = tokio::time::Instant::now();
+            assert!(matches!(
+                Idasen::connect_with(fake, policy).await,
+                Err(Error::CharacteristicsNotFound(_))
+            ));
+            assert_eq!(started.elapsed(), Duration::ZERO);
+
+            let fake = FakeDesk {
+                needs_pairing: true,
+                ..FakeDesk::default()
+            };
+            assert!(matches!(
+                Idasen::connect_with(fake, policy).await,
+                Err(Error::PairingRequired)
+            ));
+            assert_eq!(started.elapsed(), Duration::ZERO);
+        });
+}